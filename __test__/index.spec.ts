@@ -1,7 +1,124 @@
 import test from 'ava'
 
-import { setupProtocol } from '../index'
+import { setupProtocol, Player, Table } from '../index'
 
 test('sync function from native code', (t) => {
   t.is(setupProtocol(2 , 5), 10)
 })
+
+test('players peek at their own cards and open a community card', (t) => {
+  const table = new Table(2, 26)
+  const players = ['Andrija', 'Kobi', 'Nico'].map((name) => new Player(table, name))
+  const seats = players.map((player) => table.join(player))
+  t.deepEqual(seats, [0, 1, 2])
+
+  table.start()
+  t.is(table.deckSize, 52)
+
+  const shuffle = table.shuffle()
+  const otherTable = new Table(2, 26)
+  t.throws(() => otherTable.applyShuffle(shuffle))
+
+  players.forEach((player, seat) => table.deal(player, seat, seat))
+  t.is(table.owner(1), 1)
+  t.is(table.owner(10), null)
+  t.throws(() => table.deal(players[0], 0, 1))
+  t.throws(() => table.deal(players[0], players.length, 10))
+
+  players.forEach((player, seat) => {
+    const tokens = players.filter((other) => other !== player).map((other) => other.computeRevealToken(table, seat))
+    const card = player.peekAtCard(table, seat, tokens)
+    t.deepEqual(player.openedCards(), [card])
+  })
+
+  const communityIndex = players.length
+  const tokens = players.map((player) => player.computeRevealToken(table, communityIndex))
+  const card = table.openCard(communityIndex, tokens)
+  t.is(table.openedCards()[communityIndex], card)
+  t.is(table.openedCards()[0], null)
+})
+
+test('rebuilds a table from the bytes another player exported', (t) => {
+  const table = new Table(2, 26)
+  const copy = Table.fromExport(table.exportParameters(), table.exportCards())
+  const players = ['Andrija', 'Kobi'].map((name) => new Player(table, name))
+  players.forEach((player) => {
+    table.join(player)
+    copy.join(player)
+  })
+  table.start()
+  copy.start()
+
+  // Each table verifies the shuffle made on the other one
+  copy.applyShuffle(table.shuffle())
+  table.applyShuffle(copy.shuffle())
+  t.is(copy.deckSize, 52)
+
+  const tokens = players.map((player) => player.computeRevealToken(table, 0))
+  t.is(copy.openCard(0, tokens), table.openCard(0, tokens))
+  t.throws(() => Table.fromExport(table.exportParameters(), new Table(2, 13).exportCards()))
+})
+
+test('settles the pot from the opened cards', (t) => {
+  const table = new Table(2, 26)
+  const players = ['Andrija', 'Kobi', 'Nico'].map((name) => new Player(table, name))
+  players.forEach((player) => table.join(player))
+  table.start()
+
+  // Two hole cards each, then a board of five
+  players.forEach((player, seat) => {
+    table.deal(player, seat, 2 * seat)
+    table.deal(player, seat, 2 * seat + 1)
+  })
+  const board = [6, 7, 8, 9, 10]
+  const open = (index: number) => table.openCard(index, players.map((player) => player.computeRevealToken(table, index)))
+  board.forEach(open)
+  ;[0, 1, 2, 3].forEach(open)
+
+  // Nico folded and never shows, the others split 31 chips between them
+  const payouts = table.settle(board, [10, 10, 11], [false, false, true], 2)
+  t.is(payouts[2], 0)
+  t.is(payouts[0] + payouts[1], 31)
+  t.throws(() => table.settle([6, 7, 8, 9, 11], [10, 10, 11], [false, false, true], 2))
+  t.throws(() => table.settle(board, [10, 10, 11], [false, false, true], 3))
+  t.throws(() => table.settle(board, [10, -10, 11], [false, false, true], 2))
+})
+
+test('passes cards face down to another seat', (t) => {
+  const table = new Table(2, 26)
+  const players = ['Andrija', 'Kobi', 'Nico'].map((name) => new Player(table, name))
+  players.forEach((player) => table.join(player))
+  table.start()
+
+  table.deal(players[0], 0, 0)
+  table.deal(players[0], 0, 1)
+  table.deal(players[1], 1, 2)
+  ;[0, 1].forEach((index) => {
+    const tokens = players.slice(1).map((other) => other.computeRevealToken(table, index))
+    players[0].peekAtCard(table, index, tokens)
+  })
+  t.throws(() => table.passCards(players[0], 0, 1, [0, 2]))
+  t.throws(() => table.passCards(players[0], 0, 0, [0, 1]))
+
+  table.passCards(players[0], 0, 1, [0, 1])
+  t.is(players[0].openedCards().length, 2)
+  t.is(table.owner(0), 0)
+
+  // Everyone remasks in turn, starting left of the sender
+  t.throws(() => table.remaskPassed(0))
+  ;[1, 2, 0].forEach((seat) => {
+    t.is(table.nextRemasker, seat)
+    table.remaskPassed(seat)
+  })
+  t.is(table.nextRemasker, null)
+  t.is(table.owner(0), 1)
+  t.is(table.owner(1), 1)
+
+  // The cards leave the sender's hand once the pass is complete
+  t.deepEqual(table.receiveCards(players[0], 0), [])
+  t.deepEqual(players[0].openedCards(), [])
+  t.deepEqual(table.receiveCards(players[1], 1), [0, 1])
+  const tokens = [players[0], players[2]].map((other) => other.computeRevealToken(table, 0))
+  const card = players[1].peekAtCard(table, 0, tokens)
+  t.deepEqual(players[1].openedCards(), [null, card, null])
+})
@@ -0,0 +1,79 @@
+//! Times a cut of a 52-card deck against a shuffle of it, proving and verifying included, and
+//! compares the size of their proofs. Fails if the cut is not cheaper on both counts.
+//!
+//! Run the benchmark with `cargo bench --bench cut`.
+
+use ark_ff::UniformRand;
+use ark_serialize::CanonicalSerialize;
+use barnett_smart_protocol::discrete_log_cards::{self, cut, Card, MaskedCard};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use rand::thread_rng;
+use std::time::{Duration, Instant};
+
+// Choose elliptic curve setting
+type Curve = starknet_curve::Projective;
+type Scalar = starknet_curve::Fr;
+
+// Instantiate concrete type for our card protocol
+type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+const NUMBER_OF_CARDS: usize = 52;
+const ROUNDS: u32 = 10;
+
+fn main() -> anyhow::Result<()> {
+    let rng = &mut thread_rng();
+    let parameters = CardProtocol::setup(rng, 2, NUMBER_OF_CARDS / 2)?;
+    let (pk, _) = CardProtocol::player_keygen(rng, &parameters)?;
+    let mut deck: Vec<MaskedCard<Curve>> = Vec::with_capacity(NUMBER_OF_CARDS);
+    for _ in 0..NUMBER_OF_CARDS {
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        deck.push(CardProtocol::mask(rng, &parameters, &pk, &card, &alpha)?.0);
+    }
+    let masking_factors: Vec<Scalar> = sample_vector(rng, NUMBER_OF_CARDS);
+
+    let mut cut_time = Duration::ZERO;
+    let mut shuffle_time = Duration::ZERO;
+    let mut cut_size = 0;
+    let mut shuffle_size = 0;
+    for round in 0..ROUNDS {
+        let offset = round as usize * 5 % NUMBER_OF_CARDS;
+        let start = Instant::now();
+        let (cut_deck, proof) =
+            cut::cut_and_remask(rng, &parameters, &pk, &deck, offset, &masking_factors)?;
+        cut::verify_cut(&parameters, &pk, &deck, &cut_deck, &proof)?;
+        cut_time += start.elapsed();
+        cut_size = proof.serialized_size();
+
+        let permutation = Permutation::new(rng, NUMBER_OF_CARDS);
+        let start = Instant::now();
+        let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &pk,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )?;
+        CardProtocol::verify_shuffle(&parameters, &pk, &deck, &shuffled_deck, &proof)?;
+        shuffle_time += start.elapsed();
+        shuffle_size = proof.serialized_size();
+    }
+
+    println!(
+        "cut:     {:?} per round, {} byte proof",
+        cut_time / ROUNDS,
+        cut_size
+    );
+    println!(
+        "shuffle: {:?} per round, {} byte proof",
+        shuffle_time / ROUNDS,
+        shuffle_size
+    );
+    if cut_time >= shuffle_time || cut_size >= shuffle_size {
+        anyhow::bail!("the cut costs as much as a shuffle");
+    }
+    Ok(())
+}
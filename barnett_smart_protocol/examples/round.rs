@@ -156,8 +156,9 @@ impl Player {
         self.opened_cards.push(None);
     }
 
-    pub fn peek_at_card(
+    pub fn peek_at_card<R: Rng>(
         &mut self,
+        rng: &mut R,
         parameters: &CardParameters,
         reveal_tokens: &mut Vec<(RevealToken, RevealProof, PublicKey)>,
         card_mappings: &HashMap<Card, ClassicPlayingCard>,
@@ -168,7 +169,6 @@ impl Player {
         let i = i.ok_or(GameErrors::CardNotFound)?;
 
         //TODO add function to create that without the proof
-        let rng = &mut thread_rng();
         let own_reveal_token = self.compute_reveal_token(rng, parameters, card)?;
         reveal_tokens.push(own_reveal_token);
 
@@ -379,10 +379,10 @@ fn main() -> anyhow::Result<()> {
     let mut rts_tom = vec![andrija_rt_3, kobi_rt_3, nico_rt_3];
 
     //At this moment players privately open their cards and only they know that values
-    andrija.peek_at_card(&parameters, &mut rts_andrija, &card_mapping, &deck[0])?;
-    kobi.peek_at_card(&parameters, &mut rts_kobi, &card_mapping, &deck[1])?;
-    nico.peek_at_card(&parameters, &mut rts_nico, &card_mapping, &deck[2])?;
-    tom.peek_at_card(&parameters, &mut rts_tom, &card_mapping, &deck[3])?;
+    andrija.peek_at_card(rng, &parameters, &mut rts_andrija, &card_mapping, &deck[0])?;
+    kobi.peek_at_card(rng, &parameters, &mut rts_kobi, &card_mapping, &deck[1])?;
+    nico.peek_at_card(rng, &parameters, &mut rts_nico, &card_mapping, &deck[2])?;
+    tom.peek_at_card(rng, &parameters, &mut rts_tom, &card_mapping, &deck[3])?;
 
     /* Here we can add custom logic of a game:
         1. swap card
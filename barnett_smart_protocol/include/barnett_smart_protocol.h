@@ -0,0 +1,189 @@
+#ifndef BARNETT_SMART_PROTOCOL_H
+#define BARNETT_SMART_PROTOCOL_H
+
+/* Generated with cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status code returned by every function of the C API. The first variants mirror
+// `CardProtocolError`, the others report misuse of the API itself.
+typedef enum BspError {
+  BSP_ERROR_OK = 0,
+  // `CardProtocolError::ProofVerificationError`
+  BSP_ERROR_PROOF_VERIFICATION_ERROR = 1,
+  // `CardProtocolError::IoError`
+  BSP_ERROR_IO_ERROR = 2,
+  // A required pointer argument was null
+  BSP_ERROR_NULL_POINTER = 3,
+  // A buffer could not be deserialized, or an index or length is out of range
+  BSP_ERROR_INVALID_ARGUMENT = 4,
+} BspError;
+
+// Opaque deck of masked cards.
+typedef struct BspDeck BspDeck;
+
+// Opaque key pair of a single player. The secret key never leaves this handle.
+typedef struct BspKeyPair BspKeyPair;
+
+// Opaque protocol parameters for a deck of `m * n` cards.
+typedef struct BspParameters BspParameters;
+
+// A byte buffer allocated by the library. Release it with `bsp_buffer_free`.
+typedef struct BspBuffer {
+  uint8_t *data;
+  size_t len;
+} BspBuffer;
+
+// A borrowed byte buffer passed in by the caller.
+typedef struct BspSlice {
+  const uint8_t *data;
+  size_t len;
+} BspSlice;
+
+// Create protocol parameters for a deck of `m * n` cards. `seed` is either null, to seed the
+// RNG from the operating system, or points to 32 bytes for a reproducible run.
+//
+// # Safety
+// `seed` must be null or valid for 32 bytes; `out` must be valid for writes.
+enum BspError bsp_parameters_setup(size_t m,
+                                   size_t n,
+                                   const uint8_t *seed,
+                                   struct BspParameters **out);
+
+// # Safety
+// `pp` must be null or a handle returned by `bsp_parameters_setup` that was not freed yet.
+void bsp_parameters_free(struct BspParameters *pp);
+
+// Generate a key pair for a player.
+//
+// # Safety
+// `pp` must be a live parameters handle; `out` must be valid for writes.
+enum BspError bsp_player_keygen(struct BspParameters *pp, struct BspKeyPair **out);
+
+// # Safety
+// `keys` must be null or a handle returned by `bsp_player_keygen` that was not freed yet.
+void bsp_key_pair_free(struct BspKeyPair *keys);
+
+// Serialized public key of a key pair.
+//
+// # Safety
+// `keys` must be a live key pair handle; `out` must be valid for writes.
+enum BspError bsp_public_key(const struct BspKeyPair *keys, struct BspBuffer *out);
+
+// Prove ownership of the key pair, binding the proof to the player's public information
+// (for instance their name).
+//
+// # Safety
+// Handles must be live, `player_info` must be a valid slice and `out` valid for writes.
+enum BspError bsp_prove_key_ownership(struct BspParameters *pp,
+                                      const struct BspKeyPair *keys,
+                                      struct BspSlice player_info,
+                                      struct BspBuffer *out);
+
+// Verify every player's proof of key ownership and write the serialized aggregate key.
+// The three arrays hold `count` entries each, matched by position.
+//
+// # Safety
+// `pp` must be a live handle, each array must hold `count` valid slices and `out` must be
+// valid for writes.
+enum BspError bsp_compute_aggregate_key(const struct BspParameters *pp,
+                                        const struct BspSlice *public_keys,
+                                        const struct BspSlice *key_proofs,
+                                        const struct BspSlice *player_infos,
+                                        size_t count,
+                                        struct BspBuffer *out);
+
+// Sample `num_of_cards` random plaintexts to use as the card encoding. The position of a card
+// in the serialized vector is the card id reported by `bsp_unmask`.
+//
+// # Safety
+// `pp` must be a live handle and `out` must be valid for writes.
+enum BspError bsp_card_encoding(struct BspParameters *pp,
+                                size_t num_of_cards,
+                                struct BspBuffer *out);
+
+// Mask every card of a serialized card encoding under the aggregate key to form the initial
+// deck. The masking is deterministic so that every player can check they agree on the deck.
+//
+// # Safety
+// `pp` must be a live handle, the slices must be valid and `out` must be valid for writes.
+enum BspError bsp_deck_new(struct BspParameters *pp,
+                           struct BspSlice shared_key,
+                           struct BspSlice card_encoding,
+                           struct BspDeck **out);
+
+// Read a deck back from `bsp_deck_serialize`.
+//
+// # Safety
+// `bytes` must be a valid slice and `out` must be valid for writes.
+enum BspError bsp_deck_deserialize(struct BspSlice bytes, struct BspDeck **out);
+
+// # Safety
+// `deck` must be a live deck handle and `out` must be valid for writes.
+enum BspError bsp_deck_serialize(const struct BspDeck *deck, struct BspBuffer *out);
+
+// Number of cards in a deck, 0 for a null handle.
+//
+// # Safety
+// `deck` must be null or a live deck handle.
+size_t bsp_deck_len(const struct BspDeck *deck);
+
+// # Safety
+// `deck` must be null or a deck handle that was not freed yet.
+void bsp_deck_free(struct BspDeck *deck);
+
+// Shuffle and remask a deck with a fresh permutation and masking factors. Writes the new deck
+// and the serialized proof of correct shuffle.
+//
+// # Safety
+// Handles must be live, `shared_key` must be a valid slice and both outputs valid for writes.
+enum BspError bsp_shuffle_and_remask(struct BspParameters *pp,
+                                     struct BspSlice shared_key,
+                                     const struct BspDeck *deck,
+                                     struct BspDeck **out_deck,
+                                     struct BspBuffer *out_proof);
+
+// Verify that `shuffled` is a correct shuffle of `original`.
+//
+// # Safety
+// Handles must be live and the slices valid.
+enum BspError bsp_verify_shuffle(const struct BspParameters *pp,
+                                 struct BspSlice shared_key,
+                                 const struct BspDeck *original,
+                                 const struct BspDeck *shuffled,
+                                 struct BspSlice proof);
+
+// Compute this player's reveal token for the card at `index` of the deck. The serialized
+// `(token, proof, public key)` triple is what `bsp_unmask` expects from each player.
+//
+// # Safety
+// Handles must be live and `out` must be valid for writes.
+enum BspError bsp_compute_reveal_token(struct BspParameters *pp,
+                                       const struct BspKeyPair *keys,
+                                       const struct BspDeck *deck,
+                                       size_t index,
+                                       struct BspBuffer *out);
+
+// Verify `count` reveal tokens for the card at `index` and open it. Writes the id of the card,
+// that is its position in the serialized `card_encoding`.
+//
+// # Safety
+// Handles must be live, `reveal_tokens` must hold `count` valid slices, `card_encoding` must
+// be a valid slice and `out_card_id` must be valid for writes.
+enum BspError bsp_unmask(const struct BspParameters *pp,
+                         const struct BspDeck *deck,
+                         size_t index,
+                         const struct BspSlice *reveal_tokens,
+                         size_t count,
+                         struct BspSlice card_encoding,
+                         size_t *out_card_id);
+
+// # Safety
+// `buffer` must have been returned by this library and not freed yet.
+void bsp_buffer_free(struct BspBuffer buffer);
+
+#endif /* BARNETT_SMART_PROTOCOL_H */
@@ -0,0 +1,396 @@
+//! Offline protocol operations over files, so that a hand can be reproduced or audited step by
+//! step from shell scripts.
+//!
+//! Every file holds a single value in arkworks' canonical (compressed) serialization, the same
+//! bytes the N-API, WebAssembly and C bindings exchange:
+//!
+//! | file          | contents                                    |
+//! |---------------|---------------------------------------------|
+//! | parameters    | `Parameters`                                |
+//! | key           | `(PublicKey, PlayerSecretKey)`              |
+//! | public key    | `PublicKey`                                 |
+//! | key proof     | `ZKProofKeyOwnership`                       |
+//! | encoding      | `Vec<Card>`, the position is the card id    |
+//! | deck          | `Vec<MaskedCard>`                           |
+//! | shuffle proof | `ZKProofShuffle`                            |
+//! | reveal token  | `(RevealToken, ZKProofReveal, PublicKey)`   |
+//! | card          | `Card`                                      |
+
+use barnett_smart_protocol::discrete_log_cards;
+use barnett_smart_protocol::error::CardProtocolError;
+use barnett_smart_protocol::BarnettSmartProtocol;
+
+use anyhow::{anyhow, bail, Context};
+use ark_ff::{One, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use clap::{Parser, Subcommand};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// Choose elliptic curve setting
+type Curve = starknet_curve::Projective;
+type Scalar = starknet_curve::Fr;
+
+// Instantiate concrete type for our card protocol
+type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+type CardParameters = discrete_log_cards::Parameters<Curve>;
+type PublicKey = discrete_log_cards::PublicKey<Curve>;
+type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+type Card = discrete_log_cards::Card<Curve>;
+type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+type RevealToken = discrete_log_cards::RevealToken<Curve>;
+
+type ProofKeyOwnership = schnorr_identification::proof::Proof<Curve>;
+type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+type ShuffleProof = <CardProtocol<'static> as BarnettSmartProtocol>::ZKProofShuffle;
+
+#[derive(Parser)]
+#[command(name = "mental-poker", version, about)]
+struct Cli {
+    /// Seed the RNG for a reproducible run instead of using operating system entropy
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create protocol parameters for a deck of `m * n` cards
+    Setup {
+        #[arg(short)]
+        m: usize,
+        #[arg(short)]
+        n: usize,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Generate a player's key pair, and optionally write the public half on its own
+    Keygen {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long, short)]
+        out: PathBuf,
+        #[arg(long)]
+        public_out: Option<PathBuf>,
+    },
+    /// Prove ownership of a key pair, bound to the player's name
+    ProveKey {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long)]
+        name: String,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Verify every player's key ownership proof and write the aggregate key
+    Aggregate {
+        #[arg(long)]
+        parameters: PathBuf,
+        /// Repeated once per player
+        #[arg(
+            long,
+            num_args = 3,
+            value_names = ["PUBLIC_KEY", "KEY_PROOF", "NAME"],
+            required = true
+        )]
+        player: Vec<String>,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Mask a card encoding under the aggregate key to form the initial deck
+    InitDeck {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        shared_key: PathBuf,
+        /// Card encoding to mask; written first when `--generate` is given
+        #[arg(long)]
+        encoding: PathBuf,
+        /// Sample a fresh card encoding of `m * n` cards
+        #[arg(long)]
+        generate: bool,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Shuffle and remask a deck, writing the new deck and the proof of correct shuffle
+    Shuffle {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        shared_key: PathBuf,
+        #[arg(long)]
+        deck: PathBuf,
+        #[arg(long, short)]
+        out: PathBuf,
+        #[arg(long)]
+        proof_out: PathBuf,
+    },
+    /// Check a proof of correct shuffle; exits with a non-zero status if it does not verify
+    VerifyShuffle {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        shared_key: PathBuf,
+        #[arg(long)]
+        original: PathBuf,
+        #[arg(long)]
+        shuffled: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+    },
+    /// Compute a player's reveal token for one card of a deck
+    RevealToken {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long)]
+        deck: PathBuf,
+        #[arg(long)]
+        index: usize,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Verify reveal tokens for one card of a deck and write the opened card
+    Unmask {
+        #[arg(long)]
+        parameters: PathBuf,
+        #[arg(long)]
+        deck: PathBuf,
+        #[arg(long)]
+        index: usize,
+        /// Repeated once per player
+        #[arg(long, required = true)]
+        token: Vec<PathBuf>,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Print the id of an opened card, that is its position in the card encoding
+    Decode {
+        #[arg(long)]
+        encoding: PathBuf,
+        #[arg(long)]
+        card: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let rng = &mut match cli.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    match run(rng, cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(rng: &mut StdRng, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Setup { m, n, out } => {
+            let parameters = CardProtocol::setup(rng, m, n)?;
+            write(&out, &parameters)
+        }
+
+        Command::Keygen {
+            parameters,
+            out,
+            public_out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let (pk, sk) = CardProtocol::player_keygen(rng, &parameters)?;
+            if let Some(public_out) = public_out {
+                write(&public_out, &pk)?;
+            }
+            write(&out, &(pk, sk))
+        }
+
+        Command::ProveKey {
+            parameters,
+            key,
+            name,
+            out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let (pk, sk): (PublicKey, SecretKey) = read(&key)?;
+            let proof =
+                CardProtocol::prove_key_ownership(rng, &parameters, &pk, &sk, &name.into_bytes())
+                    .map_err(CardProtocolError::from)?;
+            write(&out, &proof)
+        }
+
+        Command::Aggregate {
+            parameters,
+            player,
+            out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let players = player
+                .chunks(3)
+                .map(|chunk| {
+                    let pk: PublicKey = read(Path::new(&chunk[0]))?;
+                    let proof: ProofKeyOwnership = read(Path::new(&chunk[1]))?;
+                    Ok((pk, proof, chunk[2].clone().into_bytes()))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let shared_key = CardProtocol::compute_aggregate_key(&parameters, &players)?;
+            write(&out, &shared_key)
+        }
+
+        Command::InitDeck {
+            parameters,
+            shared_key,
+            encoding,
+            generate,
+            out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let shared_key: PublicKey = read(&shared_key)?;
+            if generate {
+                let num_of_cards = parameters.m() * parameters.n();
+                let cards: Vec<Card> = (0..num_of_cards).map(|_| Card::rand(rng)).collect();
+                write(&encoding, &cards)?;
+            }
+            let cards: Vec<Card> = read(&encoding)?;
+
+            // Masking with a factor of one keeps the initial deck reproducible by every player
+            let deck = cards
+                .iter()
+                .map(|card| {
+                    CardProtocol::mask(rng, &parameters, &shared_key, card, &Scalar::one())
+                        .map(|(masked, _)| masked)
+                })
+                .collect::<Result<Vec<MaskedCard>, _>>()?;
+            write(&out, &deck)
+        }
+
+        Command::Shuffle {
+            parameters,
+            shared_key,
+            deck,
+            out,
+            proof_out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let shared_key: PublicKey = read(&shared_key)?;
+            let deck: Vec<MaskedCard> = read(&deck)?;
+
+            let permutation = Permutation::new(rng, deck.len());
+            let masking_factors: Vec<Scalar> = sample_vector(rng, deck.len());
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &shared_key,
+                &deck,
+                &masking_factors,
+                &permutation,
+            )?;
+            write(&out, &shuffled_deck)?;
+            write(&proof_out, &proof)
+        }
+
+        Command::VerifyShuffle {
+            parameters,
+            shared_key,
+            original,
+            shuffled,
+            proof,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let shared_key: PublicKey = read(&shared_key)?;
+            let original: Vec<MaskedCard> = read(&original)?;
+            let shuffled: Vec<MaskedCard> = read(&shuffled)?;
+            let proof: ShuffleProof = read(&proof)?;
+
+            CardProtocol::verify_shuffle(&parameters, &shared_key, &original, &shuffled, &proof)
+                .map_err(CardProtocolError::from)?;
+            println!("ok");
+            Ok(())
+        }
+
+        Command::RevealToken {
+            parameters,
+            key,
+            deck,
+            index,
+            out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let (pk, sk): (PublicKey, SecretKey) = read(&key)?;
+            let deck: Vec<MaskedCard> = read(&deck)?;
+            let masked = card_at(&deck, index)?;
+
+            let (token, proof) =
+                CardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, masked)?;
+            write(&out, &(token, proof, pk))
+        }
+
+        Command::Unmask {
+            parameters,
+            deck,
+            index,
+            token,
+            out,
+        } => {
+            let parameters: CardParameters = read(&parameters)?;
+            let deck: Vec<MaskedCard> = read(&deck)?;
+            let masked = card_at(&deck, index)?;
+            let decryption_key = token
+                .iter()
+                .map(|path| read(path))
+                .collect::<anyhow::Result<Vec<(RevealToken, RevealProof, PublicKey)>>>()?;
+
+            let card = CardProtocol::unmask(&parameters, &decryption_key, masked)?;
+            write(&out, &card)
+        }
+
+        Command::Decode { encoding, card } => {
+            let encoding: Vec<Card> = read(&encoding)?;
+            let card: Card = read(&card)?;
+            let id = encoding
+                .iter()
+                .position(|candidate| *candidate == card)
+                .ok_or_else(|| anyhow!("card is not part of the encoding"))?;
+            println!("{}", id);
+            Ok(())
+        }
+    }
+}
+
+fn card_at(deck: &[MaskedCard], index: usize) -> anyhow::Result<&MaskedCard> {
+    match deck.get(index) {
+        Some(card) => Ok(card),
+        None => bail!(
+            "index {} is out of range for a deck of {}",
+            index,
+            deck.len()
+        ),
+    }
+}
+
+fn read<T: CanonicalDeserialize>(path: &Path) -> anyhow::Result<T> {
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    T::deserialize(&bytes[..]).with_context(|| format!("decoding {}", path.display()))
+}
+
+fn write<T: CanonicalSerialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    let mut bytes = Vec::with_capacity(value.serialized_size());
+    value.serialize(&mut bytes)?;
+    fs::write(path, bytes).with_context(|| format!("writing {}", path.display()))
+}
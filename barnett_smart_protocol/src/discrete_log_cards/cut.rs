@@ -0,0 +1,354 @@
+//! Cutting a masked deck: a rotation of the deck by an offset only the cutter knows, with every
+//! card remasked, and a proof that the result is such a rotation.
+//!
+//! Cutting at `k` moves the top `k` cards to the bottom, so card `i` of the cut deck is a remask
+//! of card `(i + k) mod N` of the deck. With the Fiat-Shamir challenge `x`, drawn once both decks
+//! are fixed, the rotation by `k` holds exactly when
+//!
+//! `sum(x^i * c'_i) - sum(x^i * c_((i + k) mod N))`
+//!
+//! is a remask of zero, `(r * G, r * H)` with `r = sum(x^i * rho_i)` for the masking factors
+//! `rho_i`, but for a negligible chance. The folded decks `S_k = sum(x^i * c_((i + k) mod N))`
+//! follow from one another, `S_(k + 1) = (S_k - c_k) / x + x^(N - 1) * c_k`, so the `N` candidate
+//! differences cost `O(N)` group operations. The proof is then the OR-composition of one
+//! Chaum-Pedersen proof for every candidate offset, as in `membership`: `2N` scalars, and four
+//! scalar multiplications per offset to prove or verify on top of the folding.
+//!
+//! This is not built on the commitment key of the parameters. A rotation argument on it, as de
+//! Hoogh et al. build one from the discrete Fourier transform of the deck, is linear in the size
+//! of the deck as well, and adds commitments to the transform with an argument that they open to
+//! a rotation, the machinery of the shuffle argument, so it could not beat `2N` scalars and four
+//! multiplications per card. `pp.commit_parameters` also only holds `n` generators, sized for the
+//! shuffle's `m x n` layout rather than for a whole deck. `benches/cut.rs` times both proofs on a
+//! 52-card deck and fails if the cut is not the cheaper one, in time as well as in size.
+
+use super::membership::commit;
+use super::{MaskedCard, Parameters, PublicKey, CUT_RNG_SEED};
+#[cfg(feature = "std")]
+use crate::error::CardProtocolError;
+#[cfg(feature = "std")]
+use crate::Remask;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, Field, One, PrimeField, ToBytes};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+#[cfg(feature = "std")]
+use ark_std::rand::Rng;
+use ark_std::string::String;
+use ark_std::vec::Vec;
+use ark_std::UniformRand;
+use ark_std::Zero;
+use blake2::Blake2s;
+use proof_essentials::error::CryptoError;
+
+/// Proof that a masked deck is a remasked rotation of another. It holds one challenge and one
+/// response for every possible offset.
+pub struct CutProof<C: ProjectiveCurve> {
+    challenges: Vec<C::ScalarField>,
+    responses: Vec<C::ScalarField>,
+}
+
+/// Cut `deck` at `offset`, remask card `i` of the cut deck with `masking_factors[i]` and prove
+/// that the result is a rotation of `deck`.
+#[cfg(feature = "std")]
+pub fn cut_and_remask<R: Rng, C: ProjectiveCurve>(
+    rng: &mut R,
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    deck: &[MaskedCard<C>],
+    offset: usize,
+    masking_factors: &[C::ScalarField],
+) -> Result<(Vec<MaskedCard<C>>, CutProof<C>), CardProtocolError> {
+    let size = deck.len();
+    if offset >= size {
+        return Err(CardProtocolError::InvalidCutOffset(offset, size));
+    }
+    if masking_factors.len() != size {
+        return Err(CardProtocolError::MaskingFactorCount(
+            size,
+            masking_factors.len(),
+        ));
+    }
+
+    let cut_deck = (0..size)
+        .map(|i| {
+            deck[(i + offset) % size].remask(&pp.enc_parameters, shared_key, &masking_factors[i])
+        })
+        .collect::<Result<Vec<_>, CardProtocolError>>()?;
+    let generator = pp.enc_parameters.generator;
+
+    let mut fs_rng = transcript(&generator, shared_key, deck, &cut_deck)?;
+    let x = C::ScalarField::rand(&mut fs_rng);
+    let mut power = C::ScalarField::one();
+    let mut witness = C::ScalarField::zero();
+    for masking_factor in masking_factors {
+        witness += power * masking_factor;
+        power *= x;
+    }
+
+    let nonce = C::ScalarField::rand(rng);
+    let mut challenges = Vec::with_capacity(size);
+    let mut responses = Vec::with_capacity(size);
+    let mut commitments = Vec::with_capacity(size);
+    for (k, (a, b)) in rotations(deck, &cut_deck, &x)?.iter().enumerate() {
+        if k == offset {
+            challenges.push(C::ScalarField::zero());
+            responses.push(C::ScalarField::zero());
+            commitments.push((
+                generator.mul(nonce).into_affine(),
+                shared_key.mul(nonce).into_affine(),
+            ));
+        } else {
+            // Simulated proof for an offset the deck was not cut at
+            let challenge = C::ScalarField::rand(rng);
+            let response = C::ScalarField::rand(rng);
+            commitments.push(commit(&generator, shared_key, a, b, &challenge, &response));
+            challenges.push(challenge);
+            responses.push(response);
+        }
+    }
+
+    let challenge = fiat_shamir(&mut fs_rng, &commitments)?;
+    let simulated: C::ScalarField = challenges.iter().sum();
+    challenges[offset] = challenge - simulated;
+    responses[offset] = nonce + challenges[offset] * witness;
+
+    Ok((
+        cut_deck,
+        CutProof {
+            challenges,
+            responses,
+        },
+    ))
+}
+
+/// Verify a proof that `cut_deck` is a remasked rotation of `deck`.
+pub fn verify_cut<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    deck: &[MaskedCard<C>],
+    cut_deck: &[MaskedCard<C>],
+    proof: &CutProof<C>,
+) -> Result<(), CryptoError> {
+    if deck.is_empty()
+        || cut_deck.len() != deck.len()
+        || proof.challenges.len() != deck.len()
+        || proof.responses.len() != deck.len()
+    {
+        return Err(CryptoError::ProofVerificationError(String::from("Cut")));
+    }
+
+    let generator = pp.enc_parameters.generator;
+    let mut fs_rng = transcript(&generator, shared_key, deck, cut_deck)?;
+    let x = C::ScalarField::rand(&mut fs_rng);
+    let commitments: Vec<(C::Affine, C::Affine)> = rotations(deck, cut_deck, &x)?
+        .iter()
+        .zip(proof.challenges.iter().zip(&proof.responses))
+        .map(|((a, b), (challenge, response))| {
+            commit(&generator, shared_key, a, b, challenge, response)
+        })
+        .collect();
+
+    let challenge = fiat_shamir(&mut fs_rng, &commitments)?;
+    if proof.challenges.iter().sum::<C::ScalarField>() != challenge {
+        return Err(CryptoError::ProofVerificationError(String::from("Cut")));
+    }
+
+    Ok(())
+}
+
+// `sum(x^i * c'_i) - S_k` for every offset `k`, where `S_k` is the deck rotated by `k` and
+// folded with the powers of `x`
+fn rotations<C: ProjectiveCurve>(
+    deck: &[MaskedCard<C>],
+    cut_deck: &[MaskedCard<C>],
+    x: &C::ScalarField,
+) -> Result<Vec<(C::Affine, C::Affine)>, CryptoError> {
+    let x_inverse = x
+        .inverse()
+        .ok_or_else(|| CryptoError::ProofVerificationError(String::from("Cut")))?;
+
+    let mut power = C::ScalarField::one();
+    let mut folded_cut = (C::zero(), C::zero());
+    let mut folded = (C::zero(), C::zero());
+    for (card, cut_card) in deck.iter().zip(cut_deck) {
+        folded_cut.0 += cut_card.0.mul(power);
+        folded_cut.1 += cut_card.1.mul(power);
+        folded.0 += card.0.mul(power);
+        folded.1 += card.1.mul(power);
+        power *= x;
+    }
+    // `x^(N - 1)`, the weight of the card that wraps around to the bottom
+    let last = power * x_inverse;
+
+    let mut differences = Vec::with_capacity(2 * deck.len());
+    for card in deck {
+        differences.push(folded_cut.0 - folded.0);
+        differences.push(folded_cut.1 - folded.1);
+        folded.0 =
+            (folded.0 - card.0.into_projective()).mul(x_inverse.into_repr()) + card.0.mul(last);
+        folded.1 =
+            (folded.1 - card.1.into_projective()).mul(x_inverse.into_repr()) + card.1.mul(last);
+    }
+
+    let differences = C::batch_normalization_into_affine(&differences);
+    Ok(differences
+        .chunks(2)
+        .map(|difference| (difference[0], difference[1]))
+        .collect())
+}
+
+fn transcript<C: ProjectiveCurve>(
+    generator: &C::Affine,
+    shared_key: &PublicKey<C>,
+    deck: &[MaskedCard<C>],
+    cut_deck: &[MaskedCard<C>],
+) -> Result<FiatShamirRng<Blake2s>, CryptoError> {
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![CUT_RNG_SEED]?);
+    fs_rng.absorb(&to_bytes![generator, shared_key]?);
+    for (card, cut_card) in deck.iter().zip(cut_deck) {
+        fs_rng.absorb(&to_bytes![card.0, card.1, cut_card.0, cut_card.1]?);
+    }
+
+    Ok(fs_rng)
+}
+
+fn fiat_shamir<C: ProjectiveCurve>(
+    fs_rng: &mut FiatShamirRng<Blake2s>,
+    commitments: &[(C::Affine, C::Affine)],
+) -> Result<C::ScalarField, CryptoError> {
+    for (t, u) in commitments {
+        fs_rng.absorb(&to_bytes![t, u]?);
+    }
+
+    Ok(C::ScalarField::rand(fs_rng))
+}
+
+impl<C: ProjectiveCurve> CanonicalSerialize for CutProof<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.challenges.serialize(&mut writer)?;
+        self.responses.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.challenges.serialized_size() + self.responses.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for CutProof<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Self {
+            challenges: Vec::deserialize(&mut reader)?,
+            responses: Vec::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::discrete_log_cards::{self, Card};
+    use crate::BarnettSmartProtocol;
+
+    use proof_essentials::utils::permutation::Permutation;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+    fn setup(
+        size: usize,
+    ) -> (
+        Parameters<Curve>,
+        PublicKey<Curve>,
+        Vec<Card<Curve>>,
+        Vec<MaskedCard<Curve>>,
+    ) {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, size / 2).unwrap();
+        let (pk, _) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let plaintexts: Vec<Card<Curve>> = (0..size).map(|_| Card::rand(rng)).collect();
+        let deck = plaintexts
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &pk, card, &alpha)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+
+        (parameters, pk, plaintexts, deck)
+    }
+
+    #[test]
+    fn proves_a_deck_was_cut() {
+        let rng = &mut thread_rng();
+        let (parameters, pk, _, deck) = setup(52);
+        let masking_factors: Vec<Scalar> = (0..52).map(|_| Scalar::rand(rng)).collect();
+
+        for offset in vec![0, 1, 17, 51] {
+            let (cut_deck, proof) =
+                cut_and_remask(rng, &parameters, &pk, &deck, offset, &masking_factors).unwrap();
+            assert_eq!(
+                Ok(()),
+                verify_cut(&parameters, &pk, &deck, &cut_deck, &proof)
+            );
+            assert!(cut_deck.iter().all(|card| !deck.contains(card)));
+        }
+
+        let (cut_deck, proof) =
+            cut_and_remask(rng, &parameters, &pk, &deck, 17, &masking_factors).unwrap();
+        // Not once two cards are swapped, nor for another deck
+        let mut swapped = cut_deck.clone();
+        swapped.swap(3, 40);
+        assert!(verify_cut(&parameters, &pk, &deck, &swapped, &proof).is_err());
+        let mut other_deck = deck.clone();
+        other_deck.swap(0, 1);
+        assert!(verify_cut(&parameters, &pk, &other_deck, &cut_deck, &proof).is_err());
+        assert!(verify_cut(&parameters, &pk, &deck[..51], &cut_deck[..51], &proof).is_err());
+        assert_eq!(
+            cut_and_remask(rng, &parameters, &pk, &deck, 52, &masking_factors).err(),
+            Some(CardProtocolError::InvalidCutOffset(52, 52))
+        );
+        assert_eq!(
+            cut_and_remask(rng, &parameters, &pk, &deck, 17, &masking_factors[1..]).err(),
+            Some(CardProtocolError::MaskingFactorCount(52, 51))
+        );
+
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), proof.serialized_size());
+        let decoded = CutProof::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_cut(&parameters, &pk, &deck, &cut_deck, &decoded)
+        );
+    }
+
+    #[test]
+    fn cuts_for_less_than_a_shuffle() {
+        let rng = &mut thread_rng();
+        let (parameters, pk, _, deck) = setup(52);
+        let masking_factors: Vec<Scalar> = (0..52).map(|_| Scalar::rand(rng)).collect();
+        let permutation = Permutation::new(rng, 52);
+
+        let (_, cut_proof) =
+            cut_and_remask(rng, &parameters, &pk, &deck, 17, &masking_factors).unwrap();
+        let (_, shuffle_proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &pk,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        assert!(cut_proof.serialized_size() < shuffle_proof.serialized_size());
+    }
+}
@@ -0,0 +1,303 @@
+//! Proof that a masked card held by a player does not open to any card of a given set, e.g. to
+//! show that a player cannot follow suit without revealing its hand.
+//!
+//! Once every other player has published its reveal token for the card, anyone can compute
+//! `d = c2 - sum(tokens) = card + sk * c1`, where `sk` is the secret key of the holder. For every
+//! excluded card `x`, the holder proves that `y = d - x` differs from `sk * c1` with the
+//! inequality argument of Camenisch and Shoup: for a random `r`, it publishes `u = r * y`,
+//! `v = (r * sk) * c1` and `w = r * pk = (r * sk) * G`, with one Chaum-Pedersen proof that `u`
+//! and `w` share the exponent `r` over `y` and `pk`, and another that `v` and `w` share the
+//! exponent `r * sk` over `c1` and `G`. Then `u != v` holds exactly when the card is not `x`.
+
+#[cfg(feature = "std")]
+use super::PlayerSecretKey;
+use super::{Card, MaskedCard, Parameters, PublicKey, RevealToken, EXCLUSION_RNG_SEED};
+#[cfg(feature = "std")]
+use crate::error::CardProtocolError;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, ToBytes};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+#[cfg(feature = "std")]
+use ark_std::rand::Rng;
+use ark_std::string::String;
+use ark_std::vec::Vec;
+#[cfg(feature = "std")]
+use ark_std::UniformRand;
+use blake2::Blake2s;
+use proof_essentials::error::CryptoError;
+use proof_essentials::zkp::{proofs::chaum_pedersen_dl_equality, ArgumentOfKnowledge};
+
+type ZKProofExponent<C> = chaum_pedersen_dl_equality::proof::Proof<C>;
+
+/// Proof that the masked card differs from one excluded card.
+pub struct InequalityProof<C: ProjectiveCurve> {
+    u: C::Affine,
+    v: C::Affine,
+    w: C::Affine,
+    r_proof: ZKProofExponent<C>,
+    s_proof: ZKProofExponent<C>,
+}
+
+/// Proof that the masked card differs from every excluded card, in the order they are listed.
+pub struct ExclusionProof<C: ProjectiveCurve> {
+    inequalities: Vec<InequalityProof<C>>,
+}
+
+/// Prove that `masked_card`, held by the owner of `sk`, opens to none of `excluded`.
+/// `other_tokens` are the verified reveal tokens of every other player for the card.
+#[cfg(feature = "std")]
+pub fn prove_exclusion<R: Rng, C: ProjectiveCurve>(
+    rng: &mut R,
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    sk: &PlayerSecretKey<C>,
+    masked_card: &MaskedCard<C>,
+    other_tokens: &[RevealToken<C>],
+    excluded: &[Card<C>],
+) -> Result<ExclusionProof<C>, CardProtocolError> {
+    let generator = pp.enc_parameters.generator;
+    let mut inequalities = Vec::with_capacity(excluded.len());
+
+    for y in differences(masked_card, other_tokens, excluded) {
+        let r = C::ScalarField::rand(rng);
+        let s = r * sk;
+        let u = y.mul(r).into_affine();
+        let v = masked_card.0.mul(s).into_affine();
+        let w = pk.mul(r).into_affine();
+        if u == v {
+            return Err(CryptoError::ProofVerificationError(String::from(
+                "Card is in the excluded set",
+            ))
+            .into());
+        }
+
+        let r_parameters = chaum_pedersen_dl_equality::Parameters::new(&y, pk);
+        let r_statement = chaum_pedersen_dl_equality::Statement::new(&u, &w);
+        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![EXCLUSION_RNG_SEED]?);
+        let r_proof = chaum_pedersen_dl_equality::DLEquality::prove(
+            rng,
+            &r_parameters,
+            &r_statement,
+            &r,
+            &mut fs_rng,
+        )?;
+
+        let s_parameters = chaum_pedersen_dl_equality::Parameters::new(&masked_card.0, &generator);
+        let s_statement = chaum_pedersen_dl_equality::Statement::new(&v, &w);
+        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![EXCLUSION_RNG_SEED]?);
+        let s_proof = chaum_pedersen_dl_equality::DLEquality::prove(
+            rng,
+            &s_parameters,
+            &s_statement,
+            &s,
+            &mut fs_rng,
+        )?;
+
+        inequalities.push(InequalityProof {
+            u,
+            v,
+            w,
+            r_proof,
+            s_proof,
+        });
+    }
+
+    Ok(ExclusionProof { inequalities })
+}
+
+/// Verify a proof that `masked_card`, held by the owner of `pk`, opens to none of `excluded`.
+/// `other_tokens` must be the verified reveal tokens of every other player for the card.
+pub fn verify_exclusion<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    masked_card: &MaskedCard<C>,
+    other_tokens: &[RevealToken<C>],
+    excluded: &[Card<C>],
+    proof: &ExclusionProof<C>,
+) -> Result<(), CryptoError> {
+    if proof.inequalities.len() != excluded.len() {
+        return Err(CryptoError::ProofVerificationError(String::from(
+            "Exclusion proof",
+        )));
+    }
+
+    let generator = pp.enc_parameters.generator;
+    let differences = differences(masked_card, other_tokens, excluded);
+    for (y, inequality) in differences.iter().zip(&proof.inequalities) {
+        if inequality.u == inequality.v {
+            return Err(CryptoError::ProofVerificationError(String::from(
+                "Exclusion proof",
+            )));
+        }
+
+        let r_parameters = chaum_pedersen_dl_equality::Parameters::new(y, pk);
+        let r_statement = chaum_pedersen_dl_equality::Statement::new(&inequality.u, &inequality.w);
+        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![EXCLUSION_RNG_SEED]?);
+        chaum_pedersen_dl_equality::DLEquality::verify(
+            &r_parameters,
+            &r_statement,
+            &inequality.r_proof,
+            &mut fs_rng,
+        )?;
+
+        let s_parameters = chaum_pedersen_dl_equality::Parameters::new(&masked_card.0, &generator);
+        let s_statement = chaum_pedersen_dl_equality::Statement::new(&inequality.v, &inequality.w);
+        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![EXCLUSION_RNG_SEED]?);
+        chaum_pedersen_dl_equality::DLEquality::verify(
+            &s_parameters,
+            &s_statement,
+            &inequality.s_proof,
+            &mut fs_rng,
+        )?;
+    }
+
+    Ok(())
+}
+
+// `card + sk * c1 - x` for every excluded card `x`, from the reveal tokens of the other players
+fn differences<C: ProjectiveCurve>(
+    masked_card: &MaskedCard<C>,
+    other_tokens: &[RevealToken<C>],
+    excluded: &[Card<C>],
+) -> Vec<C::Affine> {
+    let mut partial = masked_card.1.into_projective();
+    for token in other_tokens {
+        partial -= token.0.into_projective();
+    }
+
+    excluded
+        .iter()
+        .map(|card| (partial - card.0.into_projective()).into_affine())
+        .collect()
+}
+
+// Written by hand rather than derived so that no bound is placed on the curve type itself.
+impl<C: ProjectiveCurve> CanonicalSerialize for InequalityProof<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.u.serialize(&mut writer)?;
+        self.v.serialize(&mut writer)?;
+        self.w.serialize(&mut writer)?;
+        self.r_proof.serialize(&mut writer)?;
+        self.s_proof.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.u.serialized_size()
+            + self.v.serialized_size()
+            + self.w.serialized_size()
+            + self.r_proof.serialized_size()
+            + self.s_proof.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for InequalityProof<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Self {
+            u: C::Affine::deserialize(&mut reader)?,
+            v: C::Affine::deserialize(&mut reader)?,
+            w: C::Affine::deserialize(&mut reader)?,
+            r_proof: ZKProofExponent::<C>::deserialize(&mut reader)?,
+            s_proof: ZKProofExponent::<C>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalSerialize for ExclusionProof<C> {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        self.inequalities.serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.inequalities.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for ExclusionProof<C> {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        Ok(Self {
+            inequalities: Vec::deserialize(reader)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::discrete_log_cards;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::Zero;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+    #[test]
+    fn proves_a_card_is_not_in_a_set() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+        let players: Vec<_> = (0..3)
+            .map(|_| CardProtocol::player_keygen(rng, &parameters).unwrap())
+            .collect();
+        let shared_key = players
+            .iter()
+            .fold(PublicKey::<Curve>::zero(), |acc, (pk, _)| acc + *pk);
+
+        // Thirteen cards of a suit, and a card from another one held by player 0
+        let suit: Vec<Card<Curve>> = (0..13).map(|_| Card::rand(rng)).collect();
+        let card = Card::<Curve>::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, _) = CardProtocol::mask(rng, &parameters, &shared_key, &card, &alpha).unwrap();
+        let other_tokens: Vec<RevealToken<Curve>> = players[1..]
+            .iter()
+            .map(|(pk, sk)| {
+                CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &masked)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+
+        let (pk, sk) = &players[0];
+        let proof =
+            prove_exclusion(rng, &parameters, pk, sk, &masked, &other_tokens, &suit).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_exclusion(&parameters, pk, &masked, &other_tokens, &suit, &proof)
+        );
+
+        // The proof only holds for the card, the key and the set it was made for
+        let (other_pk, _) = &players[1];
+        assert!(
+            verify_exclusion(&parameters, other_pk, &masked, &other_tokens, &suit, &proof).is_err()
+        );
+        assert!(
+            verify_exclusion(&parameters, pk, &masked, &other_tokens, &suit[1..], &proof).is_err()
+        );
+        let mut with_card = suit.clone();
+        with_card[0] = card;
+        assert!(
+            verify_exclusion(&parameters, pk, &masked, &other_tokens, &with_card, &proof).is_err()
+        );
+
+        // A card of the set cannot be excluded
+        assert!(
+            prove_exclusion(rng, &parameters, pk, sk, &masked, &other_tokens, &with_card).is_err()
+        );
+
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), proof.serialized_size());
+        let decoded = ExclusionProof::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_exclusion(&parameters, pk, &masked, &other_tokens, &suit, &decoded)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! One-out-of-many proof that a masked card is a remask of one of a list of masked cards, such as
+//! the hand of a player, without telling which one.
+//!
+//! A remask of `(a, b)` by `alpha` is `(a + alpha * G, b + alpha * H)` for the shared key `H`, so
+//! a card `c` is a remask of `c_i` exactly when `c - c_i` is `(alpha * G, alpha * H)` for some
+//! `alpha`. The proof is the OR-composition of Cramer, Damgård and Schoenmakers of one
+//! Chaum-Pedersen proof for every card of the list: the prover simulates the proofs for all the
+//! cards but its own, and the challenges of all the proofs must add up to the Fiat-Shamir
+//! challenge, so that only one of them can be chosen freely.
+
+use super::{MaskedCard, Parameters, PublicKey, MEMBERSHIP_RNG_SEED};
+#[cfg(feature = "std")]
+use crate::error::CardProtocolError;
+#[cfg(feature = "std")]
+use crate::Remask;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, ToBytes};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+#[cfg(feature = "std")]
+use ark_std::rand::Rng;
+use ark_std::string::String;
+use ark_std::vec::Vec;
+use ark_std::UniformRand;
+#[cfg(feature = "std")]
+use ark_std::Zero;
+use blake2::Blake2s;
+use proof_essentials::error::CryptoError;
+
+/// Proof that a masked card is a remask of one of a list of masked cards. It holds one
+/// challenge and one response for every card of the list.
+pub struct MembershipProof<C: ProjectiveCurve> {
+    challenges: Vec<C::ScalarField>,
+    responses: Vec<C::ScalarField>,
+}
+
+/// Remask the card at `index` of `cards` with `alpha` and prove that the result is a remask of
+/// one of `cards`.
+#[cfg(feature = "std")]
+pub fn prove_membership<R: Rng, C: ProjectiveCurve>(
+    rng: &mut R,
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    cards: &[MaskedCard<C>],
+    index: usize,
+    alpha: &C::ScalarField,
+) -> Result<(MaskedCard<C>, MembershipProof<C>), CardProtocolError> {
+    let original = cards.get(index).ok_or_else(|| {
+        CryptoError::ProofVerificationError(String::from("No such card in the list"))
+    })?;
+    let remasked = original.remask(&pp.enc_parameters, shared_key, alpha)?;
+    let generator = pp.enc_parameters.generator;
+
+    let nonce = C::ScalarField::rand(rng);
+    let mut challenges = Vec::with_capacity(cards.len());
+    let mut responses = Vec::with_capacity(cards.len());
+    let mut commitments = Vec::with_capacity(cards.len());
+    for (i, (x, y)) in differences(&remasked, cards).iter().enumerate() {
+        if i == index {
+            challenges.push(C::ScalarField::zero());
+            responses.push(C::ScalarField::zero());
+            commitments.push((
+                generator.mul(nonce).into_affine(),
+                shared_key.mul(nonce).into_affine(),
+            ));
+        } else {
+            // Simulated proof for a card the new one is not a remask of
+            let challenge = C::ScalarField::rand(rng);
+            let response = C::ScalarField::rand(rng);
+            commitments.push(commit(&generator, shared_key, x, y, &challenge, &response));
+            challenges.push(challenge);
+            responses.push(response);
+        }
+    }
+
+    let challenge = fiat_shamir(&generator, shared_key, cards, &remasked, &commitments)?;
+    let simulated: C::ScalarField = challenges.iter().sum();
+    challenges[index] = challenge - simulated;
+    responses[index] = nonce + challenges[index] * alpha;
+
+    Ok((
+        remasked,
+        MembershipProof {
+            challenges,
+            responses,
+        },
+    ))
+}
+
+/// Verify a proof that `card` is a remask of one of `cards`.
+pub fn verify_membership<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    cards: &[MaskedCard<C>],
+    card: &MaskedCard<C>,
+    proof: &MembershipProof<C>,
+) -> Result<(), CryptoError> {
+    if cards.is_empty()
+        || proof.challenges.len() != cards.len()
+        || proof.responses.len() != cards.len()
+    {
+        return Err(CryptoError::ProofVerificationError(String::from(
+            "One-out-of-many",
+        )));
+    }
+
+    let generator = pp.enc_parameters.generator;
+    let commitments: Vec<(C::Affine, C::Affine)> = differences(card, cards)
+        .iter()
+        .zip(proof.challenges.iter().zip(&proof.responses))
+        .map(|((x, y), (challenge, response))| {
+            commit(&generator, shared_key, x, y, challenge, response)
+        })
+        .collect();
+
+    let challenge = fiat_shamir(&generator, shared_key, cards, card, &commitments)?;
+    if proof.challenges.iter().sum::<C::ScalarField>() != challenge {
+        return Err(CryptoError::ProofVerificationError(String::from(
+            "One-out-of-many",
+        )));
+    }
+
+    Ok(())
+}
+
+// `card - c` for every card `c` of `cards`
+fn differences<C: ProjectiveCurve>(
+    card: &MaskedCard<C>,
+    cards: &[MaskedCard<C>],
+) -> Vec<(C::Affine, C::Affine)> {
+    cards
+        .iter()
+        .map(|other| {
+            (
+                (card.0.into_projective() - other.0.into_projective()).into_affine(),
+                (card.1.into_projective() - other.1.into_projective()).into_affine(),
+            )
+        })
+        .collect()
+}
+
+// The Chaum-Pedersen commitments `(z * G - c * x, z * H - c * y)` that make `response` a valid
+// answer to `challenge` for the difference `(x, y)`
+pub(super) fn commit<C: ProjectiveCurve>(
+    generator: &C::Affine,
+    shared_key: &PublicKey<C>,
+    x: &C::Affine,
+    y: &C::Affine,
+    challenge: &C::ScalarField,
+    response: &C::ScalarField,
+) -> (C::Affine, C::Affine) {
+    (
+        (generator.mul(*response) - x.mul(*challenge)).into_affine(),
+        (shared_key.mul(*response) - y.mul(*challenge)).into_affine(),
+    )
+}
+
+fn fiat_shamir<C: ProjectiveCurve>(
+    generator: &C::Affine,
+    shared_key: &PublicKey<C>,
+    cards: &[MaskedCard<C>],
+    card: &MaskedCard<C>,
+    commitments: &[(C::Affine, C::Affine)],
+) -> Result<C::ScalarField, CryptoError> {
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![MEMBERSHIP_RNG_SEED]?);
+    fs_rng.absorb(&to_bytes![generator, shared_key, card.0, card.1]?);
+    for (other, (t, u)) in cards.iter().zip(commitments) {
+        fs_rng.absorb(&to_bytes![other.0, other.1, t, u]?);
+    }
+
+    Ok(C::ScalarField::rand(&mut fs_rng))
+}
+
+impl<C: ProjectiveCurve> CanonicalSerialize for MembershipProof<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.challenges.serialize(&mut writer)?;
+        self.responses.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.challenges.serialized_size() + self.responses.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for MembershipProof<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Self {
+            challenges: Vec::deserialize(&mut reader)?,
+            responses: Vec::deserialize(&mut reader)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::discrete_log_cards::{self, Card};
+    use crate::BarnettSmartProtocol;
+
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+    #[test]
+    fn proves_a_card_comes_from_a_hand() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+        let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+        let plaintexts: Vec<Card<Curve>> = (0..5).map(|_| Card::rand(rng)).collect();
+        let hand: Vec<MaskedCard<Curve>> = plaintexts
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &pk, card, &alpha)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+
+        let alpha = Scalar::rand(rng);
+        let (played, proof) = prove_membership(rng, &parameters, &pk, &hand, 2, &alpha).unwrap();
+        assert!(!hand.contains(&played));
+        assert_eq!(
+            Ok(()),
+            verify_membership(&parameters, &pk, &hand, &played, &proof)
+        );
+        let (token, reveal_proof) =
+            CardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, &played).unwrap();
+        assert_eq!(
+            plaintexts[2],
+            CardProtocol::unmask(&parameters, &vec![(token, reveal_proof, pk)], &played).unwrap()
+        );
+
+        // Not without the card it came from, nor for another remask of it
+        let mut other_hand = hand.clone();
+        other_hand[2] = CardProtocol::mask(rng, &parameters, &pk, &plaintexts[2], &alpha)
+            .unwrap()
+            .0;
+        assert!(verify_membership(&parameters, &pk, &other_hand, &played, &proof).is_err());
+        assert!(verify_membership(&parameters, &pk, &hand[..4], &played, &proof).is_err());
+        let (remasked, _) = CardProtocol::remask(rng, &parameters, &pk, &played, &alpha).unwrap();
+        assert!(verify_membership(&parameters, &pk, &hand, &remasked, &proof).is_err());
+        assert!(prove_membership(rng, &parameters, &pk, &hand, 5, &alpha).is_err());
+
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), proof.serialized_size());
+        let decoded = MembershipProof::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_membership(&parameters, &pk, &hand, &played, &decoded)
+        );
+    }
+}
@@ -1,34 +1,58 @@
+#[cfg(feature = "std")]
 use super::BarnettSmartProtocol;
-use super::{Mask, Remask, Reveal};
+#[cfg(feature = "std")]
+use super::{Mask, Remask};
 
+#[cfg(feature = "std")]
 use crate::error::CardProtocolError;
 
-use anyhow::Result;
-use ark_ec::{AffineCurve, ProjectiveCurve};
+#[cfg(feature = "std")]
+use ark_ec::AffineCurve;
+use ark_ec::ProjectiveCurve;
+#[cfg(feature = "std")]
 use ark_ff::{to_bytes, One, PrimeField, ToBytes};
+#[cfg(feature = "std")]
 use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+#[cfg(feature = "std")]
 use ark_std::rand::Rng;
+#[cfg(feature = "std")]
 use ark_std::Zero;
+#[cfg(feature = "std")]
 use blake2::Blake2s;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
 use proof_essentials::error::CryptoError;
-use proof_essentials::homomorphic_encryption::{
-    el_gamal, el_gamal::ElGamal, HomomorphicEncryptionScheme,
-};
+use proof_essentials::homomorphic_encryption::el_gamal;
+#[cfg(feature = "std")]
+use proof_essentials::homomorphic_encryption::{el_gamal::ElGamal, HomomorphicEncryptionScheme};
+#[cfg(feature = "std")]
 use proof_essentials::utils::permutation::Permutation;
-use proof_essentials::vector_commitment::pedersen::PedersenCommitment;
-use proof_essentials::vector_commitment::{pedersen, HomomorphicCommitmentScheme};
+use proof_essentials::vector_commitment::pedersen;
+#[cfg(feature = "std")]
+use proof_essentials::vector_commitment::{
+    pedersen::PedersenCommitment, HomomorphicCommitmentScheme,
+};
+#[cfg(feature = "std")]
 use proof_essentials::zkp::{
     arguments::shuffle,
     proofs::{chaum_pedersen_dl_equality, schnorr_identification},
     ArgumentOfKnowledge,
 };
-use std::marker::PhantomData;
 
 // mod key_ownership;
+pub mod cut;
+pub mod exclusion;
 mod masking;
+pub mod membership;
 mod remasking;
 mod reveal;
+#[cfg(feature = "std")]
 mod tests;
+#[cfg(feature = "verifier")]
+pub mod verifier;
+#[cfg(not(feature = "verifier"))]
+mod verifier;
 
 pub struct DLCards<'a, C: ProjectiveCurve> {
     _group: &'a PhantomData<C>,
@@ -58,6 +82,61 @@ impl<C: ProjectiveCurve> Parameters<C> {
             generator,
         }
     }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+}
+
+#[cfg(feature = "std")]
+impl<C: ProjectiveCurve> Parameters<C> {
+    /// Parameters for shuffling `m * n` cards under the same encryption parameters and generator,
+    /// so that keys and masked cards carry over, e.g. to reshuffle part of a deck. Like the output
+    /// of `setup`, the result must be shared by all players.
+    pub fn with_size<R: Rng>(&self, rng: &mut R, m: usize, n: usize) -> Self {
+        Self::new(
+            m,
+            n,
+            self.enc_parameters.clone(),
+            PedersenCommitment::<C>::setup(rng, n),
+            self.generator.clone(),
+        )
+    }
+}
+
+// Written by hand rather than derived so that no bound is placed on the curve type itself.
+impl<C: ProjectiveCurve> CanonicalSerialize for Parameters<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.m.serialize(&mut writer)?;
+        self.n.serialize(&mut writer)?;
+        self.enc_parameters.serialize(&mut writer)?;
+        self.commit_parameters.serialize(&mut writer)?;
+        self.generator.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.m.serialized_size()
+            + self.n.serialized_size()
+            + self.enc_parameters.serialized_size()
+            + self.commit_parameters.serialized_size()
+            + self.generator.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for Parameters<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Self::new(
+            usize::deserialize(&mut reader)?,
+            usize::deserialize(&mut reader)?,
+            el_gamal::Parameters::deserialize(&mut reader)?,
+            pedersen::CommitKey::deserialize(&mut reader)?,
+            el_gamal::Generator::deserialize(&mut reader)?,
+        ))
+    }
 }
 
 pub type PublicKey<C> = el_gamal::PublicKey<C>;
@@ -77,12 +156,16 @@ pub type MaskedCard<C> = el_gamal::Ciphertext<C>;
 /// then be aggregated to reveal the card.
 pub type RevealToken<C> = el_gamal::Plaintext<C>;
 
+const CUT_RNG_SEED: &'static [u8] = b"Cut Proof";
+const EXCLUSION_RNG_SEED: &'static [u8] = b"Exclusion Proof";
 const KEY_OWN_RNG_SEED: &'static [u8] = b"Key Ownership Proof";
 const MASKING_RNG_SEED: &'static [u8] = b"Masking Proof";
+const MEMBERSHIP_RNG_SEED: &'static [u8] = b"Membership Proof";
 const REMASKING_RNG_SEED: &'static [u8] = b"Remasking Proof";
 const REVEAL_RNG_SEED: &'static [u8] = b"Reveal Proof";
 const SHUFFLE_RNG_SEED: &'static [u8] = b"Shuffle Proof";
 
+#[cfg(feature = "std")]
 impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
     type Scalar = C::ScalarField;
     type Enc = ElGamal<C>;
@@ -154,14 +237,7 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         player_public_info: &B,
         proof: &Self::ZKProofKeyOwnership,
     ) -> Result<(), CryptoError> {
-        let mut fs_rng =
-            FiatShamirRng::<Blake2s>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
-        schnorr_identification::SchnorrIdentification::verify(
-            &pp.enc_parameters.generator,
-            pk,
-            proof,
-            &mut fs_rng,
-        )
+        verifier::verify_key_ownership(pp, pk, player_public_info, proof)
     }
 
     fn compute_aggregate_key<B: ToBytes>(
@@ -218,24 +294,7 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         masked_card: &Self::MaskedCard,
         proof: &Self::ZKProofMasking,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters =
-            chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
-
-        // Map to Chaum-Pedersen statement
-        let minus_one = -Self::Scalar::one();
-        let negative_original = card.0.mul(minus_one).into_affine();
-        let statement_cipher = masked_card.1 + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&masked_card.0, &statement_cipher);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![MASKING_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
-            proof,
-            &mut fs_rng,
-        )
+        verifier::verify_mask(pp, shared_key, card, masked_card, proof)
     }
 
     fn remask<R: Rng>(
@@ -277,24 +336,7 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         remasked: &Self::MaskedCard,
         proof: &Self::ZKProofRemasking,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters =
-            chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
-
-        // Map to Chaum-Pedersen statement
-        let minus_one = -C::ScalarField::one();
-        let negative_original = *original_masked * minus_one;
-        let statement_cipher = *remasked + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&statement_cipher.0, &statement_cipher.1);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REMASKING_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
-            proof,
-            &mut fs_rng,
-        )
+        verifier::verify_remask(pp, shared_key, original_masked, remasked, proof)
     }
 
     fn compute_reveal_token<R: Rng>(
@@ -335,22 +377,7 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         masked_card: &Self::MaskedCard,
         proof: &Self::ZKProofReveal,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters = chaum_pedersen_dl_equality::Parameters::new(
-            &masked_card.0,
-            &pp.enc_parameters.generator,
-        );
-
-        // Map to Chaum-Pedersen parameters
-        let cp_statement = chaum_pedersen_dl_equality::Statement::new(&reveal_token.0, pk);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REVEAL_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
-            proof,
-            &mut fs_rng,
-        )
+        verifier::verify_reveal(pp, pk, reveal_token, masked_card, proof)
     }
 
     fn unmask(
@@ -362,19 +389,7 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         )>,
         masked_card: &Self::MaskedCard,
     ) -> Result<Self::Card, CardProtocolError> {
-        let zero = Self::RevealToken::zero();
-
-        let mut aggregate_token = zero;
-
-        for (token, proof, pk) in decryption_key {
-            Self::verify_reveal(pp, pk, token, masked_card, proof)?;
-
-            aggregate_token = aggregate_token + *token;
-        }
-
-        let decrypted = aggregate_token.reveal(masked_card)?;
-
-        Ok(decrypted)
+        verifier::unmask(pp, decryption_key, masked_card)
     }
 
     fn shuffle_and_remask<R: Rng>(
@@ -424,21 +439,6 @@ impl<'a, C: ProjectiveCurve> BarnettSmartProtocol for DLCards<'a, C> {
         shuffled_deck: &Vec<Self::MaskedCard>,
         proof: &Self::ZKProofShuffle,
     ) -> Result<(), CryptoError> {
-        let shuffle_parameters = shuffle::Parameters::new(
-            &pp.enc_parameters,
-            shared_key,
-            &pp.commit_parameters,
-            &pp.generator,
-        );
-
-        let shuffle_statement = shuffle::Statement::new(original_deck, shuffled_deck, pp.m, pp.n);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
-        shuffle::ShuffleArgument::verify(
-            &shuffle_parameters,
-            &shuffle_statement,
-            proof,
-            &mut fs_rng,
-        )
+        verifier::verify_shuffle(pp, shared_key, original_deck, shuffled_deck, proof)
     }
 }
@@ -5,6 +5,7 @@ mod test {
     use crate::BarnettSmartProtocol;
 
     use ark_ff::UniformRand;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
     use ark_std::{rand::Rng, Zero};
     use proof_essentials::error::CryptoError;
     use proof_essentials::utils::permutation::Permutation;
@@ -225,4 +226,99 @@ mod test {
             )))
         )
     }
+
+    #[test]
+    fn shuffle_with_resized_parameters() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 4, 13).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 3);
+
+        // Cards masked for the full deck are shuffled as a pile of six
+        let small = parameters.with_size(rng, 2, 3);
+        let cards: Vec<Card> = sample_vector(rng, 6);
+        let pile = cards
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &aggregate_key, card, &alpha)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<MaskedCard>>();
+        let permutation = Permutation::new(rng, 6);
+        let masking_factors: Vec<Scalar> = sample_vector(rng, 6);
+
+        let (shuffled_pile, shuffle_proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &small,
+            &aggregate_key,
+            &pile,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(()),
+            CardProtocol::verify_shuffle(
+                &small,
+                &aggregate_key,
+                &pile,
+                &shuffled_pile,
+                &shuffle_proof
+            )
+        );
+
+        // The shuffled cards still open under the original parameters
+        let tokens = players
+            .iter()
+            .map(|(pk, sk, _)| {
+                let (token, proof) =
+                    CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &shuffled_pile[0])
+                        .unwrap();
+                (token, proof, *pk)
+            })
+            .collect::<Vec<_>>();
+        let opened = CardProtocol::unmask(&parameters, &tokens, &shuffled_pile[0]).unwrap();
+        assert!(cards.contains(&opened));
+    }
+
+    #[test]
+    fn parameters_serialization_round_trip() {
+        let rng = &mut thread_rng();
+        let m = 4;
+        let n = 13;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+
+        let mut bytes = Vec::with_capacity(parameters.serialized_size());
+        parameters.serialize(&mut bytes).unwrap();
+        let restored = CardParameters::deserialize(&bytes[..]).unwrap();
+
+        // Proofs made under the original parameters verify under the restored ones
+        let (_, aggregate_key) = setup_players(rng, &parameters, 3);
+        let deck: Vec<MaskedCard> = sample_vector(rng, m * n);
+        let permutation = Permutation::new(rng, m * n);
+        let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+
+        let (shuffled_deck, shuffle_proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &aggregate_key,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Ok(()),
+            CardProtocol::verify_shuffle(
+                &restored,
+                &aggregate_key,
+                &deck,
+                &shuffled_deck,
+                &shuffle_proof
+            )
+        );
+    }
 }
@@ -0,0 +1,253 @@
+//! The verification half of the protocol: everything a referee or verifier needs to check the
+//! proofs published by players and to open cards from verified reveal tokens. These functions
+//! take no randomness and build under `no_std + alloc` with the `verifier` feature.
+
+use super::{Card, MaskedCard, Parameters, PublicKey, RevealToken};
+use super::{
+    KEY_OWN_RNG_SEED, MASKING_RNG_SEED, REMASKING_RNG_SEED, REVEAL_RNG_SEED, SHUFFLE_RNG_SEED,
+};
+use crate::error::CardProtocolError;
+use crate::Reveal;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, One, ToBytes};
+use ark_marlin::rng::FiatShamirRng;
+use ark_std::vec::Vec;
+use ark_std::Zero;
+use blake2::Blake2s;
+use proof_essentials::error::CryptoError;
+use proof_essentials::homomorphic_encryption::el_gamal::ElGamal;
+use proof_essentials::vector_commitment::pedersen::PedersenCommitment;
+use proof_essentials::zkp::{
+    arguments::shuffle,
+    proofs::{chaum_pedersen_dl_equality, schnorr_identification},
+    ArgumentOfKnowledge,
+};
+
+pub type ZKProofKeyOwnership<C> = schnorr_identification::proof::Proof<C>;
+pub type ZKProofMasking<C> = chaum_pedersen_dl_equality::proof::Proof<C>;
+pub type ZKProofRemasking<C> = chaum_pedersen_dl_equality::proof::Proof<C>;
+pub type ZKProofReveal<C> = chaum_pedersen_dl_equality::proof::Proof<C>;
+pub type ZKProofShuffle<C> =
+    shuffle::proof::Proof<<C as ProjectiveCurve>::ScalarField, ElGamal<C>, PedersenCommitment<C>>;
+
+/// Verify a proof of key ownership
+pub fn verify_key_ownership<C: ProjectiveCurve, B: ToBytes>(
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    player_public_info: &B,
+    proof: &ZKProofKeyOwnership<C>,
+) -> Result<(), CryptoError> {
+    let mut fs_rng =
+        FiatShamirRng::<Blake2s>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
+    schnorr_identification::SchnorrIdentification::verify(
+        &pp.enc_parameters.generator,
+        pk,
+        proof,
+        &mut fs_rng,
+    )
+}
+
+/// Verify a proof of masking
+pub fn verify_mask<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    card: &Card<C>,
+    masked_card: &MaskedCard<C>,
+    proof: &ZKProofMasking<C>,
+) -> Result<(), CryptoError> {
+    // Map to Chaum-Pedersen parameters
+    let cp_parameters =
+        chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
+
+    // Map to Chaum-Pedersen statement
+    let minus_one = -C::ScalarField::one();
+    let negative_original = card.0.mul(minus_one).into_affine();
+    let statement_cipher = masked_card.1 + negative_original;
+    let cp_statement =
+        chaum_pedersen_dl_equality::Statement::new(&masked_card.0, &statement_cipher);
+
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![MASKING_RNG_SEED]?);
+    chaum_pedersen_dl_equality::DLEquality::verify(
+        &cp_parameters,
+        &cp_statement,
+        proof,
+        &mut fs_rng,
+    )
+}
+
+/// Verify a proof of remasking
+pub fn verify_remask<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    original_masked: &MaskedCard<C>,
+    remasked: &MaskedCard<C>,
+    proof: &ZKProofRemasking<C>,
+) -> Result<(), CryptoError> {
+    // Map to Chaum-Pedersen parameters
+    let cp_parameters =
+        chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
+
+    // Map to Chaum-Pedersen statement
+    let minus_one = -C::ScalarField::one();
+    let negative_original = *original_masked * minus_one;
+    let statement_cipher = *remasked + negative_original;
+    let cp_statement =
+        chaum_pedersen_dl_equality::Statement::new(&statement_cipher.0, &statement_cipher.1);
+
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REMASKING_RNG_SEED]?);
+    chaum_pedersen_dl_equality::DLEquality::verify(
+        &cp_parameters,
+        &cp_statement,
+        proof,
+        &mut fs_rng,
+    )
+}
+
+/// Verify a proof of correctly computed reveal token
+pub fn verify_reveal<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    reveal_token: &RevealToken<C>,
+    masked_card: &MaskedCard<C>,
+    proof: &ZKProofReveal<C>,
+) -> Result<(), CryptoError> {
+    // Map to Chaum-Pedersen parameters
+    let cp_parameters =
+        chaum_pedersen_dl_equality::Parameters::new(&masked_card.0, &pp.enc_parameters.generator);
+
+    // Map to Chaum-Pedersen parameters
+    let cp_statement = chaum_pedersen_dl_equality::Statement::new(&reveal_token.0, pk);
+
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REVEAL_RNG_SEED]?);
+    chaum_pedersen_dl_equality::DLEquality::verify(
+        &cp_parameters,
+        &cp_statement,
+        proof,
+        &mut fs_rng,
+    )
+}
+
+/// Verify every reveal token for a masked card and use them to recover the underlying card.
+pub fn unmask<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    decryption_key: &[(RevealToken<C>, ZKProofReveal<C>, PublicKey<C>)],
+    masked_card: &MaskedCard<C>,
+) -> Result<Card<C>, CardProtocolError> {
+    let zero = RevealToken::<C>::zero();
+
+    let mut aggregate_token = zero;
+
+    for (token, proof, pk) in decryption_key {
+        verify_reveal(pp, pk, token, masked_card, proof)?;
+
+        aggregate_token = aggregate_token + *token;
+    }
+
+    let decrypted = aggregate_token.reveal(masked_card)?;
+
+    Ok(decrypted)
+}
+
+/// Verify a proof of correct shuffle
+pub fn verify_shuffle<C: ProjectiveCurve>(
+    pp: &Parameters<C>,
+    shared_key: &PublicKey<C>,
+    original_deck: &Vec<MaskedCard<C>>,
+    shuffled_deck: &Vec<MaskedCard<C>>,
+    proof: &ZKProofShuffle<C>,
+) -> Result<(), CryptoError> {
+    let shuffle_parameters = shuffle::Parameters::new(
+        &pp.enc_parameters,
+        shared_key,
+        &pp.commit_parameters,
+        &pp.generator,
+    );
+
+    let shuffle_statement = shuffle::Statement::new(original_deck, shuffled_deck, pp.m, pp.n);
+
+    let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
+    shuffle::ShuffleArgument::verify(&shuffle_parameters, &shuffle_statement, proof, &mut fs_rng)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::discrete_log_cards;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+    #[test]
+    fn verifies_proofs_from_the_prover() {
+        let rng = &mut thread_rng();
+        let m = 4;
+        let n = 13;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let player_name = b"Alice";
+
+        let key_proof =
+            CardProtocol::prove_key_ownership(rng, &parameters, &pk, &sk, &player_name).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_key_ownership(&parameters, &pk, &player_name, &key_proof)
+        );
+
+        let card = Card::<Curve>::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, masking_proof) =
+            CardProtocol::mask(rng, &parameters, &pk, &card, &alpha).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_mask(&parameters, &pk, &card, &masked, &masking_proof)
+        );
+
+        let alpha = Scalar::rand(rng);
+        let (remasked, remasking_proof) =
+            CardProtocol::remask(rng, &parameters, &pk, &masked, &alpha).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_remask(&parameters, &pk, &masked, &remasked, &remasking_proof)
+        );
+
+        let (token, reveal_proof) =
+            CardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, &remasked).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_reveal(&parameters, &pk, &token, &remasked, &reveal_proof)
+        );
+        assert_eq!(
+            Ok(card),
+            unmask(&parameters, &[(token, reveal_proof, pk)], &remasked)
+        );
+
+        let deck: Vec<MaskedCard<Curve>> = sample_vector(rng, m * n);
+        let permutation = Permutation::new(rng, m * n);
+        let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+        let (shuffled_deck, shuffle_proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &pk,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_shuffle(&parameters, &pk, &deck, &shuffled_deck, &shuffle_proof)
+        );
+    }
+}
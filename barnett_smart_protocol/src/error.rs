@@ -1,18 +1,55 @@
+use ark_std::fmt;
+use ark_std::string::{String, ToString};
 use proof_essentials::error::CryptoError;
-use thiserror::Error;
 
 /// This is an error that could occur when running a cryptographic primitive
-#[derive(Error, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum CardProtocolError {
-    #[error("Failed to verify proof")]
-    ProofVerificationError(#[from] CryptoError),
+    ProofVerificationError(CryptoError),
 
-    #[error("IoError: {0}")]
     IoError(String),
+
+    /// A deck of `.1` cards cannot be cut at offset `.0`
+    InvalidCutOffset(usize, usize),
+
+    /// Expected `.0` masking factors, one for every card, got `.1`
+    MaskingFactorCount(usize, usize),
+}
+
+impl fmt::Display for CardProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProofVerificationError(_) => write!(f, "Failed to verify proof"),
+            Self::IoError(err) => write!(f, "IoError: {}", err),
+            Self::InvalidCutOffset(offset, size) => {
+                write!(f, "Cannot cut a deck of {} cards at {}", size, offset)
+            }
+            Self::MaskingFactorCount(expected, got) => {
+                write!(f, "Expected {} masking factors, got {}", expected, got)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CardProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ProofVerificationError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<CryptoError> for CardProtocolError {
+    fn from(err: CryptoError) -> Self {
+        Self::ProofVerificationError(err)
+    }
 }
 
-impl From<std::io::Error> for CardProtocolError {
-    fn from(err: std::io::Error) -> Self {
+// `ark_std::io` is `std::io` when the `std` feature is on, and arkworks' own replacement otherwise
+impl From<ark_std::io::Error> for CardProtocolError {
+    fn from(err: ark_std::io::Error) -> Self {
         Self::IoError(err.to_string())
     }
 }
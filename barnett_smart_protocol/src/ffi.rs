@@ -0,0 +1,724 @@
+//! C ABI over `DLCards<starknet_curve::Projective>` for game clients that are not written in Rust.
+//!
+//! Parameters, key pairs and decks live behind opaque handles that must be released with their
+//! `*_free` function. Every other value (public keys, cards, proofs, reveal tokens) crosses the
+//! boundary as a canonically serialized byte buffer. Buffers returned by the library are owned by
+//! the caller and must be released with `bsp_buffer_free`.
+//!
+//! Every function returns a `BspError`; outputs are only written when it is `BSP_ERROR_OK`. The
+//! header in `include/barnett_smart_protocol.h` is generated from this file with cbindgen.
+
+use crate::discrete_log_cards::{self, Card, MaskedCard, PublicKey, RevealToken};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ff::{One, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use proof_essentials::error::CryptoError;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::convert::TryInto;
+use std::slice;
+
+// Choose elliptic curve setting
+type Curve = starknet_curve::Projective;
+type Scalar = starknet_curve::Fr;
+
+// Instantiate concrete type for our card protocol
+type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+type CardParameters = discrete_log_cards::Parameters<Curve>;
+type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+type ProofKeyOwnership = schnorr_identification::proof::Proof<Curve>;
+type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+type ShuffleProof = <CardProtocol<'static> as BarnettSmartProtocol>::ZKProofShuffle;
+
+/// Early-return the `BspError` of a failed conversion.
+macro_rules! try_ffi {
+    ($expr:expr) => {
+        match $expr {
+            Ok(value) => value,
+            Err(err) => return BspError::from(err),
+        }
+    };
+}
+
+/// Status code returned by every function of the C API. The first variants mirror
+/// `CardProtocolError`, the others report misuse of the API itself.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BspError {
+    Ok = 0,
+    /// `CardProtocolError::ProofVerificationError`
+    ProofVerificationError = 1,
+    /// `CardProtocolError::IoError`
+    IoError = 2,
+    /// A required pointer argument was null
+    NullPointer = 3,
+    /// A buffer could not be deserialized, or an index or length is out of range
+    InvalidArgument = 4,
+}
+
+impl From<CardProtocolError> for BspError {
+    fn from(err: CardProtocolError) -> Self {
+        match err {
+            CardProtocolError::ProofVerificationError(_) => Self::ProofVerificationError,
+            CardProtocolError::IoError(_) => Self::IoError,
+            CardProtocolError::InvalidCutOffset(_, _)
+            | CardProtocolError::MaskingFactorCount(_, _) => Self::InvalidArgument,
+        }
+    }
+}
+
+impl From<CryptoError> for BspError {
+    fn from(err: CryptoError) -> Self {
+        CardProtocolError::from(err).into()
+    }
+}
+
+/// A byte buffer allocated by the library. Release it with `bsp_buffer_free`.
+#[repr(C)]
+pub struct BspBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// A borrowed byte buffer passed in by the caller.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct BspSlice {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Opaque protocol parameters for a deck of `m * n` cards.
+pub struct BspParameters {
+    parameters: CardParameters,
+    rng: StdRng,
+}
+
+/// Opaque key pair of a single player. The secret key never leaves this handle.
+pub struct BspKeyPair {
+    pk: PublicKey<Curve>,
+    sk: SecretKey,
+}
+
+/// Opaque deck of masked cards.
+pub struct BspDeck {
+    cards: Vec<MaskedCard<Curve>>,
+}
+
+/// Create protocol parameters for a deck of `m * n` cards. `seed` is either null, to seed the
+/// RNG from the operating system, or points to 32 bytes for a reproducible run.
+///
+/// # Safety
+/// `seed` must be null or valid for 32 bytes; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_parameters_setup(
+    m: usize,
+    n: usize,
+    seed: *const u8,
+    out: *mut *mut BspParameters,
+) -> BspError {
+    if out.is_null() {
+        return BspError::NullPointer;
+    }
+
+    let mut rng = if seed.is_null() {
+        StdRng::from_entropy()
+    } else {
+        let seed: [u8; 32] = slice::from_raw_parts(seed, 32).try_into().unwrap();
+        StdRng::from_seed(seed)
+    };
+
+    match CardProtocol::setup(&mut rng, m, n) {
+        Ok(parameters) => {
+            *out = Box::into_raw(Box::new(BspParameters { parameters, rng }));
+            BspError::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// # Safety
+/// `pp` must be null or a handle returned by `bsp_parameters_setup` that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_parameters_free(pp: *mut BspParameters) {
+    if !pp.is_null() {
+        drop(Box::from_raw(pp));
+    }
+}
+
+/// Generate a key pair for a player.
+///
+/// # Safety
+/// `pp` must be a live parameters handle; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_player_keygen(
+    pp: *mut BspParameters,
+    out: *mut *mut BspKeyPair,
+) -> BspError {
+    let (pp, out) = match (pp.as_mut(), out.as_mut()) {
+        (Some(pp), Some(out)) => (pp, out),
+        _ => return BspError::NullPointer,
+    };
+
+    match CardProtocol::player_keygen(&mut pp.rng, &pp.parameters) {
+        Ok((pk, sk)) => {
+            *out = Box::into_raw(Box::new(BspKeyPair { pk, sk }));
+            BspError::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// # Safety
+/// `keys` must be null or a handle returned by `bsp_player_keygen` that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_key_pair_free(keys: *mut BspKeyPair) {
+    if !keys.is_null() {
+        drop(Box::from_raw(keys));
+    }
+}
+
+/// Serialized public key of a key pair.
+///
+/// # Safety
+/// `keys` must be a live key pair handle; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_public_key(keys: *const BspKeyPair, out: *mut BspBuffer) -> BspError {
+    match keys.as_ref() {
+        Some(keys) => write_buffer(&keys.pk, out),
+        None => BspError::NullPointer,
+    }
+}
+
+/// Prove ownership of the key pair, binding the proof to the player's public information
+/// (for instance their name).
+///
+/// # Safety
+/// Handles must be live, `player_info` must be a valid slice and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_prove_key_ownership(
+    pp: *mut BspParameters,
+    keys: *const BspKeyPair,
+    player_info: BspSlice,
+    out: *mut BspBuffer,
+) -> BspError {
+    let (pp, keys) = match (pp.as_mut(), keys.as_ref()) {
+        (Some(pp), Some(keys)) => (pp, keys),
+        _ => return BspError::NullPointer,
+    };
+    let player_info = try_ffi!(as_bytes(&player_info)).to_vec();
+
+    let proof = try_ffi!(CardProtocol::prove_key_ownership(
+        &mut pp.rng,
+        &pp.parameters,
+        &keys.pk,
+        &keys.sk,
+        &player_info,
+    ));
+    write_buffer(&proof, out)
+}
+
+/// Verify every player's proof of key ownership and write the serialized aggregate key.
+/// The three arrays hold `count` entries each, matched by position.
+///
+/// # Safety
+/// `pp` must be a live handle, each array must hold `count` valid slices and `out` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_compute_aggregate_key(
+    pp: *const BspParameters,
+    public_keys: *const BspSlice,
+    key_proofs: *const BspSlice,
+    player_infos: *const BspSlice,
+    count: usize,
+    out: *mut BspBuffer,
+) -> BspError {
+    let pp = try_ffi!(pp.as_ref().ok_or(BspError::NullPointer));
+    let public_keys = try_ffi!(as_slices(public_keys, count));
+    let key_proofs = try_ffi!(as_slices(key_proofs, count));
+    let player_infos = try_ffi!(as_slices(player_infos, count));
+
+    let mut key_proof_info = Vec::with_capacity(count);
+    for i in 0..count {
+        let pk: PublicKey<Curve> = try_ffi!(deserialize(&public_keys[i]));
+        let proof: ProofKeyOwnership = try_ffi!(deserialize(&key_proofs[i]));
+        let info = try_ffi!(as_bytes(&player_infos[i])).to_vec();
+        key_proof_info.push((pk, proof, info));
+    }
+
+    let aggregate_key = try_ffi!(CardProtocol::compute_aggregate_key(
+        &pp.parameters,
+        &key_proof_info
+    ));
+    write_buffer(&aggregate_key, out)
+}
+
+/// Sample `num_of_cards` random plaintexts to use as the card encoding. The position of a card
+/// in the serialized vector is the card id reported by `bsp_unmask`.
+///
+/// # Safety
+/// `pp` must be a live handle and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_card_encoding(
+    pp: *mut BspParameters,
+    num_of_cards: usize,
+    out: *mut BspBuffer,
+) -> BspError {
+    let pp = try_ffi!(pp.as_mut().ok_or(BspError::NullPointer));
+    let cards = (0..num_of_cards)
+        .map(|_| Card::rand(&mut pp.rng))
+        .collect::<Vec<_>>();
+
+    write_buffer(&cards, out)
+}
+
+/// Mask every card of a serialized card encoding under the aggregate key to form the initial
+/// deck. The masking is deterministic so that every player can check they agree on the deck.
+///
+/// # Safety
+/// `pp` must be a live handle, the slices must be valid and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_deck_new(
+    pp: *mut BspParameters,
+    shared_key: BspSlice,
+    card_encoding: BspSlice,
+    out: *mut *mut BspDeck,
+) -> BspError {
+    let pp = try_ffi!(pp.as_mut().ok_or(BspError::NullPointer));
+    let out = try_ffi!(out.as_mut().ok_or(BspError::NullPointer));
+    let shared_key: PublicKey<Curve> = try_ffi!(deserialize(&shared_key));
+    let card_encoding: Vec<Card<Curve>> = try_ffi!(deserialize(&card_encoding));
+
+    let mut cards = Vec::with_capacity(card_encoding.len());
+    for card in &card_encoding {
+        let (masked, _) = try_ffi!(CardProtocol::mask(
+            &mut pp.rng,
+            &pp.parameters,
+            &shared_key,
+            card,
+            &Scalar::one()
+        ));
+        cards.push(masked);
+    }
+
+    *out = Box::into_raw(Box::new(BspDeck { cards }));
+    BspError::Ok
+}
+
+/// Read a deck back from `bsp_deck_serialize`.
+///
+/// # Safety
+/// `bytes` must be a valid slice and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_deck_deserialize(bytes: BspSlice, out: *mut *mut BspDeck) -> BspError {
+    let out = try_ffi!(out.as_mut().ok_or(BspError::NullPointer));
+    let cards: Vec<MaskedCard<Curve>> = try_ffi!(deserialize(&bytes));
+
+    *out = Box::into_raw(Box::new(BspDeck { cards }));
+    BspError::Ok
+}
+
+/// # Safety
+/// `deck` must be a live deck handle and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_deck_serialize(deck: *const BspDeck, out: *mut BspBuffer) -> BspError {
+    match deck.as_ref() {
+        Some(deck) => write_buffer(&deck.cards, out),
+        None => BspError::NullPointer,
+    }
+}
+
+/// Number of cards in a deck, 0 for a null handle.
+///
+/// # Safety
+/// `deck` must be null or a live deck handle.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_deck_len(deck: *const BspDeck) -> usize {
+    deck.as_ref().map_or(0, |deck| deck.cards.len())
+}
+
+/// # Safety
+/// `deck` must be null or a deck handle that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_deck_free(deck: *mut BspDeck) {
+    if !deck.is_null() {
+        drop(Box::from_raw(deck));
+    }
+}
+
+/// Shuffle and remask a deck with a fresh permutation and masking factors. Writes the new deck
+/// and the serialized proof of correct shuffle.
+///
+/// # Safety
+/// Handles must be live, `shared_key` must be a valid slice and both outputs valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_shuffle_and_remask(
+    pp: *mut BspParameters,
+    shared_key: BspSlice,
+    deck: *const BspDeck,
+    out_deck: *mut *mut BspDeck,
+    out_proof: *mut BspBuffer,
+) -> BspError {
+    let (pp, deck) = match (pp.as_mut(), deck.as_ref()) {
+        (Some(pp), Some(deck)) => (pp, deck),
+        _ => return BspError::NullPointer,
+    };
+    let out_deck = try_ffi!(out_deck.as_mut().ok_or(BspError::NullPointer));
+    let shared_key: PublicKey<Curve> = try_ffi!(deserialize(&shared_key));
+
+    let num_of_cards = deck.cards.len();
+    let permutation = Permutation::new(&mut pp.rng, num_of_cards);
+    let masking_factors: Vec<Scalar> = sample_vector(&mut pp.rng, num_of_cards);
+
+    let (shuffled, proof) = try_ffi!(CardProtocol::shuffle_and_remask(
+        &mut pp.rng,
+        &pp.parameters,
+        &shared_key,
+        &deck.cards,
+        &masking_factors,
+        &permutation,
+    ));
+
+    try_ffi!(write_buffer(&proof, out_proof).into_result());
+    *out_deck = Box::into_raw(Box::new(BspDeck { cards: shuffled }));
+    BspError::Ok
+}
+
+/// Verify that `shuffled` is a correct shuffle of `original`.
+///
+/// # Safety
+/// Handles must be live and the slices valid.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_verify_shuffle(
+    pp: *const BspParameters,
+    shared_key: BspSlice,
+    original: *const BspDeck,
+    shuffled: *const BspDeck,
+    proof: BspSlice,
+) -> BspError {
+    let (pp, original, shuffled) = match (pp.as_ref(), original.as_ref(), shuffled.as_ref()) {
+        (Some(pp), Some(original), Some(shuffled)) => (pp, original, shuffled),
+        _ => return BspError::NullPointer,
+    };
+    let shared_key: PublicKey<Curve> = try_ffi!(deserialize(&shared_key));
+    let proof: ShuffleProof = try_ffi!(deserialize(&proof));
+
+    try_ffi!(CardProtocol::verify_shuffle(
+        &pp.parameters,
+        &shared_key,
+        &original.cards,
+        &shuffled.cards,
+        &proof,
+    ));
+    BspError::Ok
+}
+
+/// Compute this player's reveal token for the card at `index` of the deck. The serialized
+/// `(token, proof, public key)` triple is what `bsp_unmask` expects from each player.
+///
+/// # Safety
+/// Handles must be live and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_compute_reveal_token(
+    pp: *mut BspParameters,
+    keys: *const BspKeyPair,
+    deck: *const BspDeck,
+    index: usize,
+    out: *mut BspBuffer,
+) -> BspError {
+    let (pp, keys, deck) = match (pp.as_mut(), keys.as_ref(), deck.as_ref()) {
+        (Some(pp), Some(keys), Some(deck)) => (pp, keys, deck),
+        _ => return BspError::NullPointer,
+    };
+    let card = try_ffi!(deck.cards.get(index).ok_or(BspError::InvalidArgument));
+
+    let (token, proof) = try_ffi!(CardProtocol::compute_reveal_token(
+        &mut pp.rng,
+        &pp.parameters,
+        &keys.sk,
+        &keys.pk,
+        card,
+    ));
+    write_buffer(&(token, proof, keys.pk), out)
+}
+
+/// Verify `count` reveal tokens for the card at `index` and open it. Writes the id of the card,
+/// that is its position in the serialized `card_encoding`.
+///
+/// # Safety
+/// Handles must be live, `reveal_tokens` must hold `count` valid slices, `card_encoding` must
+/// be a valid slice and `out_card_id` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_unmask(
+    pp: *const BspParameters,
+    deck: *const BspDeck,
+    index: usize,
+    reveal_tokens: *const BspSlice,
+    count: usize,
+    card_encoding: BspSlice,
+    out_card_id: *mut usize,
+) -> BspError {
+    let (pp, deck, out_card_id) = match (pp.as_ref(), deck.as_ref(), out_card_id.as_mut()) {
+        (Some(pp), Some(deck), Some(out_card_id)) => (pp, deck, out_card_id),
+        _ => return BspError::NullPointer,
+    };
+    let card = try_ffi!(deck.cards.get(index).ok_or(BspError::InvalidArgument));
+    let card_encoding: Vec<Card<Curve>> = try_ffi!(deserialize(&card_encoding));
+
+    let mut decryption_key: Vec<(RevealToken<Curve>, RevealProof, PublicKey<Curve>)> =
+        Vec::with_capacity(count);
+    for token in try_ffi!(as_slices(reveal_tokens, count)) {
+        decryption_key.push(try_ffi!(deserialize(token)));
+    }
+
+    let opened = try_ffi!(CardProtocol::unmask(&pp.parameters, &decryption_key, card));
+    *out_card_id = try_ffi!(card_encoding
+        .iter()
+        .position(|plaintext| *plaintext == opened)
+        .ok_or(BspError::InvalidArgument));
+    BspError::Ok
+}
+
+/// # Safety
+/// `buffer` must have been returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bsp_buffer_free(buffer: BspBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(buffer.data, buffer.len, buffer.len));
+    }
+}
+
+impl BspError {
+    fn into_result(self) -> Result<(), BspError> {
+        match self {
+            BspError::Ok => Ok(()),
+            err => Err(err),
+        }
+    }
+}
+
+unsafe fn as_bytes<'a>(slice: &BspSlice) -> Result<&'a [u8], BspError> {
+    match (slice.data.is_null(), slice.len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(BspError::NullPointer),
+        (false, len) => Ok(slice::from_raw_parts(slice.data, len)),
+    }
+}
+
+unsafe fn as_slices<'a>(slices: *const BspSlice, count: usize) -> Result<&'a [BspSlice], BspError> {
+    match (slices.is_null(), count) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(BspError::NullPointer),
+        (false, count) => Ok(slice::from_raw_parts(slices, count)),
+    }
+}
+
+unsafe fn deserialize<T: CanonicalDeserialize>(slice: &BspSlice) -> Result<T, BspError> {
+    let bytes = as_bytes(slice)?;
+    T::deserialize(bytes).map_err(|_| BspError::InvalidArgument)
+}
+
+unsafe fn write_buffer<T: CanonicalSerialize>(value: &T, out: *mut BspBuffer) -> BspError {
+    let out = match out.as_mut() {
+        Some(out) => out,
+        None => return BspError::NullPointer,
+    };
+
+    let mut bytes = Vec::with_capacity(value.serialized_size());
+    if value.serialize(&mut bytes).is_err() {
+        return BspError::InvalidArgument;
+    }
+
+    // `bsp_buffer_free` rebuilds the vector with `len` as its capacity
+    let mut bytes = bytes.into_boxed_slice();
+    *out = BspBuffer {
+        data: bytes.as_mut_ptr(),
+        len: bytes.len(),
+    };
+    std::mem::forget(bytes);
+    BspError::Ok
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::ptr;
+
+    unsafe fn as_slice(buffer: &BspBuffer) -> BspSlice {
+        BspSlice {
+            data: buffer.data,
+            len: buffer.len,
+        }
+    }
+
+    #[test]
+    fn reports_errors_as_codes() {
+        assert_eq!(
+            BspError::from(CardProtocolError::IoError(String::from("oops"))),
+            BspError::IoError
+        );
+        assert_eq!(
+            BspError::from(CryptoError::ProofVerificationError(String::from(
+                "Chaum-Pedersen"
+            ))),
+            BspError::ProofVerificationError
+        );
+
+        unsafe {
+            assert_eq!(
+                bsp_parameters_setup(4, 13, ptr::null(), ptr::null_mut()),
+                BspError::NullPointer
+            );
+
+            let mut deck = ptr::null_mut();
+            let garbage = [1u8, 2, 3];
+            let bytes = BspSlice {
+                data: garbage.as_ptr(),
+                len: garbage.len(),
+            };
+            assert_eq!(
+                bsp_deck_deserialize(bytes, &mut deck),
+                BspError::InvalidArgument
+            );
+            assert!(deck.is_null());
+        }
+    }
+
+    #[test]
+    fn round_trip_through_the_c_api() {
+        unsafe {
+            let seed = [7u8; 32];
+            let mut pp = ptr::null_mut();
+            assert_eq!(
+                bsp_parameters_setup(2, 4, seed.as_ptr(), &mut pp),
+                BspError::Ok
+            );
+
+            let mut keys = ptr::null_mut();
+            assert_eq!(bsp_player_keygen(pp, &mut keys), BspError::Ok);
+
+            let name = b"Alice";
+            let info = BspSlice {
+                data: name.as_ptr(),
+                len: name.len(),
+            };
+            let mut pk = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let mut key_proof = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(bsp_public_key(keys, &mut pk), BspError::Ok);
+            assert_eq!(
+                bsp_prove_key_ownership(pp, keys, info, &mut key_proof),
+                BspError::Ok
+            );
+
+            let mut shared_key = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                bsp_compute_aggregate_key(
+                    pp,
+                    &as_slice(&pk),
+                    &as_slice(&key_proof),
+                    &info,
+                    1,
+                    &mut shared_key
+                ),
+                BspError::Ok
+            );
+
+            let mut encoding = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(bsp_card_encoding(pp, 8, &mut encoding), BspError::Ok);
+
+            let mut deck = ptr::null_mut();
+            assert_eq!(
+                bsp_deck_new(pp, as_slice(&shared_key), as_slice(&encoding), &mut deck),
+                BspError::Ok
+            );
+            assert_eq!(bsp_deck_len(deck), 8);
+
+            let mut shuffled = ptr::null_mut();
+            let mut shuffle_proof = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                bsp_shuffle_and_remask(
+                    pp,
+                    as_slice(&shared_key),
+                    deck,
+                    &mut shuffled,
+                    &mut shuffle_proof
+                ),
+                BspError::Ok
+            );
+            assert_eq!(
+                bsp_verify_shuffle(
+                    pp,
+                    as_slice(&shared_key),
+                    deck,
+                    shuffled,
+                    as_slice(&shuffle_proof)
+                ),
+                BspError::Ok
+            );
+            assert_eq!(
+                bsp_verify_shuffle(
+                    pp,
+                    as_slice(&shared_key),
+                    shuffled,
+                    deck,
+                    as_slice(&shuffle_proof)
+                ),
+                BspError::ProofVerificationError
+            );
+
+            let mut token = BspBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                bsp_compute_reveal_token(pp, keys, shuffled, 0, &mut token),
+                BspError::Ok
+            );
+
+            let mut card_id = usize::MAX;
+            assert_eq!(
+                bsp_unmask(
+                    pp,
+                    shuffled,
+                    0,
+                    &as_slice(&token),
+                    1,
+                    as_slice(&encoding),
+                    &mut card_id
+                ),
+                BspError::Ok
+            );
+            assert!(card_id < 8);
+
+            for buffer in [pk, key_proof, shared_key, encoding, shuffle_proof, token] {
+                bsp_buffer_free(buffer);
+            }
+            bsp_deck_free(shuffled);
+            bsp_deck_free(deck);
+            bsp_key_pair_free(keys);
+            bsp_parameters_free(pp);
+        }
+    }
+}
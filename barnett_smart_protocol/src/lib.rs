@@ -1,17 +1,37 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "verifier")))]
+compile_error!("barnett_smart_protocol needs either the `std` or the `verifier` feature");
+
 use crate::error::CardProtocolError;
 
-use ark_ff::{Field, ToBytes};
+use ark_ff::Field;
+#[cfg(feature = "std")]
+use ark_ff::ToBytes;
+#[cfg(feature = "std")]
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "std")]
 use ark_std::rand::Rng;
+#[cfg(feature = "std")]
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use core::ops::{Add, Mul};
+#[cfg(feature = "std")]
 use proof_essentials::error::CryptoError;
 use proof_essentials::homomorphic_encryption::HomomorphicEncryptionScheme;
+#[cfg(feature = "std")]
 use proof_essentials::utils::permutation::Permutation;
+#[cfg(feature = "std")]
 use proof_essentials::vector_commitment::HomomorphicCommitmentScheme;
-use std::hash::Hash;
-use std::ops::{Add, Mul};
 
 pub mod discrete_log_cards;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod table;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub trait Mask<Scalar: Field, Enc: HomomorphicEncryptionScheme<Scalar>> {
     fn mask(
@@ -38,6 +58,7 @@ pub trait Reveal<F: Field, Enc: HomomorphicEncryptionScheme<F>> {
 /// Mental Poker protocol based on the one described by Barnett and Smart (2003).
 /// The protocol has been modified to make use of the argument of a correct shuffle presented
 /// by Bayer and Groth (2014).
+#[cfg(feature = "std")]
 pub trait BarnettSmartProtocol {
     // Cryptography
     type Scalar: Field;
@@ -0,0 +1,335 @@
+//! The state of a card table and of the players sitting at it, shared by the N-API and the
+//! `wasm-bindgen` bindings. The bindings only add an RNG, the conversions to and from their
+//! runtime, and how an opened card is labelled: `T` is whatever a plaintext card stands for.
+
+use crate::discrete_log_cards::{
+    Card, DLCards, MaskedCard, Parameters, PlayerSecretKey, PublicKey, RevealToken,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::One;
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::Rng;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
+use std::collections::HashMap;
+use std::fmt;
+
+type CardProtocol<'a, C> = DLCards<'a, C>;
+pub type ProofKeyOwnership<C> = schnorr_identification::proof::Proof<C>;
+pub type RevealProof<C> = chaum_pedersen_dl_equality::proof::Proof<C>;
+pub type ShuffleProof<C> = <DLCards<'static, C> as BarnettSmartProtocol>::ZKProofShuffle;
+
+/// A reveal token with its proof and the key of the player who computed it.
+pub type RevealShare<C> = (RevealToken<C>, RevealProof<C>, PublicKey<C>);
+
+#[derive(Debug, PartialEq)]
+pub enum TableError {
+    TableNotStarted,
+    TableAlreadyStarted,
+    InvalidCardIndex(u32),
+    CardAlreadyDealt(u32),
+    InvalidSeat(u32),
+    CardNotFound,
+    InvalidCard,
+    Protocol(CardProtocolError),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TableNotStarted => write!(f, "The table has not been started"),
+            Self::TableAlreadyStarted => write!(f, "The table has already been started"),
+            Self::InvalidCardIndex(index) => {
+                write!(f, "No card at position {} of the deck", index)
+            }
+            Self::CardAlreadyDealt(index) => {
+                write!(f, "Card at position {} has already been dealt", index)
+            }
+            Self::InvalidSeat(seat) => write!(f, "No player in seat {}", seat),
+            Self::CardNotFound => write!(f, "No such card in hand"),
+            Self::InvalidCard => write!(f, "Invalid card"),
+            Self::Protocol(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<CardProtocolError> for TableError {
+    fn from(err: CardProtocolError) -> Self {
+        Self::Protocol(err)
+    }
+}
+
+impl From<proof_essentials::error::CryptoError> for TableError {
+    fn from(err: proof_essentials::error::CryptoError) -> Self {
+        Self::Protocol(err.into())
+    }
+}
+
+/// A player's keys and the cards dealt to it, with what it has opened of them.
+#[derive(Clone)]
+pub struct Player<C: ProjectiveCurve, T> {
+    pub name: Vec<u8>,
+    pub sk: PlayerSecretKey<C>,
+    pub pk: PublicKey<C>,
+    pub proof_key: ProofKeyOwnership<C>,
+    pub cards: Vec<MaskedCard<C>>,
+    pub opened_cards: Vec<Option<T>>,
+}
+
+impl<C: ProjectiveCurve, T: Copy> Player<C, T> {
+    pub fn new<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        name: &[u8],
+    ) -> Result<Self, CardProtocolError> {
+        let (pk, sk) = CardProtocol::player_keygen(rng, pp)?;
+        let proof_key = CardProtocol::prove_key_ownership(rng, pp, &pk, &sk, &name.to_vec())?;
+        Ok(Self {
+            name: name.to_vec(),
+            sk,
+            pk,
+            proof_key,
+            cards: vec![],
+            opened_cards: vec![],
+        })
+    }
+
+    pub fn receive_card(&mut self, card: MaskedCard<C>) {
+        self.cards.push(card);
+        self.opened_cards.push(None);
+    }
+
+    /// Throw a card away. Returns what it was if the player had peeked at it.
+    pub fn discard(&mut self, card: &MaskedCard<C>) -> Result<Option<T>, TableError> {
+        let i = self.position(card)?;
+        self.cards.remove(i);
+        Ok(self.opened_cards.remove(i))
+    }
+
+    pub fn compute_reveal_token<R: Rng>(
+        &self,
+        rng: &mut R,
+        pp: &Parameters<C>,
+        card: &MaskedCard<C>,
+    ) -> Result<RevealShare<C>, CardProtocolError> {
+        let (reveal_token, reveal_proof) =
+            CardProtocol::compute_reveal_token(rng, pp, &self.sk, &self.pk, card)?;
+
+        Ok((reveal_token, reveal_proof, self.pk))
+    }
+
+    /// Privately open a card dealt to this player with the reveal tokens of every other player,
+    /// to which its own is added.
+    pub fn peek_at_card<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        pp: &Parameters<C>,
+        reveal_tokens: &mut Vec<RevealShare<C>>,
+        card_mapping: &HashMap<Card<C>, T>,
+        card: &MaskedCard<C>,
+    ) -> Result<T, TableError> {
+        let i = self.position(card)?;
+        reveal_tokens.push(self.compute_reveal_token(rng, pp, card)?);
+
+        let opened = unmask(pp, reveal_tokens, card_mapping, card)?;
+        self.opened_cards[i] = Some(opened);
+        Ok(opened)
+    }
+
+    fn position(&self, card: &MaskedCard<C>) -> Result<usize, TableError> {
+        self.cards
+            .iter()
+            .position(|x| x == card)
+            .ok_or(TableError::CardNotFound)
+    }
+}
+
+/// The public state every player agrees on: the protocol parameters, the card encoding, the
+/// registered players, the current deck and who owns or has opened each card.
+pub struct Table<C: ProjectiveCurve, T> {
+    pub parameters: Parameters<C>,
+    pub card_mapping: HashMap<Card<C>, T>,
+    pub players: Vec<(PublicKey<C>, ProofKeyOwnership<C>, Vec<u8>)>,
+    pub joint_pk: Option<PublicKey<C>>,
+    pub deck: Vec<MaskedCard<C>>,
+    pub owners: Vec<Option<u32>>,
+    pub opened_cards: Vec<Option<T>>,
+}
+
+impl<C: ProjectiveCurve, T: Copy> Table<C, T> {
+    pub fn new(parameters: Parameters<C>, card_mapping: HashMap<Card<C>, T>) -> Self {
+        Self {
+            parameters,
+            card_mapping,
+            players: vec![],
+            joint_pk: None,
+            deck: vec![],
+            owners: vec![],
+            opened_cards: vec![],
+        }
+    }
+
+    pub fn joint_pk(&self) -> Result<&PublicKey<C>, TableError> {
+        self.joint_pk.as_ref().ok_or(TableError::TableNotStarted)
+    }
+
+    pub fn card_at(&self, index: u32) -> Result<&MaskedCard<C>, TableError> {
+        self.deck
+            .get(index as usize)
+            .ok_or(TableError::InvalidCardIndex(index))
+    }
+
+    /// Register a player and verify their proof of key ownership. Returns the player's seat.
+    pub fn join(&mut self, player: &Player<C, T>) -> Result<u32, TableError> {
+        if self.joint_pk.is_some() {
+            return Err(TableError::TableAlreadyStarted);
+        }
+
+        CardProtocol::verify_key_ownership(
+            &self.parameters,
+            &player.pk,
+            &player.name,
+            &player.proof_key,
+        )?;
+
+        self.players
+            .push((player.pk, player.proof_key.clone(), player.name.clone()));
+        Ok((self.players.len() - 1) as u32)
+    }
+
+    /// Compute the aggregate key of all joined players and mask the initial deck with it. The
+    /// cards are masked in the order of their serialized plaintext, so that every player builds
+    /// the same deck.
+    pub fn start<R: Rng>(&mut self, rng: &mut R) -> Result<(), TableError> {
+        if self.joint_pk.is_some() {
+            return Err(TableError::TableAlreadyStarted);
+        }
+
+        let joint_pk = CardProtocol::compute_aggregate_key(&self.parameters, &self.players)?;
+
+        let mut cards: Vec<&Card<C>> = self.card_mapping.keys().collect();
+        cards.sort_by_cached_key(|card| {
+            let mut bytes = vec![];
+            card.serialize(&mut bytes)
+                .expect("serializing into a Vec cannot fail");
+            bytes
+        });
+        let deck = cards
+            .into_iter()
+            .map(|card| {
+                CardProtocol::mask(
+                    rng,
+                    &self.parameters,
+                    &joint_pk,
+                    card,
+                    &C::ScalarField::one(),
+                )
+                .map(|(masked, _)| masked)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.owners = vec![None; deck.len()];
+        self.opened_cards = vec![None; deck.len()];
+        self.deck = deck;
+        self.joint_pk = Some(joint_pk);
+        Ok(())
+    }
+
+    /// Shuffle and remask the current deck, returning the new deck with its proof.
+    pub fn shuffle<R: Rng>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(Vec<MaskedCard<C>>, ShuffleProof<C>), TableError> {
+        let joint_pk = self.joint_pk()?;
+        let num_of_cards = self.deck.len();
+        let permutation = Permutation::new(rng, num_of_cards);
+        let masking_factors: Vec<C::ScalarField> = sample_vector(rng, num_of_cards);
+
+        Ok(CardProtocol::shuffle_and_remask(
+            rng,
+            &self.parameters,
+            joint_pk,
+            &self.deck,
+            &masking_factors,
+            &permutation,
+        )?)
+    }
+
+    /// Verify a shuffle of the current deck and adopt the shuffled deck.
+    pub fn apply_shuffle(
+        &mut self,
+        shuffled_deck: Vec<MaskedCard<C>>,
+        shuffle_proof: &ShuffleProof<C>,
+    ) -> Result<(), TableError> {
+        CardProtocol::verify_shuffle(
+            &self.parameters,
+            self.joint_pk()?,
+            &self.deck,
+            &shuffled_deck,
+            shuffle_proof,
+        )?;
+
+        self.deck = shuffled_deck;
+        Ok(())
+    }
+
+    /// Give the card at `index` of the deck to `player`, sitting at `seat`.
+    pub fn deal(
+        &mut self,
+        player: &mut Player<C, T>,
+        seat: u32,
+        index: u32,
+    ) -> Result<(), TableError> {
+        if seat as usize >= self.players.len() {
+            return Err(TableError::InvalidSeat(seat));
+        }
+        let card = *self.card_at(index)?;
+        let owner = &mut self.owners[index as usize];
+        if owner.is_some() {
+            return Err(TableError::CardAlreadyDealt(index));
+        }
+
+        *owner = Some(seat);
+        player.receive_card(card);
+        Ok(())
+    }
+
+    /// Publicly open the card at `index` using the reveal tokens of every player.
+    pub fn open_card(
+        &mut self,
+        index: u32,
+        reveal_tokens: &[RevealShare<C>],
+    ) -> Result<T, TableError> {
+        let card = self.card_at(index)?;
+        let opened = unmask(&self.parameters, reveal_tokens, &self.card_mapping, card)?;
+
+        self.opened_cards[index as usize] = Some(opened);
+        Ok(opened)
+    }
+
+    /// The seat holding the card at `index`, `None` if the card has not been dealt.
+    pub fn owner(&self, index: u32) -> Result<Option<u32>, TableError> {
+        self.card_at(index)?;
+        Ok(self.owners[index as usize])
+    }
+}
+
+/// Open `card` with the reveal tokens of every player and look up what it stands for.
+pub fn unmask<C: ProjectiveCurve, T: Copy>(
+    pp: &Parameters<C>,
+    reveal_tokens: &[RevealShare<C>],
+    card_mapping: &HashMap<Card<C>, T>,
+    card: &MaskedCard<C>,
+) -> Result<T, TableError> {
+    let unmasked_card = CardProtocol::unmask(pp, &reveal_tokens.to_vec(), card)?;
+    card_mapping
+        .get(&unmasked_card)
+        .copied()
+        .ok_or(TableError::InvalidCard)
+}
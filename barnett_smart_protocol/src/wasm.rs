@@ -0,0 +1,263 @@
+//! `wasm-bindgen` bindings for web clients that cannot load the N-API addon. The exported
+//! `Player` and `Table` classes mirror the N-API ones, except that an opened card is reported as
+//! its index in the table's card encoding rather than as a label.
+//!
+//! Every class owns its RNG. Passing a 32-byte seed makes a run reproducible; without one the
+//! RNG is seeded from the browser or Node crypto API.
+
+use crate::discrete_log_cards;
+use crate::table;
+use crate::BarnettSmartProtocol;
+
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+
+// Choose elliptic curve setting
+type Curve = starknet_curve::Projective;
+
+// Instantiate concrete type for our card protocol
+type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
+
+type Parameters = discrete_log_cards::Parameters<Curve>;
+type Card = discrete_log_cards::Card<Curve>;
+type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+type RevealShare = table::RevealShare<Curve>;
+type ShuffleProof = table::ShuffleProof<Curve>;
+
+#[wasm_bindgen(js_name = setupProtocol)]
+pub fn setup_protocol(m: u32, n: u32) -> u32 {
+    m * n
+}
+
+#[wasm_bindgen]
+pub struct Player {
+    player: table::Player<Curve, u32>,
+    rng: StdRng,
+}
+
+#[wasm_bindgen]
+impl Player {
+    #[wasm_bindgen(constructor)]
+    pub fn new(table: &Table, name: String, seed: Option<Vec<u8>>) -> Result<Player, JsError> {
+        let mut rng = rng_from_seed(seed)?;
+        let player = table::Player::new(&mut rng, &table.table.parameters, name.as_bytes())?;
+
+        Ok(Self { player, rng })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.player.name).into_owned()
+    }
+
+    #[wasm_bindgen(js_name = publicKey)]
+    pub fn public_key(&self) -> Result<Vec<u8>, JsError> {
+        serialize(&self.player.pk)
+    }
+
+    /// Serialized `(token, proof, public key)` triple for the card at `index` of the table deck.
+    #[wasm_bindgen(js_name = computeRevealToken)]
+    pub fn compute_reveal_token(&mut self, table: &Table, index: u32) -> Result<Vec<u8>, JsError> {
+        let card = table.table.card_at(index)?;
+        let token =
+            self.player
+                .compute_reveal_token(&mut self.rng, &table.table.parameters, card)?;
+
+        serialize(&token)
+    }
+
+    /// Privately open a card dealt to this player using the reveal tokens of every other player.
+    #[wasm_bindgen(js_name = peekAtCard)]
+    pub fn peek_at_card(
+        &mut self,
+        table: &Table,
+        index: u32,
+        reveal_tokens: Box<[JsValue]>,
+    ) -> Result<u32, JsError> {
+        let card = table.table.card_at(index)?;
+        let mut reveal_tokens = deserialize_tokens(&reveal_tokens)?;
+
+        Ok(self.player.peek_at_card(
+            &mut self.rng,
+            &table.table.parameters,
+            &mut reveal_tokens,
+            &table.table.card_mapping,
+            card,
+        )?)
+    }
+
+    /// The cards this player has opened so far, `null` for cards that are still masked.
+    #[wasm_bindgen(js_name = openedCards)]
+    pub fn opened_cards(&self) -> Box<[JsValue]> {
+        to_js_cards(&self.player.opened_cards)
+    }
+}
+
+/// The table state of `table::Table`, with opened cards reported as their index in the card
+/// encoding.
+#[wasm_bindgen]
+pub struct Table {
+    table: table::Table<Curve, u32>,
+    rng: StdRng,
+}
+
+#[wasm_bindgen]
+impl Table {
+    #[wasm_bindgen(constructor)]
+    pub fn new(m: u32, n: u32, seed: Option<Vec<u8>>) -> Result<Table, JsError> {
+        let mut rng = rng_from_seed(seed)?;
+        let (m, n) = (m as usize, n as usize);
+        let parameters = CardProtocol::setup(&mut rng, m, n)?;
+        let card_mapping = (0..m * n)
+            .map(|id| (Card::rand(&mut rng), id as u32))
+            .collect();
+
+        Ok(Self {
+            table: table::Table::new(parameters, card_mapping),
+            rng,
+        })
+    }
+
+    /// Set up the table another player created, from the bytes of its `exportParameters` and
+    /// `exportCards`, so that both hold the same table and can verify each other's shuffles.
+    #[wasm_bindgen(js_name = fromExport)]
+    pub fn from_export(
+        parameters: &[u8],
+        cards: &[u8],
+        seed: Option<Vec<u8>>,
+    ) -> Result<Table, JsError> {
+        let parameters: Parameters = CanonicalDeserialize::deserialize(parameters)?;
+        let plaintexts: Vec<Card> = CanonicalDeserialize::deserialize(cards)?;
+        let num_of_cards = parameters.m() * parameters.n();
+        let card_mapping: HashMap<Card, u32> = plaintexts
+            .iter()
+            .enumerate()
+            .map(|(id, card)| (*card, id as u32))
+            .collect();
+        if plaintexts.len() != num_of_cards || card_mapping.len() != num_of_cards {
+            return Err(JsError::new(&format!(
+                "Expected the plaintexts of {} distinct cards",
+                num_of_cards
+            )));
+        }
+
+        Ok(Self {
+            table: table::Table::new(parameters, card_mapping),
+            rng: rng_from_seed(seed)?,
+        })
+    }
+
+    /// The serialized protocol parameters, to be sent to the other players with `exportCards`.
+    #[wasm_bindgen(js_name = exportParameters)]
+    pub fn export_parameters(&self) -> Result<Vec<u8>, JsError> {
+        serialize(&self.table.parameters)
+    }
+
+    /// The serialized plaintexts of the cards, in the order of their index.
+    #[wasm_bindgen(js_name = exportCards)]
+    pub fn export_cards(&self) -> Result<Vec<u8>, JsError> {
+        let mut cards: Vec<(&Card, &u32)> = self.table.card_mapping.iter().collect();
+        cards.sort_by_key(|(_, id)| **id);
+        serialize(&cards.into_iter().map(|(card, _)| *card).collect::<Vec<_>>())
+    }
+
+    /// Register a player and verify their proof of key ownership. Returns the player's seat.
+    pub fn join(&mut self, player: &Player) -> Result<u32, JsError> {
+        Ok(self.table.join(&player.player)?)
+    }
+
+    /// Compute the aggregate key of all joined players and mask the initial deck with it.
+    pub fn start(&mut self) -> Result<(), JsError> {
+        Ok(self.table.start(&mut self.rng)?)
+    }
+
+    /// Shuffle the deck locally. Returns the serialized `(deck, proof)` pair so that it can be
+    /// broadcast to the other players, who adopt it with `applyShuffle`.
+    pub fn shuffle(&mut self) -> Result<Vec<u8>, JsError> {
+        let (shuffled_deck, shuffle_proof) = self.table.shuffle(&mut self.rng)?;
+
+        // Same layout as the `(deck, proof)` tuple read back by `applyShuffle`
+        let mut bytes = Vec::new();
+        shuffled_deck.serialize(&mut bytes)?;
+        shuffle_proof.serialize(&mut bytes)?;
+
+        self.table.apply_shuffle(shuffled_deck, &shuffle_proof)?;
+        Ok(bytes)
+    }
+
+    #[wasm_bindgen(js_name = applyShuffle)]
+    pub fn apply_shuffle(&mut self, shuffle: &[u8]) -> Result<(), JsError> {
+        let (shuffled_deck, shuffle_proof): (Vec<MaskedCard>, ShuffleProof) =
+            CanonicalDeserialize::deserialize(shuffle)?;
+
+        Ok(self.table.apply_shuffle(shuffled_deck, &shuffle_proof)?)
+    }
+
+    /// Give the card at `index` of the deck to the player sitting at `seat`.
+    pub fn deal(&mut self, player: &mut Player, seat: u32, index: u32) -> Result<(), JsError> {
+        Ok(self.table.deal(&mut player.player, seat, index)?)
+    }
+
+    /// Publicly open the card at `index` using the reveal tokens of every player.
+    #[wasm_bindgen(js_name = openCard)]
+    pub fn open_card(&mut self, index: u32, reveal_tokens: Box<[JsValue]>) -> Result<u32, JsError> {
+        let reveal_tokens = deserialize_tokens(&reveal_tokens)?;
+        Ok(self.table.open_card(index, &reveal_tokens)?)
+    }
+
+    #[wasm_bindgen(getter, js_name = deckSize)]
+    pub fn deck_size(&self) -> u32 {
+        self.table.deck.len() as u32
+    }
+
+    /// The seat holding the card at `index`, `undefined` if the card has not been dealt.
+    pub fn owner(&self, index: u32) -> Result<Option<u32>, JsError> {
+        Ok(self.table.owner(index)?)
+    }
+
+    /// The publicly opened cards of the deck, `null` for cards that are still masked.
+    #[wasm_bindgen(js_name = openedCards)]
+    pub fn opened_cards(&self) -> Box<[JsValue]> {
+        to_js_cards(&self.table.opened_cards)
+    }
+}
+
+fn rng_from_seed(seed: Option<Vec<u8>>) -> Result<StdRng, JsError> {
+    match seed {
+        Some(seed) => {
+            let seed: [u8; 32] = seed
+                .try_into()
+                .map_err(|_| JsError::new("The RNG seed must be 32 bytes long"))?;
+            Ok(StdRng::from_seed(seed))
+        }
+        None => Ok(StdRng::from_entropy()),
+    }
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>, JsError> {
+    let mut bytes = Vec::with_capacity(value.serialized_size());
+    value.serialize(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn deserialize_tokens(reveal_tokens: &[JsValue]) -> Result<Vec<RevealShare>, JsError> {
+    reveal_tokens
+        .iter()
+        .map(|token| {
+            let bytes = js_sys::Uint8Array::new(token).to_vec();
+            Ok(CanonicalDeserialize::deserialize(&bytes[..])?)
+        })
+        .collect()
+}
+
+fn to_js_cards(cards: &[Option<u32>]) -> Box<[JsValue]> {
+    cards
+        .iter()
+        .map(|card| card.map_or(JsValue::NULL, JsValue::from))
+        .collect()
+}
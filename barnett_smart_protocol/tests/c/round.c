@@ -0,0 +1,122 @@
+/*
+ * Plays a full round through the C API: setup, key generation, aggregate key, initial deck,
+ * one shuffle per player with verification, then every player reveals every card.
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "barnett_smart_protocol.h"
+
+#define M 2
+#define N 4
+#define NUM_OF_CARDS (M * N)
+#define NUM_OF_PLAYERS 3
+
+#define CHECK(call)                                                                            \
+    do {                                                                                       \
+        enum BspError err = (call);                                                            \
+        if (err != BSP_ERROR_OK) {                                                             \
+            fprintf(stderr, "%s:%d: %s returned %d\n", __FILE__, __LINE__, #call, (int)err);   \
+            exit(1);                                                                           \
+        }                                                                                      \
+    } while (0)
+
+static BspSlice as_slice(BspBuffer buffer) {
+    BspSlice slice = {buffer.data, buffer.len};
+    return slice;
+}
+
+int main(void) {
+    const char *names[NUM_OF_PLAYERS] = {"Alice", "Bob", "Carol"};
+    uint8_t seed[32] = {42};
+
+    BspParameters *pp = NULL;
+    CHECK(bsp_parameters_setup(M, N, seed, &pp));
+
+    BspKeyPair *keys[NUM_OF_PLAYERS];
+    BspBuffer pks[NUM_OF_PLAYERS], key_proofs[NUM_OF_PLAYERS];
+    BspSlice pk_slices[NUM_OF_PLAYERS], proof_slices[NUM_OF_PLAYERS], infos[NUM_OF_PLAYERS];
+    for (int i = 0; i < NUM_OF_PLAYERS; i++) {
+        infos[i].data = (const uint8_t *)names[i];
+        infos[i].len = strlen(names[i]);
+        CHECK(bsp_player_keygen(pp, &keys[i]));
+        CHECK(bsp_public_key(keys[i], &pks[i]));
+        CHECK(bsp_prove_key_ownership(pp, keys[i], infos[i], &key_proofs[i]));
+        pk_slices[i] = as_slice(pks[i]);
+        proof_slices[i] = as_slice(key_proofs[i]);
+    }
+
+    BspBuffer shared_key;
+    CHECK(bsp_compute_aggregate_key(pp, pk_slices, proof_slices, infos, NUM_OF_PLAYERS,
+                                    &shared_key));
+
+    /* Swapping two players' proofs must be rejected */
+    BspBuffer rejected;
+    BspSlice swapped[NUM_OF_PLAYERS] = {proof_slices[1], proof_slices[0], proof_slices[2]};
+    if (bsp_compute_aggregate_key(pp, pk_slices, swapped, infos, NUM_OF_PLAYERS, &rejected) !=
+        BSP_ERROR_PROOF_VERIFICATION_ERROR) {
+        fprintf(stderr, "swapped key proofs were accepted\n");
+        return 1;
+    }
+
+    BspBuffer encoding;
+    CHECK(bsp_card_encoding(pp, NUM_OF_CARDS, &encoding));
+
+    BspDeck *deck = NULL;
+    CHECK(bsp_deck_new(pp, as_slice(shared_key), as_slice(encoding), &deck));
+
+    for (int i = 0; i < NUM_OF_PLAYERS; i++) {
+        BspDeck *shuffled = NULL;
+        BspBuffer proof;
+        CHECK(bsp_shuffle_and_remask(pp, as_slice(shared_key), deck, &shuffled, &proof));
+
+        /* Other players check the shuffle on the serialized deck they received */
+        BspBuffer bytes;
+        BspDeck *received = NULL;
+        CHECK(bsp_deck_serialize(shuffled, &bytes));
+        CHECK(bsp_deck_deserialize(as_slice(bytes), &received));
+        CHECK(bsp_verify_shuffle(pp, as_slice(shared_key), deck, received, as_slice(proof)));
+
+        bsp_buffer_free(bytes);
+        bsp_buffer_free(proof);
+        bsp_deck_free(received);
+        bsp_deck_free(deck);
+        deck = shuffled;
+    }
+
+    int seen[NUM_OF_CARDS] = {0};
+    for (size_t index = 0; index < bsp_deck_len(deck); index++) {
+        BspBuffer tokens[NUM_OF_PLAYERS];
+        BspSlice token_slices[NUM_OF_PLAYERS];
+        for (int i = 0; i < NUM_OF_PLAYERS; i++) {
+            CHECK(bsp_compute_reveal_token(pp, keys[i], deck, index, &tokens[i]));
+            token_slices[i] = as_slice(tokens[i]);
+        }
+
+        size_t card_id;
+        CHECK(bsp_unmask(pp, deck, index, token_slices, NUM_OF_PLAYERS, as_slice(encoding),
+                         &card_id));
+        if (card_id >= NUM_OF_CARDS || seen[card_id]++) {
+            fprintf(stderr, "card %zu opened to unexpected id %zu\n", index, card_id);
+            return 1;
+        }
+
+        for (int i = 0; i < NUM_OF_PLAYERS; i++) {
+            bsp_buffer_free(tokens[i]);
+        }
+    }
+
+    bsp_deck_free(deck);
+    bsp_buffer_free(encoding);
+    bsp_buffer_free(shared_key);
+    for (int i = 0; i < NUM_OF_PLAYERS; i++) {
+        bsp_buffer_free(pks[i]);
+        bsp_buffer_free(key_proofs[i]);
+        bsp_key_pair_free(keys[i]);
+    }
+    bsp_parameters_free(pp);
+
+    printf("opened all %d cards\n", NUM_OF_CARDS);
+    return 0;
+}
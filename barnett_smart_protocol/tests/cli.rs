@@ -0,0 +1,221 @@
+#![cfg(feature = "cli")]
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn mental_poker(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mental-poker"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = mental_poker(dir, args);
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mental-poker-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn play_a_round_from_files() {
+    let dir = scratch_dir("round");
+    let dir = dir.as_path();
+    let players = ["Alice", "Bob"];
+
+    run(dir, &["setup", "-m", "2", "-n", "4", "--out", "pp"]);
+
+    let mut aggregate = vec!["aggregate", "--parameters", "pp", "--out", "joint"];
+    let files: Vec<(String, String, String)> = players
+        .iter()
+        .map(|name| {
+            (
+                format!("{}.key", name),
+                format!("{}.pk", name),
+                format!("{}.proof", name),
+            )
+        })
+        .collect();
+    for (name, (key, pk, proof)) in players.iter().zip(files.iter()) {
+        run(
+            dir,
+            &[
+                "keygen",
+                "--parameters",
+                "pp",
+                "--out",
+                key,
+                "--public-out",
+                pk,
+            ],
+        );
+        run(
+            dir,
+            &[
+                "prove-key",
+                "--parameters",
+                "pp",
+                "--key",
+                key,
+                "--name",
+                name,
+                "--out",
+                proof,
+            ],
+        );
+        aggregate.extend_from_slice(&["--player", pk, proof, name]);
+    }
+    run(dir, &aggregate);
+
+    // Proofs are bound to the player's name
+    let output = mental_poker(
+        dir,
+        &[
+            "aggregate",
+            "--parameters",
+            "pp",
+            "--out",
+            "bad",
+            "--player",
+            "Alice.pk",
+            "Alice.proof",
+            "Bob",
+        ],
+    );
+    assert!(!output.status.success());
+
+    run(
+        dir,
+        &[
+            "init-deck",
+            "--parameters",
+            "pp",
+            "--shared-key",
+            "joint",
+            "--encoding",
+            "encoding",
+            "--generate",
+            "--out",
+            "deck0",
+        ],
+    );
+    for (i, _) in players.iter().enumerate() {
+        let original = format!("deck{}", i);
+        let shuffled = format!("deck{}", i + 1);
+        let proof = format!("shuffle{}", i);
+        run(
+            dir,
+            &[
+                "shuffle",
+                "--parameters",
+                "pp",
+                "--shared-key",
+                "joint",
+                "--deck",
+                &original,
+                "--out",
+                &shuffled,
+                "--proof-out",
+                &proof,
+            ],
+        );
+        let verdict = run(
+            dir,
+            &[
+                "verify-shuffle",
+                "--parameters",
+                "pp",
+                "--shared-key",
+                "joint",
+                "--original",
+                &original,
+                "--shuffled",
+                &shuffled,
+                "--proof",
+                &proof,
+            ],
+        );
+        assert_eq!(verdict.trim(), "ok");
+    }
+
+    // A proof does not verify against another pair of decks
+    let output = mental_poker(
+        dir,
+        &[
+            "verify-shuffle",
+            "--parameters",
+            "pp",
+            "--shared-key",
+            "joint",
+            "--original",
+            "deck1",
+            "--shuffled",
+            "deck2",
+            "--proof",
+            "shuffle0",
+        ],
+    );
+    assert!(!output.status.success());
+
+    let mut ids = HashSet::new();
+    for index in 0..8 {
+        let index = index.to_string();
+        let tokens: Vec<String> = players
+            .iter()
+            .map(|name| format!("{}.token", name))
+            .collect();
+        let mut unmask = vec![
+            "unmask",
+            "--parameters",
+            "pp",
+            "--deck",
+            "deck2",
+            "--index",
+            &index,
+            "--out",
+            "card",
+        ];
+        for ((key, _, _), token) in files.iter().zip(tokens.iter()) {
+            run(
+                dir,
+                &[
+                    "reveal-token",
+                    "--parameters",
+                    "pp",
+                    "--key",
+                    key,
+                    "--deck",
+                    "deck2",
+                    "--index",
+                    &index,
+                    "--out",
+                    token,
+                ],
+            );
+            unmask.extend_from_slice(&["--token", token]);
+        }
+        run(dir, &unmask);
+
+        let id: usize = run(dir, &["decode", "--encoding", "encoding", "--card", "card"])
+            .trim()
+            .parse()
+            .unwrap();
+        assert!(id < 8);
+        ids.insert(id);
+    }
+    assert_eq!(ids.len(), 8);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,123 @@
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use barnett_smart_protocol::wasm::{Player, Table};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn seed(byte: u8) -> Option<Vec<u8>> {
+    Some(vec![byte; 32])
+}
+
+fn tokens(tokens: Vec<Vec<u8>>) -> Box<[JsValue]> {
+    tokens
+        .iter()
+        .map(|token| js_sys::Uint8Array::from(&token[..]).into())
+        .collect()
+}
+
+#[wasm_bindgen_test]
+fn play_a_round() {
+    let mut table = Table::new(2, 26, seed(0)).unwrap();
+    let mut players = vec![
+        Player::new(&table, String::from("Andrija"), seed(1)).unwrap(),
+        Player::new(&table, String::from("Kobi"), seed(2)).unwrap(),
+        Player::new(&table, String::from("Nico"), seed(3)).unwrap(),
+    ];
+
+    for (seat, player) in players.iter().enumerate() {
+        assert_eq!(table.join(player).unwrap(), seat as u32);
+    }
+    table.start().unwrap();
+    assert_eq!(table.deck_size(), 52);
+
+    let shuffle = table.shuffle().unwrap();
+    assert!(Table::new(2, 26, seed(0))
+        .unwrap()
+        .apply_shuffle(&shuffle)
+        .is_err());
+
+    for (seat, player) in players.iter_mut().enumerate() {
+        table.deal(player, seat as u32, seat as u32).unwrap();
+    }
+    assert_eq!(table.owner(1).unwrap(), Some(1));
+    assert_eq!(table.owner(10).unwrap(), None);
+
+    for seat in 0..players.len() {
+        let others = (0..players.len())
+            .filter(|&other| other != seat)
+            .map(|other| {
+                players[other]
+                    .compute_reveal_token(&table, seat as u32)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let card = players[seat]
+            .peek_at_card(&table, seat as u32, tokens(others))
+            .unwrap();
+        assert!(card < 52);
+    }
+
+    let community = players.len() as u32;
+    let all = players
+        .iter_mut()
+        .map(|player| player.compute_reveal_token(&table, community).unwrap())
+        .collect::<Vec<_>>();
+    let card = table.open_card(community, tokens(all)).unwrap();
+
+    assert_eq!(
+        table.opened_cards()[community as usize],
+        JsValue::from(card)
+    );
+    assert_eq!(table.opened_cards()[0], JsValue::NULL);
+}
+
+#[wasm_bindgen_test]
+fn seeded_tables_are_reproducible() {
+    let first = Table::new(2, 26, seed(7)).unwrap();
+    let second = Table::new(2, 26, seed(7)).unwrap();
+
+    let first_player = Player::new(&first, String::from("Alice"), seed(8)).unwrap();
+    let second_player = Player::new(&second, String::from("Alice"), seed(8)).unwrap();
+
+    assert_eq!(
+        first_player.public_key().unwrap(),
+        second_player.public_key().unwrap()
+    );
+    assert!(Table::new(2, 26, Some(vec![0; 31])).is_err());
+}
+
+#[wasm_bindgen_test]
+fn tables_rebuilt_from_an_export_verify_each_others_shuffles() {
+    let mut table = Table::new(2, 26, seed(0)).unwrap();
+    let parameters = table.export_parameters().unwrap();
+    let cards = table.export_cards().unwrap();
+    let mut copy = Table::from_export(&parameters, &cards, seed(4)).unwrap();
+    let mut players = vec![
+        Player::new(&table, String::from("Andrija"), seed(1)).unwrap(),
+        Player::new(&table, String::from("Kobi"), seed(2)).unwrap(),
+    ];
+
+    for player in &players {
+        table.join(player).unwrap();
+        copy.join(player).unwrap();
+    }
+    table.start().unwrap();
+    copy.start().unwrap();
+
+    // Each table verifies the shuffle made on the other one
+    copy.apply_shuffle(&table.shuffle().unwrap()).unwrap();
+    table.apply_shuffle(&copy.shuffle().unwrap()).unwrap();
+
+    let all = players
+        .iter_mut()
+        .map(|player| player.compute_reveal_token(&table, 0).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        copy.open_card(0, tokens(all.clone())).unwrap(),
+        table.open_card(0, tokens(all)).unwrap()
+    );
+
+    let other_cards = Table::new(2, 13, seed(5)).unwrap().export_cards().unwrap();
+    assert!(Table::from_export(&parameters, &other_cards, None).is_err());
+}
@@ -4,9 +4,74 @@
 /* auto-generated by NAPI-RS */
 
 export declare function setupProtocol(m: number, n: number): number
+/**
+ * The N-API surface for a player. The secret key never leaves the Rust side: JS only ever sees
+ * the player's name, public key and serialized reveal tokens.
+ */
 export declare class Player {
-  static new(rng: R, pp: CardParameters, name: Array<number>): Player
-  receiveCard(card: MaskedCard): void
-  peekAtCard(parameters: CardParameters, revealTokens: Array<[RevealToken, RevealProof, PublicKey]>, cardMappings: Record<Card, ClassicPlayingCard>, card: MaskedCard): void
-  computeRevealToken(rng: R, pp: CardParameters, card: MaskedCard): [RevealToken, RevealProof, PublicKey]
+  constructor(table: Table, name: string)
+  get name(): string
+  publicKey(): Buffer
+  /** Serialized `(token, proof, public key)` triple for the card at `index` of the table deck. */
+  computeRevealToken(table: Table, index: number): Buffer
+  /** Privately open a card dealt to this player using the reveal tokens of every other player. */
+  peekAtCard(table: Table, index: number, revealTokens: Array<Buffer>): string
+  /** The cards this player has opened so far, `null` for cards that are still masked. */
+  openedCards(): Array<string | undefined | null>
+}
+/**
+ * A table holds the public state every player agrees on: the protocol parameters, the card
+ * encoding, the registered players, the current deck and who owns or has opened each card.
+ */
+export declare class Table {
+  constructor(m: number, n: number)
+  /**
+   * Set up the table another player created, from the bytes of its `exportParameters` and
+   * `exportCards`.
+   */
+  static fromExport(parameters: Buffer, cards: Buffer): Table
+  /** The serialized protocol parameters, to be sent to the other players with `exportCards`. */
+  exportParameters(): Buffer
+  /** The serialized plaintexts of the cards, in card order. */
+  exportCards(): Buffer
+  /** Register a player and verify their proof of key ownership. Returns the player's seat. */
+  join(player: Player): number
+  /** Compute the aggregate key of all joined players and mask the initial deck with it. */
+  start(): void
+  /**
+   * Shuffle the deck locally. Returns the serialized `(deck, proof)` pair so that it can be
+   * broadcast to the other players, who adopt it with `applyShuffle`.
+   */
+  shuffle(): Buffer
+  applyShuffle(shuffle: Buffer): void
+  /** Give the card at `index` of the deck to the player sitting at `seat`. */
+  deal(player: Player, seat: number, index: number): void
+  /** Publicly open the card at `index` using the reveal tokens of every player. */
+  openCard(index: number, revealTokens: Array<Buffer>): string
+  /** Start passing the cards at `indices` from `sender`, sitting at `from`, face down to `to`. */
+  passCards(sender: Player, from: number, to: number, indices: Array<number>): void
+  /** Seat that remasks the cards being passed next, `null` when no cards are being passed. */
+  get nextRemasker(): number | null
+  /**
+   * Remask the cards being passed as `seat`. Returns the serialized `(cards, proofs)` pair so
+   * that it can be broadcast to the other players, who adopt it with `applyRemask`.
+   */
+  remaskPassed(seat: number): Buffer
+  applyRemask(seat: number, remask: Buffer): void
+  /**
+   * Give `player`, sitting at `seat`, the cards passed to it and take back the ones it passed.
+   * Returns the positions of the cards received.
+   */
+  receiveCards(player: Player, seat: number): Array<number>
+  get deckSize(): number
+  /** The seat holding the card at `index`, `null` if the card has not been dealt. */
+  owner(index: number): number | null
+  /**
+   * Share the pots at showdown. `contributions` and `folded` hold one entry per seat; hands are
+   * made of each seat's cards and the `board` positions, as opened with `openCard`. Returns the
+   * chips won by every seat.
+   */
+  settle(board: Array<number>, contributions: Array<number>, folded: Array<boolean>, button: number): Array<number>
+  /** The publicly opened cards of the deck, `null` for cards that are still masked. */
+  openedCards(): Array<string | undefined | null>
 }
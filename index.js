@@ -310,7 +310,8 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { setupProtocol, Player } = nativeBinding
+const { setupProtocol, Player, Table } = nativeBinding
 
 module.exports.setupProtocol = setupProtocol
 module.exports.Player = Player
+module.exports.Table = Table
@@ -0,0 +1,603 @@
+//! Betting for a single poker hand.
+//!
+//! Seats are the positions of the protocol players at the `Table`, and action moves clockwise,
+//! that is towards increasing seat indices. A `Betting` lives for a whole hand: each street is
+//! opened with `start_round`, blinds are posted on the first one, and the chips every seat put
+//! in over the hand are kept for the pot manager.
+
+use crate::GameErrors;
+
+/// Fixed-limit betting allows this many bets and raises together on each street: a bet and three
+/// raises. The big blind counts as the opening bet preflop.
+pub const FIXED_LIMIT_CAP: u32 = 4;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BettingLimit {
+  NoLimit,
+  PotLimit,
+  /// Bets and raises are `small_bet` preflop and on the flop, `big_bet` on the turn and river.
+  FixedLimit {
+    small_bet: u64,
+    big_bet: u64,
+  },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Street {
+  Preflop,
+  Flop,
+  Turn,
+  River,
+}
+
+/// Bet and raise amounts are the seat's total bet on the street once the action is done.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+  Fold,
+  Check,
+  Call,
+  Bet(u64),
+  Raise(u64),
+  AllIn,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SeatStatus {
+  Active,
+  Folded,
+  AllIn,
+}
+
+#[derive(Debug, Clone)]
+struct Seat {
+  stack: u64,
+  street_bet: u64,
+  contributed: u64,
+  status: SeatStatus,
+  // Still has to act before the street can close
+  pending: bool,
+  // Acted since the last full bet or raise, so an incomplete all-in does not let it raise again
+  acted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Betting {
+  limit: BettingLimit,
+  big_blind: u64,
+  seats: Vec<Seat>,
+  street: Street,
+  current_bet: u64,
+  min_raise: u64,
+  // Bets and raises so far on this street, capped at `FIXED_LIMIT_CAP` in fixed-limit games
+  raises: u32,
+  aggressor: Option<usize>,
+  to_act: Option<usize>,
+}
+
+impl Betting {
+  pub fn new(stacks: Vec<u64>, limit: BettingLimit, big_blind: u64) -> Self {
+    let seats = stacks
+      .into_iter()
+      .map(|stack| Seat {
+        stack,
+        street_bet: 0,
+        contributed: 0,
+        status: if stack > 0 {
+          SeatStatus::Active
+        } else {
+          SeatStatus::AllIn
+        },
+        pending: false,
+        acted: false,
+      })
+      .collect();
+
+    Self {
+      limit,
+      big_blind,
+      seats,
+      street: Street::Preflop,
+      current_bet: 0,
+      min_raise: big_blind,
+      raises: 0,
+      aggressor: None,
+      to_act: None,
+    }
+  }
+
+  /// Open the betting on `street`. Action starts at `first_to_act`, or at the next seat after
+  /// it that can still act.
+  pub fn start_round(&mut self, street: Street, first_to_act: usize) {
+    self.street = street;
+    self.current_bet = 0;
+    self.min_raise = self.bet_unit();
+    self.raises = 0;
+    self.aggressor = None;
+    for seat in self.seats.iter_mut() {
+      seat.street_bet = 0;
+      seat.acted = false;
+      seat.pending = seat.status == SeatStatus::Active;
+    }
+
+    self.to_act = None;
+    if self.can_act(first_to_act) {
+      self.to_act = Some(first_to_act);
+    } else {
+      self.to_act = self.next_to_act(first_to_act);
+    }
+    self.close_if_settled();
+  }
+
+  /// Post a forced bet. A seat that cannot cover it goes all-in, but the amount to call stays
+  /// the full blind. Posting does not count as acting, so the big blind keeps its option.
+  pub fn post_blind(&mut self, seat: usize, amount: u64) -> Result<(), GameErrors> {
+    self.check_seat(seat)?;
+    self.put_in(seat, amount.min(self.seats[seat].stack));
+    self.current_bet = self.current_bet.max(amount);
+    if amount >= self.bet_unit() {
+      // The big blind is the opening bet of the street
+      self.raises = self.raises.max(1);
+    }
+    if self.to_act == Some(seat) && !self.can_act(seat) {
+      self.to_act = self.next_to_act(seat);
+    }
+    self.close_if_settled();
+    Ok(())
+  }
+
+  /// Post an ante. It goes into the pot without counting as a bet on the street.
+  pub fn post_ante(&mut self, seat: usize, amount: u64) -> Result<(), GameErrors> {
+    self.check_seat(seat)?;
+    let amount = amount.min(self.seats[seat].stack);
+    self.put_in(seat, amount);
+    self.seats[seat].street_bet -= amount;
+    Ok(())
+  }
+
+  /// Post a stud bring-in, a forced bet that may be smaller than the opening bet. It counts as
+  /// the seat's action, and the next players call it or complete it to a full bet.
+  pub fn post_bring_in(&mut self, seat: usize, amount: u64) -> Result<(), GameErrors> {
+    self.check_seat(seat)?;
+    self.put_in(seat, amount.min(self.seats[seat].stack));
+    self.current_bet = self.current_bet.max(amount);
+    if amount >= self.bet_unit() {
+      self.raises = self.raises.max(1);
+    } else {
+      // Completing to the opening bet is a full raise
+      self.min_raise = self.bet_unit() - amount;
+    }
+
+    self.seats[seat].pending = false;
+    self.seats[seat].acted = true;
+    if self.to_act == Some(seat) {
+      self.to_act = self.next_to_act(seat);
+    }
+    self.close_if_settled();
+    Ok(())
+  }
+
+  pub fn act(&mut self, seat: usize, action: Action) -> Result<(), GameErrors> {
+    if self.to_act != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32));
+    }
+
+    let to_call = self.to_call(seat);
+    match action {
+      Action::Fold => {
+        self.seats[seat].status = SeatStatus::Folded;
+      }
+      Action::Check => {
+        if to_call > 0 {
+          return Err(GameErrors::IllegalAction("check facing a bet"));
+        }
+      }
+      Action::Call => {
+        if to_call == 0 {
+          return Err(GameErrors::IllegalAction("call without a bet"));
+        }
+        self.put_in(seat, to_call.min(self.seats[seat].stack));
+      }
+      Action::Bet(amount) => {
+        if self.current_bet > 0 {
+          return Err(GameErrors::IllegalAction("bet facing a bet"));
+        }
+        self.raise_to(seat, amount)?;
+      }
+      Action::Raise(amount) => {
+        if self.current_bet == 0 {
+          return Err(GameErrors::IllegalAction("raise without a bet"));
+        }
+        self.raise_to(seat, amount)?;
+      }
+      Action::AllIn => {
+        let all_in = self.seats[seat].street_bet + self.seats[seat].stack;
+        if all_in <= self.current_bet {
+          self.put_in(seat, self.seats[seat].stack);
+        } else {
+          self.raise_to(seat, all_in)?;
+        }
+      }
+    }
+
+    self.seats[seat].pending = false;
+    self.seats[seat].acted = true;
+    self.to_act = self.next_to_act(seat);
+    self.close_if_settled();
+    Ok(())
+  }
+
+  /// Seat whose action is awaited, `None` once the street is closed.
+  pub fn to_act(&self) -> Option<usize> {
+    self.to_act
+  }
+
+  pub fn is_round_over(&self) -> bool {
+    self.to_act.is_none()
+  }
+
+  /// Chips `seat` has to add to stay in the hand, before capping at its stack.
+  pub fn to_call(&self, seat: usize) -> u64 {
+    self.current_bet.saturating_sub(self.seats[seat].street_bet)
+  }
+
+  /// Smallest total street bet a seat may bet or raise to, short of going all-in.
+  pub fn min_raise_to(&self) -> u64 {
+    if self.current_bet == 0 {
+      self.bet_unit()
+    } else {
+      self.current_bet + self.min_raise
+    }
+  }
+
+  /// Largest total street bet `seat` may bet or raise to under the betting limit and its stack.
+  pub fn max_raise_to(&self, seat: usize) -> u64 {
+    let all_in = self.seats[seat].street_bet + self.seats[seat].stack;
+    let limit = match self.limit {
+      BettingLimit::NoLimit => all_in,
+      // The raise may be as large as the pot after calling
+      BettingLimit::PotLimit => self.current_bet + self.pot() + self.to_call(seat),
+      BettingLimit::FixedLimit { .. } => self.min_raise_to(),
+    };
+    limit.min(all_in)
+  }
+
+  /// Seat that made the last bet or raise of the street, which shows first at showdown.
+  pub fn last_aggressor(&self) -> Option<usize> {
+    self.aggressor
+  }
+
+  pub fn street(&self) -> Street {
+    self.street
+  }
+
+  pub fn current_bet(&self) -> u64 {
+    self.current_bet
+  }
+
+  /// Every chip put in so far, on all streets.
+  pub fn pot(&self) -> u64 {
+    self.seats.iter().map(|seat| seat.contributed).sum()
+  }
+
+  pub fn stack(&self, seat: usize) -> u64 {
+    self.seats[seat].stack
+  }
+
+  pub fn stacks(&self) -> Vec<u64> {
+    self.seats.iter().map(|seat| seat.stack).collect()
+  }
+
+  /// Chips each seat put in over the whole hand, the input of the side-pot computation.
+  pub fn contributions(&self) -> Vec<u64> {
+    self.seats.iter().map(|seat| seat.contributed).collect()
+  }
+
+  pub fn status(&self, seat: usize) -> SeatStatus {
+    self.seats[seat].status
+  }
+
+  /// Seats that have not folded.
+  pub fn live_seats(&self) -> Vec<usize> {
+    (0..self.seats.len())
+      .filter(|seat| self.seats[*seat].status != SeatStatus::Folded)
+      .collect()
+  }
+
+  /// Add winnings to a seat's stack.
+  pub fn award(&mut self, seat: usize, amount: u64) {
+    self.seats[seat].stack += amount;
+  }
+
+  fn bet_unit(&self) -> u64 {
+    match (self.limit, self.street) {
+      (BettingLimit::FixedLimit { small_bet, .. }, Street::Preflop | Street::Flop) => small_bet,
+      (BettingLimit::FixedLimit { big_bet, .. }, Street::Turn | Street::River) => big_bet,
+      _ => self.big_blind,
+    }
+  }
+
+  fn raise_to(&mut self, seat: usize, amount: u64) -> Result<(), GameErrors> {
+    if self.seats[seat].acted {
+      // Only an incomplete all-in raise can bring action back to a seat that already acted
+      return Err(GameErrors::IllegalAction("raise after an incomplete raise"));
+    }
+    if let BettingLimit::FixedLimit { .. } = self.limit {
+      if self.raises >= FIXED_LIMIT_CAP {
+        return Err(GameErrors::IllegalAction(
+          "raise after the betting is capped",
+        ));
+      }
+    }
+
+    let all_in = self.seats[seat].street_bet + self.seats[seat].stack;
+    let max = self.max_raise_to(seat);
+    // Going all-in for less than a full raise is always allowed
+    let min = self.min_raise_to().min(all_in);
+    if amount < min || amount > max || amount <= self.current_bet {
+      return Err(GameErrors::InvalidAmount(amount, min, max));
+    }
+
+    let raise = amount - self.current_bet;
+    let full = raise >= self.min_raise;
+    self.put_in(seat, amount - self.seats[seat].street_bet);
+    self.current_bet = amount;
+    self.aggressor = Some(seat);
+    if full {
+      // A completed bring-in still sets the raise size to a full bet
+      self.min_raise = raise.max(self.bet_unit());
+      self.raises += 1;
+    }
+
+    for (i, other) in self.seats.iter_mut().enumerate() {
+      if i != seat && other.status == SeatStatus::Active {
+        other.pending = true;
+        if full {
+          other.acted = false;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn put_in(&mut self, seat: usize, amount: u64) {
+    let seat = &mut self.seats[seat];
+    seat.stack -= amount;
+    seat.street_bet += amount;
+    seat.contributed += amount;
+    if seat.stack == 0 {
+      seat.status = SeatStatus::AllIn;
+      seat.pending = false;
+    }
+  }
+
+  fn can_act(&self, seat: usize) -> bool {
+    let seat = &self.seats[seat];
+    seat.status == SeatStatus::Active && seat.pending
+  }
+
+  fn next_to_act(&self, seat: usize) -> Option<usize> {
+    let n = self.seats.len();
+    (1..=n).map(|i| (seat + i) % n).find(|i| self.can_act(*i))
+  }
+
+  fn close_if_settled(&mut self) {
+    let live = self
+      .seats
+      .iter()
+      .filter(|seat| seat.status != SeatStatus::Folded)
+      .count();
+    let active = self
+      .seats
+      .iter()
+      .filter(|seat| seat.status == SeatStatus::Active)
+      .count();
+    let facing_bet = self
+      .seats
+      .iter()
+      .any(|seat| seat.status == SeatStatus::Active && seat.street_bet < self.current_bet);
+
+    // One hand left wins uncontested, and a lone active seat has nobody left to bet against
+    if live <= 1 || (active <= 1 && !facing_bet) {
+      self.to_act = None;
+    }
+  }
+
+  fn check_seat(&self, seat: usize) -> Result<(), GameErrors> {
+    match self.seats.get(seat) {
+      Some(_) => Ok(()),
+      None => Err(GameErrors::InvalidSeat(seat as u32)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn preflop(stacks: Vec<u64>, limit: BettingLimit) -> Betting {
+    // Button on seat 0, blinds 1/2 on seats 1 and 2
+    let mut betting = Betting::new(stacks.clone(), limit, 2);
+    betting.start_round(Street::Preflop, 3 % stacks.len());
+    betting.post_blind(1, 1).unwrap();
+    betting.post_blind(2, 2).unwrap();
+    betting
+  }
+
+  #[test]
+  fn big_blind_keeps_its_option() {
+    let mut betting = preflop(vec![100, 100, 100, 100], BettingLimit::NoLimit);
+    assert_eq!(betting.to_act(), Some(3));
+    assert_eq!(
+      betting.act(0, Action::Call),
+      Err(GameErrors::NotYourTurn(0))
+    );
+
+    betting.act(3, Action::Call).unwrap();
+    betting.act(0, Action::Call).unwrap();
+    betting.act(1, Action::Call).unwrap();
+    assert_eq!(betting.to_act(), Some(2));
+    assert_eq!(
+      betting.act(2, Action::Call),
+      Err(GameErrors::IllegalAction("call without a bet"))
+    );
+    betting.act(2, Action::Check).unwrap();
+    assert!(betting.is_round_over());
+    assert_eq!(betting.pot(), 8);
+
+    betting.start_round(Street::Flop, 1);
+    assert_eq!(betting.to_act(), Some(1));
+    assert_eq!(betting.min_raise_to(), 2);
+  }
+
+  #[test]
+  fn enforces_the_minimum_raise() {
+    let mut betting = preflop(vec![100, 100, 100, 100], BettingLimit::NoLimit);
+    assert_eq!(
+      betting.act(3, Action::Raise(3)),
+      Err(GameErrors::InvalidAmount(3, 4, 100))
+    );
+    betting.act(3, Action::Raise(6)).unwrap();
+    // The last raise was 4, so the next one must be to at least 10
+    assert_eq!(betting.min_raise_to(), 10);
+    assert_eq!(
+      betting.act(0, Action::Raise(9)),
+      Err(GameErrors::InvalidAmount(9, 10, 100))
+    );
+    betting.act(0, Action::Raise(10)).unwrap();
+    betting.act(1, Action::Fold).unwrap();
+    betting.act(2, Action::Fold).unwrap();
+    betting.act(3, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+    assert_eq!(betting.last_aggressor(), Some(0));
+    assert_eq!(betting.contributions(), vec![10, 1, 2, 10]);
+    assert_eq!(betting.live_seats(), vec![0, 3]);
+  }
+
+  #[test]
+  fn incomplete_all_in_does_not_reopen_the_betting() {
+    let mut betting = preflop(vec![100, 100, 100, 13], BettingLimit::NoLimit);
+    betting.act(3, Action::Fold).unwrap();
+    betting.act(0, Action::Raise(10)).unwrap();
+    betting.act(1, Action::Fold).unwrap();
+    betting.act(2, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+
+    let mut betting = preflop(vec![100, 100, 100, 13], BettingLimit::NoLimit);
+    betting.act(3, Action::Call).unwrap();
+    betting.act(0, Action::Raise(10)).unwrap();
+    betting.act(1, Action::Fold).unwrap();
+    betting.act(2, Action::Call).unwrap();
+    // Raising to 13 is less than a full raise of 8
+    betting.act(3, Action::AllIn).unwrap();
+    assert_eq!(betting.status(3), SeatStatus::AllIn);
+    assert_eq!(betting.to_act(), Some(0));
+    assert_eq!(
+      betting.act(0, Action::Raise(30)),
+      Err(GameErrors::IllegalAction("raise after an incomplete raise"))
+    );
+    betting.act(0, Action::Call).unwrap();
+    betting.act(2, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+    assert_eq!(betting.contributions(), vec![13, 1, 13, 13]);
+  }
+
+  #[test]
+  fn short_blind_goes_all_in() {
+    let mut betting = preflop(vec![100, 100, 1], BettingLimit::NoLimit);
+    assert_eq!(betting.status(2), SeatStatus::AllIn);
+    assert_eq!(betting.to_call(0), 2);
+    betting.act(0, Action::Call).unwrap();
+    betting.act(1, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+
+    // Nobody is left to bet against once all but one seat are all-in
+    betting.start_round(Street::Flop, 1);
+    assert_eq!(betting.to_act(), Some(1));
+    betting.act(1, Action::AllIn).unwrap();
+    betting.act(0, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+    betting.start_round(Street::Turn, 1);
+    assert!(betting.is_round_over());
+  }
+
+  #[test]
+  fn antes_and_bring_in() {
+    let limit = BettingLimit::FixedLimit {
+      small_bet: 4,
+      big_bet: 8,
+    };
+    let mut betting = Betting::new(vec![100, 100, 100], limit, 4);
+    for seat in 0..3 {
+      betting.post_ante(seat, 1).unwrap();
+    }
+    betting.start_round(Street::Preflop, 2);
+    betting.post_bring_in(1, 2).unwrap();
+    assert_eq!(betting.pot(), 5);
+    assert_eq!(betting.to_act(), Some(2));
+
+    // Completing the bring-in is a raise to the small bet, the next raise adds a full one
+    assert_eq!(betting.min_raise_to(), 4);
+    assert_eq!(
+      betting.act(2, Action::Raise(6)),
+      Err(GameErrors::InvalidAmount(6, 4, 4))
+    );
+    betting.act(2, Action::Raise(4)).unwrap();
+    assert_eq!(betting.min_raise_to(), 8);
+    betting.act(0, Action::Call).unwrap();
+    betting.act(1, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+    assert_eq!(betting.contributions(), vec![5, 5, 5]);
+
+    // When everyone calls the bring-in, its owner does not act again
+    let mut betting = Betting::new(vec![100, 100, 100], limit, 4);
+    betting.start_round(Street::Preflop, 1);
+    betting.post_bring_in(0, 2).unwrap();
+    betting.act(1, Action::Call).unwrap();
+    betting.act(2, Action::Call).unwrap();
+    assert!(betting.is_round_over());
+  }
+
+  #[test]
+  fn pot_limit_caps_the_raise_at_the_pot() {
+    let mut betting = preflop(vec![100, 100, 100, 100], BettingLimit::PotLimit);
+    // Calling 2 makes the pot 5, so the largest raise is to 2 + 5
+    assert_eq!(betting.max_raise_to(3), 7);
+    assert_eq!(
+      betting.act(3, Action::Raise(8)),
+      Err(GameErrors::InvalidAmount(8, 4, 7))
+    );
+    betting.act(3, Action::Raise(7)).unwrap();
+    // 10 in the pot and 7 to call: up to 7 + 17
+    assert_eq!(betting.max_raise_to(0), 24);
+  }
+
+  #[test]
+  fn fixed_limit_sizes_and_caps_raises() {
+    let limit = BettingLimit::FixedLimit {
+      small_bet: 2,
+      big_bet: 4,
+    };
+    let mut betting = preflop(vec![100, 100, 100, 100], limit);
+    assert_eq!(
+      betting.act(3, Action::Raise(6)),
+      Err(GameErrors::InvalidAmount(6, 4, 4))
+    );
+    // The big blind counts as the first bet
+    betting.act(3, Action::Raise(4)).unwrap();
+    betting.act(0, Action::Raise(6)).unwrap();
+    betting.act(1, Action::Raise(8)).unwrap();
+    assert_eq!(
+      betting.act(2, Action::Raise(10)),
+      Err(GameErrors::IllegalAction(
+        "raise after the betting is capped"
+      ))
+    );
+    betting.act(2, Action::Call).unwrap();
+
+    let mut betting = Betting::new(vec![100, 100], limit, 2);
+    betting.start_round(Street::Turn, 0);
+    assert_eq!(betting.min_raise_to(), 4);
+    betting.act(0, Action::Bet(4)).unwrap();
+    assert_eq!(betting.max_raise_to(1), 8);
+  }
+}
@@ -0,0 +1,1058 @@
+//! Blackjack dealt from a multi-deck shoe, with the house as one of the key holders.
+//!
+//! `BlackjackTable` is the public state of the table that the house and every player keep in
+//! sync. The house holds a key like any player, so it takes part in `compute_aggregate_key`, in
+//! every shuffle of the shoe and in every reveal, and cannot open a card on its own. The house
+//! sits after the players, as seat `num_of_players()`.
+//!
+//! A shoe of `decks` decks holds several copies of each card, each under its own plaintext (see
+//! `encode_cards`), and is masked in `deck_order` so that everyone builds the same one. Once
+//! every key holder has shuffled it, a card is burned and rounds are dealt from it until the cut
+//! card comes out, `cut_card` cards from the end. The round where it comes out is finished, and
+//! the shoe is shuffled again before the next one.
+//!
+//! Player cards are dealt face up. The house gets one card up and its hole card face down, which
+//! only the house can peek at. When its up card is an Ace or worth ten, the house peeks and
+//! declares whether it has blackjack before the players act. Declaring one opens the hole card
+//! right away. The hole card of a house that declared none is opened at the end of the round
+//! like any other: if it did make blackjack after all, each player only loses its original bet,
+//! so a false declaration never wins the house more than an honest one.
+//!
+//! Players then hit, stand, double or split in seat order, and the house draws to 17, hitting
+//! a soft 17 if the table says so. Blackjack pays 3 to 2, rounded down.
+
+use crate::draw::DrawDeck;
+use crate::texas_holdem::Variant;
+use crate::zones::Visibility;
+use crate::{
+  deck_order, Card, CardParameters, CardProtocol, ClassicPlayingCard, GameErrors, MaskedCard,
+  ProofKeyOwnership, PublicKey, RevealShare, Scalar, ShuffleProof, Value,
+};
+
+use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use std::collections::HashMap;
+
+pub const MAX_PLAYERS: usize = 7;
+
+/// A seat may split up to this many hands.
+pub const MAX_HANDS: usize = 4;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BlackjackConfig {
+  /// Decks in the shoe, usually 6 or 8.
+  pub decks: usize,
+  /// Cards left behind the cut card. There must be enough of them to finish the round where it
+  /// comes out, whatever the players draw.
+  pub cut_card: usize,
+  pub min_bet: u64,
+  pub max_bet: u64,
+  pub dealer_hits_soft_17: bool,
+}
+
+impl BlackjackConfig {
+  /// Protocol parameters `(m, n)` to set up for the shoe, with `m * n` cards.
+  pub fn protocol_shape(&self) -> (usize, usize) {
+    (2 * self.decks, 26)
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Move {
+  Hit,
+  Stand,
+  /// Double the bet on the first two cards and take exactly one more card.
+  Double,
+  /// Split two cards of the same value into two hands with a bet each.
+  Split,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+  /// The house and every player shuffle and remask the shoe in turn.
+  Shuffle,
+  /// Players place their bets.
+  Bet,
+  /// Key holders hand out reveal tokens for the first two cards of every hand.
+  Deal,
+  /// The house declares whether its hole card makes blackjack.
+  Peek,
+  /// Players play their hands in turn.
+  Play,
+  /// The house opens its hole card and draws.
+  Dealer,
+  Complete,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Awaiting {
+  Shuffle { seat: usize },
+  Bet { seat: usize },
+  RevealToken { seat: usize, card: usize },
+  Peek,
+  Move { seat: usize, hand: usize },
+}
+
+/// Blackjack total of `cards`, and whether it is soft: an Ace counts 11 without busting it.
+pub fn total(cards: &[ClassicPlayingCard]) -> (u32, bool) {
+  let mut total = 0;
+  let mut aces = 0;
+  for card in cards {
+    total += points(card.value);
+    if card.value == Value::Ace {
+      aces += 1;
+    }
+  }
+  while total > 21 && aces > 0 {
+    total -= 10;
+    aces -= 1;
+  }
+
+  (total, aces > 0)
+}
+
+fn points(value: Value) -> u32 {
+  match value {
+    Value::Ace => 11,
+    Value::King | Value::Queen | Value::Jack | Value::Ten => 10,
+    // Two is 2, Three is 3 and so on up to Nine
+    _ => value as u32 + 2,
+  }
+}
+
+// The most cards `hands` hands can take from `shoe` in a round. Before its last card a hand is
+// worth 20 or less with its Aces as one, so the other cards of the round are at most the lowest
+// cards of the shoe that add up to 20 for each hand.
+fn max_round_cards(shoe: &[ClassicPlayingCard], hands: usize) -> usize {
+  let mut hard_points: Vec<u32> = shoe
+    .iter()
+    .map(|card| match card.value {
+      Value::Ace => 1,
+      value => points(value),
+    })
+    .collect();
+  hard_points.sort_unstable();
+
+  let mut left = 20 * hands as u32;
+  let before_last = hard_points
+    .into_iter()
+    .take_while(|points| {
+      let fits = *points <= left;
+      left = left.saturating_sub(*points);
+      fits
+    })
+    .count();
+  (before_last + hands).min(shoe.len())
+}
+
+/// One hand of a seat, several after a split.
+#[derive(Debug, Clone)]
+pub struct Hand {
+  cards: Vec<usize>,
+  bet: u64,
+  doubled: bool,
+  split: bool,
+  done: bool,
+}
+
+impl Hand {
+  fn new(cards: Vec<usize>, bet: u64, split: bool) -> Self {
+    Self {
+      cards,
+      bet,
+      doubled: false,
+      split,
+      done: false,
+    }
+  }
+
+  /// Ids of the cards of the hand in the shoe.
+  pub fn cards(&self) -> &[usize] {
+    &self.cards
+  }
+
+  pub fn bet(&self) -> u64 {
+    self.bet
+  }
+
+  pub fn is_doubled(&self) -> bool {
+    self.doubled
+  }
+
+  /// Whether the hand came out of a split, so that 21 on two cards is not a blackjack.
+  pub fn is_split(&self) -> bool {
+    self.split
+  }
+
+  pub fn is_done(&self) -> bool {
+    self.done
+  }
+}
+
+struct Seat {
+  stack: u64,
+  bet: Option<u64>,
+  hands: Vec<Hand>,
+}
+
+pub struct BlackjackTable<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+  joint_pk: PublicKey,
+  // The players in seat order, then the house
+  key_holders: Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)>,
+  config: BlackjackConfig,
+  phase: Phase,
+  shuffles: usize,
+  // The shoe being shuffled, until it is handed to `cards` for dealing
+  shoe: Vec<MaskedCard>,
+  cards: Option<DrawDeck<'a>>,
+  cut_card_out: bool,
+  seats: Vec<Seat>,
+  dealer_cards: Vec<usize>,
+  declared_blackjack: Option<bool>,
+  payouts: Option<Vec<u64>>,
+}
+
+impl<'a> BlackjackTable<'a> {
+  /// Open a table between `players`, listed in seat order with their proofs of key ownership,
+  /// and the `house`. `card_mapping` must hold `config.decks` copies of the 52 cards.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    house: (PublicKey, ProofKeyOwnership, Vec<u8>),
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    stacks: Vec<u64>,
+    config: BlackjackConfig,
+  ) -> anyhow::Result<Self> {
+    let num_of_players = players.len();
+    let mut expected: Vec<ClassicPlayingCard> = (0..config.decks)
+      .flat_map(|_| Variant::Holdem.deck())
+      .collect();
+    expected.sort();
+    if config.decks == 0
+      || !deck_order(card_mapping)
+        .into_iter()
+        .map(|(_, classic)| *classic)
+        .eq(expected.iter().copied())
+    {
+      return Err(GameErrors::WrongDeck(expected.len() as u32).into());
+    }
+    if !(1..=MAX_PLAYERS).contains(&num_of_players) {
+      return Err(GameErrors::InvalidPlayerCount(num_of_players as u32).into());
+    }
+    if stacks.len() != num_of_players {
+      return Err(GameErrors::WrongSeatCount(num_of_players as u32).into());
+    }
+    if config.min_bet == 0 || config.min_bet > config.max_bet {
+      return Err(GameErrors::InvalidAmount(config.min_bet, 1, config.max_bet).into());
+    }
+    let most_hands = num_of_players * MAX_HANDS + 1;
+    if config.cut_card < max_round_cards(&expected, most_hands) || config.cut_card >= expected.len()
+    {
+      return Err(GameErrors::NotEnoughCards(expected.len()).into());
+    }
+
+    let mut key_holders = players.to_vec();
+    key_holders.push(house);
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &key_holders)?;
+
+    let mut table = Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      key_holders,
+      config,
+      phase: Phase::Shuffle,
+      shuffles: 0,
+      shoe: vec![],
+      cards: None,
+      cut_card_out: false,
+      seats: stacks
+        .into_iter()
+        .map(|stack| Seat {
+          stack,
+          bet: None,
+          hands: vec![],
+        })
+        .collect(),
+      dealer_cards: vec![],
+      declared_blackjack: None,
+      payouts: None,
+    };
+    table.shoe = table.initial_shoe(rng)?;
+    Ok(table)
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.seats.len()
+  }
+
+  /// Seat of the house in the shuffles and reveal tokens, after the players.
+  pub fn house(&self) -> usize {
+    self.seats.len()
+  }
+
+  pub fn stacks(&self) -> Vec<u64> {
+    self.seats.iter().map(|seat| seat.stack).collect()
+  }
+
+  /// Hands of `seat` in the current round.
+  pub fn hands(&self, seat: usize) -> &[Hand] {
+    self
+      .seats
+      .get(seat)
+      .map(|seat| seat.hands.as_slice())
+      .unwrap_or_default()
+  }
+
+  /// Ids of the house cards in the current round, the up card first and the hole card second.
+  pub fn dealer_cards(&self) -> &[usize] {
+    &self.dealer_cards
+  }
+
+  /// The cards in play, once every key holder has shuffled the shoe. The house peeks at its
+  /// hole card with the reveal tokens it holds.
+  pub fn cards(&self) -> Option<&DrawDeck<'a>> {
+    self.cards.as_ref()
+  }
+
+  /// The opened cards among `ids`, `None` while one of them is still masked.
+  pub fn opened(&self, ids: &[usize]) -> Option<Vec<ClassicPlayingCard>> {
+    let cards = self.cards.as_ref()?;
+    ids.iter().map(|id| cards.opened_card(*id)).collect()
+  }
+
+  /// Whether the cut card came out in this round, so the shoe is shuffled before the next one.
+  pub fn cut_card_out(&self) -> bool {
+    self.cut_card_out
+  }
+
+  /// What the house declared after peeking, `None` if it did not have to peek.
+  pub fn declared_blackjack(&self) -> Option<bool> {
+    self.declared_blackjack
+  }
+
+  /// Chips returned to every player at the end of the round, stakes included.
+  pub fn payouts(&self) -> Option<&[u64]> {
+    self.payouts.as_deref()
+  }
+
+  /// Seat that shuffles next. Players shuffle in seat order and the house last.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Shuffle => Some(self.shuffles),
+      _ => None,
+    }
+  }
+
+  /// The hand whose move is awaited, as a seat and an index in its hands.
+  pub fn turn(&self) -> Option<(usize, usize)> {
+    if self.phase != Phase::Play || !self.awaited_tokens().is_empty() {
+      return None;
+    }
+    self.seats.iter().enumerate().find_map(|(i, seat)| {
+      seat
+        .hands
+        .iter()
+        .position(|hand| !hand.done)
+        .map(|hand| (i, hand))
+    })
+  }
+
+  /// Everything the table needs before it can move on.
+  pub fn awaiting(&self) -> Vec<Awaiting> {
+    let tokens = self.awaited_tokens();
+    if !tokens.is_empty() {
+      return tokens;
+    }
+
+    match self.phase {
+      Phase::Shuffle => self
+        .next_shuffler()
+        .map(|seat| Awaiting::Shuffle { seat })
+        .into_iter()
+        .collect(),
+      Phase::Bet => (0..self.num_of_players())
+        .filter(|seat| self.seats[*seat].bet.is_none())
+        .map(|seat| Awaiting::Bet { seat })
+        .collect(),
+      Phase::Peek => vec![Awaiting::Peek],
+      Phase::Play => self
+        .turn()
+        .map(|(seat, hand)| Awaiting::Move { seat, hand })
+        .into_iter()
+        .collect(),
+      _ => vec![],
+    }
+  }
+
+  /// Shuffle and remask the shoe, as the next shuffler does locally before sending the result
+  /// to the others.
+  pub fn shuffle_shoe<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let num_of_cards = self.shoe.len();
+    let permutation = Permutation::new(rng, num_of_cards);
+    let masking_factors: Vec<Scalar> = sample_vector(rng, num_of_cards);
+
+    Ok(CardProtocol::shuffle_and_remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      &self.shoe,
+      &masking_factors,
+      &permutation,
+    )?)
+  }
+
+  /// Verify and adopt the shuffle of `seat`.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    shuffled_shoe: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    CardProtocol::verify_shuffle(
+      self.parameters,
+      &self.joint_pk,
+      &self.shoe,
+      &shuffled_shoe,
+      shuffle_proof,
+    )?;
+
+    self.shoe = shuffled_shoe;
+    self.shuffles += 1;
+    self.advance()
+  }
+
+  /// Place the bet of `seat` for the round, or sit it out with 0.
+  pub fn bet(&mut self, seat: usize, amount: u64) -> anyhow::Result<()> {
+    let awaited = Awaiting::Bet { seat };
+    if !self.awaiting().contains(&awaited) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    let max = self.config.max_bet.min(self.seats[seat].stack);
+    if amount != 0 && (amount < self.config.min_bet || amount > max) {
+      return Err(GameErrors::InvalidAmount(amount, self.config.min_bet, max).into());
+    }
+
+    self.seats[seat].stack -= amount;
+    self.seats[seat].bet = Some(amount);
+    self.advance()
+  }
+
+  /// Verify and record the reveal token of `seat`, the house included, for the card `id` of
+  /// `cards`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    let cards = self
+      .cards
+      .as_mut()
+      .ok_or(GameErrors::UnexpectedRevealToken(seat as u32, id as u32))?;
+    cards.add_reveal_token(seat, id, token)?;
+    self.advance()
+  }
+
+  /// The house declares whether it has blackjack, after peeking at its hole card.
+  pub fn peek(&mut self, blackjack: bool) -> anyhow::Result<()> {
+    if self.phase != Phase::Peek || self.declared_blackjack.is_some() {
+      return Err(GameErrors::NotYourTurn(self.house() as u32).into());
+    }
+
+    self.declared_blackjack = Some(blackjack);
+    if blackjack {
+      let house = self.house();
+      self.cards.as_mut().unwrap().show(house)?;
+    }
+    self.advance()
+  }
+
+  pub fn play(&mut self, seat: usize, hand: usize, play: Move) -> anyhow::Result<()> {
+    if self.turn() != Some((seat, hand)) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    let stack = self.seats[seat].stack;
+    let num_of_hands = self.seats[seat].hands.len();
+    let current = &self.seats[seat].hands[hand];
+    let bet = current.bet;
+    let first_two = current.cards.len() == 2;
+    let pair = first_two && {
+      let opened = self.opened(&current.cards).unwrap();
+      points(opened[0].value) == points(opened[1].value)
+    };
+
+    match play {
+      Move::Hit => {
+        self.deal_to(seat, hand)?;
+      }
+      Move::Stand => {
+        self.seats[seat].hands[hand].done = true;
+      }
+      Move::Double => {
+        if !first_two {
+          return Err(GameErrors::IllegalAction("double after the first two cards").into());
+        }
+        if stack < bet {
+          return Err(GameErrors::InvalidAmount(bet, bet, stack).into());
+        }
+        let seat_state = &mut self.seats[seat];
+        seat_state.stack -= bet;
+        seat_state.hands[hand].bet += bet;
+        seat_state.hands[hand].doubled = true;
+        self.deal_to(seat, hand)?;
+      }
+      Move::Split => {
+        if !pair {
+          return Err(GameErrors::IllegalAction("split without a pair").into());
+        }
+        if num_of_hands >= MAX_HANDS {
+          return Err(GameErrors::IllegalAction("split past the most hands").into());
+        }
+        if stack < bet {
+          return Err(GameErrors::InvalidAmount(bet, bet, stack).into());
+        }
+        let seat_state = &mut self.seats[seat];
+        seat_state.stack -= bet;
+        let second = seat_state.hands[hand].cards.pop().unwrap();
+        seat_state.hands[hand].split = true;
+        seat_state
+          .hands
+          .insert(hand + 1, Hand::new(vec![second], bet, true));
+        self.deal_to(seat, hand)?;
+        self.deal_to(seat, hand + 1)?;
+      }
+    }
+
+    self.advance()
+  }
+
+  /// Clear the table for the next round, once this one is complete. When the cut card came
+  /// out, the whole shoe is gathered and goes through a new shuffle chain first.
+  pub fn next_round<R: Rng>(&mut self, rng: &mut R) -> anyhow::Result<()> {
+    if self.phase != Phase::Complete {
+      return Err(GameErrors::HandNotComplete.into());
+    }
+
+    let cards = self.cards.as_mut().unwrap();
+    for seat in 0..=self.seats.len() {
+      cards.discard_hand(seat)?;
+    }
+    for seat in self.seats.iter_mut() {
+      seat.bet = None;
+      seat.hands.clear();
+    }
+    self.dealer_cards.clear();
+    self.declared_blackjack = None;
+    self.payouts = None;
+
+    if self.cut_card_out {
+      self.cards = None;
+      self.cut_card_out = false;
+      self.shuffles = 0;
+      self.shoe = self.initial_shoe(rng)?;
+      self.phase = Phase::Shuffle;
+    } else {
+      self.phase = Phase::Bet;
+    }
+    self.advance()
+  }
+
+  // Every card of the mapping masked under the aggregate key, in `deck_order`
+  fn initial_shoe<R: Rng>(&self, rng: &mut R) -> anyhow::Result<Vec<MaskedCard>> {
+    Ok(
+      deck_order(self.card_mapping)
+        .into_iter()
+        .map(|(card, _)| {
+          CardProtocol::mask(rng, self.parameters, &self.joint_pk, card, &Scalar::one())
+            .map(|(masked, _)| masked)
+        })
+        .collect::<Result<Vec<_>, _>>()?,
+    )
+  }
+
+  fn awaited_tokens(&self) -> Vec<Awaiting> {
+    self
+      .cards
+      .as_ref()
+      .map(|cards| {
+        cards
+          .awaited_tokens()
+          .into_iter()
+          .map(|(seat, card)| Awaiting::RevealToken { seat, card })
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  /// Move through every phase whose requirements are already met.
+  fn advance(&mut self) -> anyhow::Result<()> {
+    loop {
+      if !self.awaited_tokens().is_empty() {
+        return Ok(());
+      }
+
+      match self.phase {
+        Phase::Shuffle if self.shuffles == self.key_holders.len() => {
+          let shoe = std::mem::take(&mut self.shoe);
+          let mut cards = DrawDeck::new(
+            self.parameters,
+            self.card_mapping,
+            &self.key_holders,
+            shoe,
+            false,
+          )?;
+          cards.burn(1)?;
+          self.cards = Some(cards);
+          self.phase = Phase::Bet;
+        }
+        Phase::Bet => {
+          for seat in self.seats.iter_mut() {
+            if seat.stack == 0 && seat.bet.is_none() {
+              seat.bet = Some(0);
+            }
+          }
+          if self.seats.iter().any(|seat| seat.bet.is_none()) {
+            return Ok(());
+          }
+          if self.seats.iter().all(|seat| seat.bet == Some(0)) {
+            self.settle();
+          } else {
+            self.deal_round()?;
+          }
+        }
+        Phase::Deal => {
+          self.close_finished_hands();
+          let up_card = self.opened(&self.dealer_cards[..1]).unwrap()[0];
+          self.phase = if points(up_card.value) >= 10 {
+            Phase::Peek
+          } else {
+            Phase::Play
+          };
+        }
+        Phase::Peek => match self.declared_blackjack {
+          Some(true) => {
+            let house = self.house();
+            let dealer = self.cards.as_ref().unwrap().open_hand(house)?;
+            if total(&dealer).0 == 21 {
+              self.settle();
+            } else {
+              // A false declaration only gave the hole card away
+              self.phase = Phase::Play;
+            }
+          }
+          Some(false) => self.phase = Phase::Play,
+          None => return Ok(()),
+        },
+        Phase::Play => {
+          self.close_finished_hands();
+          if self.turn().is_some() {
+            return Ok(());
+          }
+          let house = self.house();
+          self.cards.as_mut().unwrap().show(house)?;
+          self.phase = Phase::Dealer;
+        }
+        Phase::Dealer => {
+          let house = self.house();
+          let dealer = self.cards.as_ref().unwrap().open_hand(house)?;
+          let (points, soft) = total(&dealer);
+          let blackjack = dealer.len() == 2 && points == 21;
+          let draws = points < 17 || (points == 17 && soft && self.config.dealer_hits_soft_17);
+          if draws && !blackjack && self.has_live_hands() {
+            let id = self.deal_card(house, Visibility::Public)?;
+            self.dealer_cards.push(id);
+          } else {
+            self.settle();
+          }
+        }
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  fn deal_card(&mut self, seat: usize, visibility: Visibility) -> anyhow::Result<usize> {
+    let cards = self.cards.as_mut().unwrap();
+    let id = cards.deal_as(seat, 1, visibility)?[0];
+    if cards.stub_len() <= self.config.cut_card {
+      self.cut_card_out = true;
+    }
+    Ok(id)
+  }
+
+  fn deal_to(&mut self, seat: usize, hand: usize) -> anyhow::Result<()> {
+    let id = self.deal_card(seat, Visibility::Public)?;
+    self.seats[seat].hands[hand].cards.push(id);
+    Ok(())
+  }
+
+  /// One card up to every seat that bet and to the house, then a second card up to every seat
+  /// and the hole card down to the house.
+  fn deal_round(&mut self) -> anyhow::Result<()> {
+    let house = self.house();
+    let playing: Vec<usize> = (0..self.num_of_players())
+      .filter(|seat| self.seats[*seat].bet != Some(0))
+      .collect();
+    for seat in &playing {
+      let bet = self.seats[*seat].bet.unwrap();
+      self.seats[*seat].hands.push(Hand::new(vec![], bet, false));
+    }
+
+    for visibility in [Visibility::Public, Visibility::FaceDown] {
+      for seat in &playing {
+        self.deal_to(*seat, 0)?;
+      }
+      let id = self.deal_card(house, visibility)?;
+      self.dealer_cards.push(id);
+    }
+
+    self.phase = Phase::Deal;
+    Ok(())
+  }
+
+  /// Mark as done the hands that take no more cards: 21 or more, a doubled hand with its card,
+  /// and split Aces with their second card.
+  fn close_finished_hands(&mut self) {
+    let cards = self.cards.as_ref().unwrap();
+    for seat in self.seats.iter_mut() {
+      for hand in seat.hands.iter_mut().filter(|hand| !hand.done) {
+        let opened: Option<Vec<ClassicPlayingCard>> =
+          hand.cards.iter().map(|id| cards.opened_card(*id)).collect();
+        let opened = match opened {
+          Some(opened) if opened.len() >= 2 => opened,
+          _ => continue,
+        };
+        let split_aces = hand.split && opened[0].value == Value::Ace;
+        hand.done = total(&opened).0 >= 21 || (hand.doubled && opened.len() == 3) || split_aces;
+      }
+    }
+  }
+
+  /// Whether some hand is neither bust nor a blackjack, so the house has to draw.
+  fn has_live_hands(&self) -> bool {
+    self.seats.iter().flat_map(|seat| &seat.hands).any(|hand| {
+      let opened = self.opened(&hand.cards).unwrap_or_default();
+      total(&opened).0 <= 21 && !is_blackjack(hand, &opened)
+    })
+  }
+
+  fn settle(&mut self) {
+    let dealer = self.opened(&self.dealer_cards).unwrap_or_default();
+    let (dealer_points, _) = total(&dealer);
+    let dealer_blackjack = dealer.len() == 2 && dealer_points == 21;
+
+    let mut payouts = vec![0; self.num_of_players()];
+    for (i, seat) in self.seats.iter().enumerate() {
+      let staked: u64 = seat.hands.iter().map(|hand| hand.bet).sum();
+      let original = seat.bet.unwrap_or(0);
+      payouts[i] = if dealer_blackjack {
+        // Only the original bet is lost, or pushed against a blackjack
+        let push = seat.hands.iter().any(|hand| {
+          let opened = self.opened(&hand.cards).unwrap_or_default();
+          is_blackjack(hand, &opened)
+        });
+        if push {
+          staked
+        } else {
+          staked - original
+        }
+      } else {
+        seat
+          .hands
+          .iter()
+          .map(|hand| {
+            let opened = self.opened(&hand.cards).unwrap_or_default();
+            let (points, _) = total(&opened);
+            if points > 21 {
+              0
+            } else if is_blackjack(hand, &opened) {
+              hand.bet + hand.bet * 3 / 2
+            } else if dealer_points > 21 || points > dealer_points {
+              2 * hand.bet
+            } else if points == dealer_points {
+              hand.bet
+            } else {
+              0
+            }
+          })
+          .sum()
+      };
+    }
+
+    for (seat, payout) in self.seats.iter_mut().zip(&payouts) {
+      seat.stack += payout;
+    }
+    self.payouts = Some(payouts);
+    self.phase = Phase::Complete;
+  }
+}
+
+fn is_blackjack(hand: &Hand, opened: &[ClassicPlayingCard]) -> bool {
+  !hand.split && opened.len() == 2 && total(opened).0 == 21
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{encode_cards, Player, Suite};
+
+  use rand::thread_rng;
+
+  fn card(value: Value) -> ClassicPlayingCard {
+    ClassicPlayingCard::new(value, Suite::Spade)
+  }
+
+  fn key_info(player: &Player) -> (PublicKey, ProofKeyOwnership, Vec<u8>) {
+    (player.pk, player.proof_key, player.name.clone())
+  }
+
+  /// Hand out every shuffle, bet and reveal token the table is waiting for and play every hand
+  /// by the dealer's rule, splitting pairs, until the round is complete. The house peeks at its
+  /// hole card and declares honestly.
+  fn play_round<R: Rng>(
+    rng: &mut R,
+    table: &mut BlackjackTable,
+    players: &[Player],
+    house: &mut Player,
+  ) {
+    loop {
+      match table.awaiting().first().copied() {
+        Some(Awaiting::Shuffle { seat }) => {
+          let (shoe, proof) = table.shuffle_shoe(rng).unwrap();
+          table.shuffle(seat, shoe, &proof).unwrap();
+        }
+        Some(Awaiting::Bet { seat }) => table.bet(seat, 10).unwrap(),
+        Some(Awaiting::RevealToken { seat, card }) => {
+          let holder = players.get(seat).unwrap_or(&*house);
+          let masked = table.cards().unwrap().card(card).unwrap();
+          let token = holder
+            .compute_reveal_token(rng, table.parameters, masked)
+            .unwrap();
+          table.add_reveal_token(seat, card, token).unwrap();
+        }
+        Some(Awaiting::Peek) => {
+          let cards = table.cards().unwrap();
+          let hole = *cards.card(table.dealer_cards()[1]).unwrap();
+          // Nobody else has opened the hole card
+          assert_eq!(cards.opened_card(table.dealer_cards()[1]), None);
+          assert_eq!(
+            cards.reveal_tokens(table.dealer_cards()[1]).len(),
+            players.len()
+          );
+          house.receive_card(hole);
+          house
+            .peek_at_card(
+              rng,
+              table.parameters,
+              &mut cards.reveal_tokens(table.dealer_cards()[1]),
+              table.card_mapping,
+              &hole,
+            )
+            .unwrap();
+          let up = table.opened(&table.dealer_cards()[..1]).unwrap()[0];
+          let hole = house.opened_cards.last().unwrap().unwrap();
+          table.peek(total(&[up, hole]).0 == 21).unwrap();
+        }
+        Some(Awaiting::Move { seat, hand }) => {
+          let current = &table.hands(seat)[hand];
+          let opened = table.opened(current.cards()).unwrap();
+          let play = if opened.len() == 2
+            && opened[0].value == opened[1].value
+            && table.hands(seat).len() < MAX_HANDS
+          {
+            Move::Split
+          } else if total(&opened).0 < 17 {
+            Move::Hit
+          } else {
+            Move::Stand
+          };
+          table.play(seat, hand, play).unwrap();
+        }
+        None => return,
+      }
+    }
+  }
+
+  #[test]
+  fn counts_aces_soft_or_hard() {
+    use Value::*;
+
+    assert_eq!(total(&[card(Ace), card(Six)]), (17, true));
+    assert_eq!(total(&[card(Ace), card(Six), card(Ten)]), (17, false));
+    assert_eq!(total(&[card(Ace), card(Ace), card(Nine)]), (21, true));
+    assert_eq!(total(&[card(King), card(Six), card(Nine)]), (25, false));
+    assert_eq!(total(&[card(Two), card(Three)]), (5, false));
+  }
+
+  #[test]
+  fn plays_rounds_from_a_six_deck_shoe() {
+    let rng = &mut thread_rng();
+    let config = BlackjackConfig {
+      decks: 6,
+      cut_card: 104,
+      min_bet: 5,
+      max_bet: 100,
+      dealer_hits_soft_17: false,
+    };
+    let (m, n) = config.protocol_shape();
+    let parameters = CardProtocol::setup(rng, m, n).unwrap();
+    let card_mapping = encode_cards(rng, m * n);
+    assert_eq!(card_mapping.len(), 312);
+    assert_eq!(
+      card_mapping
+        .values()
+        .filter(|classic| **classic == card(Value::Ace))
+        .count(),
+      6
+    );
+
+    let mut house = Player::new(rng, &parameters, &b"House".to_vec()).unwrap();
+    let players: Vec<Player> = ["Andrija", "Kobi"]
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    let keys: Vec<_> = players.iter().map(key_info).collect();
+
+    let single_deck = encode_cards(rng, 52);
+    assert!(BlackjackTable::new(
+      rng,
+      &parameters,
+      &single_deck,
+      key_info(&house),
+      &keys,
+      vec![100, 100],
+      config,
+    )
+    .is_err());
+
+    let mut table = BlackjackTable::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      key_info(&house),
+      &keys,
+      vec![1000, 1000],
+      config,
+    )
+    .unwrap();
+    assert_eq!(table.house(), 2);
+    assert_eq!(table.awaiting(), vec![Awaiting::Shuffle { seat: 0 }]);
+
+    for _ in 0..3 {
+      play_round(rng, &mut table, &players, &mut house);
+      assert_eq!(table.phase(), Phase::Complete);
+
+      // The house stood on 17 or more unless every hand was settled without it
+      let dealer = table.opened(table.dealer_cards()).unwrap();
+      let (dealer_points, _) = total(&dealer);
+      let drew = dealer.len() > 2;
+      assert!(!drew || total(&dealer[..dealer.len() - 1]).0 < 17);
+      if table.declared_blackjack().is_some() {
+        assert_eq!(
+          table.declared_blackjack(),
+          Some(dealer_points == 21 && dealer.len() == 2)
+        );
+      }
+
+      for seat in 0..2 {
+        let staked: u64 = table.hands(seat).iter().map(Hand::bet).sum();
+        let payout = table.payouts().unwrap()[seat];
+        assert!(payout <= staked * 5 / 2);
+        for hand in table.hands(seat) {
+          assert!(hand.is_done());
+          assert!(table.opened(hand.cards()).is_some());
+        }
+      }
+      table.next_round(rng).unwrap();
+      assert_eq!(table.phase(), Phase::Bet);
+      assert_eq!(table.cards().unwrap().hand(2), vec![]);
+    }
+  }
+
+  #[test]
+  fn reshuffles_once_the_cut_card_is_out() {
+    let rng = &mut thread_rng();
+    let config = BlackjackConfig {
+      decks: 1,
+      cut_card: 40,
+      min_bet: 5,
+      max_bet: 100,
+      dealer_hits_soft_17: true,
+    };
+    let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+    let card_mapping = encode_cards(rng, 52);
+    let mut house = Player::new(rng, &parameters, &b"House".to_vec()).unwrap();
+    let players = vec![Player::new(rng, &parameters, &b"Andrija".to_vec()).unwrap()];
+
+    // Five hands can take up to 31 cards of a single deck, more than a cut card at 30 leaves
+    assert_eq!(max_round_cards(&Variant::Holdem.deck(), 5), 31);
+    let shallow = BlackjackConfig {
+      cut_card: 30,
+      ..config
+    };
+    assert!(BlackjackTable::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      key_info(&house),
+      &[key_info(&players[0])],
+      vec![1000],
+      shallow,
+    )
+    .is_err());
+
+    let mut table = BlackjackTable::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      key_info(&house),
+      &[key_info(&players[0])],
+      vec![1000],
+      config,
+    )
+    .unwrap();
+
+    play_round(rng, &mut table, &players, &mut house);
+    assert!(table.bet(0, 10).is_err());
+    let staked: u64 = table.hands(0).iter().map(Hand::bet).sum();
+    assert_eq!(
+      table.stacks()[0],
+      1000 - staked + table.payouts().unwrap()[0]
+    );
+
+    // Eleven cards go out before the cut card, so a few rounds reach it
+    while !table.cut_card_out() {
+      table.next_round(rng).unwrap();
+      assert!(table.bet(0, 4).is_err());
+      assert!(table.bet(0, 101).is_err());
+      assert!(table.bet(1, 10).is_err());
+      play_round(rng, &mut table, &players, &mut house);
+    }
+    assert!(table.cards().unwrap().stub_len() <= 40);
+
+    table.next_round(rng).unwrap();
+    assert_eq!(table.phase(), Phase::Shuffle);
+    assert!(table.cards().is_none());
+    assert_eq!(table.awaiting(), vec![Awaiting::Shuffle { seat: 0 }]);
+    play_round(rng, &mut table, &players, &mut house);
+    assert_eq!(table.phase(), Phase::Complete);
+    assert!(table.cards().unwrap().discards().is_empty());
+  }
+}
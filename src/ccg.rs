@@ -0,0 +1,507 @@
+//! Player-owned decks for collectible card games, where every player brings a deck of its own
+//! and the others must learn neither its order nor what is left in it.
+//!
+//! The owner commits to its deck list with `commit_deck`, masking every card under the shared key
+//! with a masking factor of its own rather than `Scalar::one()`, so the masked list tells nothing
+//! about the cards. `PrivateDeck` is the public state of one such deck that every player keeps in
+//! sync, on top of a zoned `Deck` where the library is the deck and the owner's seat holds the
+//! hand. Every seat shuffles it in turn, starting left of the owner, before the owner draws from
+//! the top: the others publish their reveal tokens for the cards drawn, and only the owner can
+//! add its own to peek at them. A card played opens to everyone with the owner's token.
+//!
+//! A mulligan puts the hand back into the library, and a search lets the owner peek at the whole
+//! library to take cards from it. Either way every seat shuffles the library again afterwards,
+//! under new masks that none of the tokens handed out before opens. At the end of the game the
+//! owner reveals its list with the masking factors, and `audit` checks them against the
+//! commitment: since every shuffle is proven, the cards played came out of that list.
+//!
+//! Shuffles after the first one work on fewer cards, so every shuffle takes its parameters from a
+//! `ParameterCache`, which pads the library to a size the shuffle argument supports.
+
+use crate::reshuffle::{reshuffle, verify_reshuffle, ParameterCache};
+use crate::zones::{Deck, Visibility, Zone};
+use crate::{
+  Card, CardParameters, CardProtocol, GameErrors, MaskedCard, Player, ProofKeyOwnership, PublicKey,
+  RevealShare, Scalar, ShuffleProof,
+};
+
+use ark_ff::UniformRand;
+use ark_std::rand::Rng;
+use barnett_smart_protocol::BarnettSmartProtocol;
+use std::collections::HashMap;
+
+/// A deck list with the masking factors that open its commitment. The owner keeps it to itself
+/// until the audit.
+pub struct DeckList<T> {
+  pub cards: Vec<T>,
+  pub alphas: Vec<Scalar>,
+}
+
+/// Mask the cards of `list` under the shared key as the owner of a deck does. Returns the masked
+/// cards, which commit to the list, and the list with its masking factors.
+pub fn commit_deck<R: Rng, T: Copy + PartialEq>(
+  rng: &mut R,
+  parameters: &CardParameters,
+  joint_pk: &PublicKey,
+  card_mapping: &HashMap<Card, T>,
+  list: &[T],
+) -> anyhow::Result<(Vec<MaskedCard>, DeckList<T>)> {
+  let mut commitment = Vec::with_capacity(list.len());
+  let mut alphas = Vec::with_capacity(list.len());
+  for card in list {
+    let plaintext = encoding(card_mapping, card)?;
+    let alpha = Scalar::rand(rng);
+    commitment.push(CardProtocol::mask(rng, parameters, joint_pk, plaintext, &alpha)?.0);
+    alphas.push(alpha);
+  }
+
+  Ok((
+    commitment,
+    DeckList {
+      cards: list.to_vec(),
+      alphas,
+    },
+  ))
+}
+
+// The plaintext that stands for `card`
+fn encoding<'a, T: PartialEq>(
+  card_mapping: &'a HashMap<Card, T>,
+  card: &T,
+) -> Result<&'a Card, GameErrors> {
+  card_mapping
+    .iter()
+    .find(|(_, other)| *other == card)
+    .map(|(plaintext, _)| plaintext)
+    .ok_or(GameErrors::InvalidCard)
+}
+
+// The deck being shuffled, with the number of seats that have shuffled it
+struct Shuffle {
+  deck: Vec<MaskedCard>,
+  shuffles: usize,
+}
+
+pub struct PrivateDeck<'a, T> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, T>,
+  owner: usize,
+  commitment: Vec<MaskedCard>,
+  cards: Deck<'a, T>,
+  shuffle: Option<Shuffle>,
+  searching: bool,
+}
+
+impl<'a, T: Copy + PartialEq> PrivateDeck<'a, T> {
+  /// Start a deck of `owner` from its `commitment`. Players are listed in seat order with their
+  /// proofs of key ownership. Every seat shuffles the deck before the owner can draw.
+  pub fn new(
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, T>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    owner: usize,
+    commitment: Vec<MaskedCard>,
+  ) -> anyhow::Result<Self> {
+    if owner >= players.len() {
+      return Err(GameErrors::InvalidSeat(owner as u32).into());
+    }
+
+    Ok(Self {
+      parameters,
+      card_mapping,
+      owner,
+      commitment: commitment.clone(),
+      cards: Deck::new(parameters, card_mapping, players, vec![])?,
+      shuffle: Some(Shuffle {
+        deck: commitment,
+        shuffles: 0,
+      }),
+      searching: false,
+    })
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.cards.num_of_players()
+  }
+
+  pub fn owner(&self) -> usize {
+    self.owner
+  }
+
+  /// The masked deck list the owner committed to.
+  pub fn commitment(&self) -> &[MaskedCard] {
+    &self.commitment
+  }
+
+  pub fn card(&self, id: usize) -> Option<&MaskedCard> {
+    self.cards.card(id)
+  }
+
+  /// Where the card `id` is: `Zone::Deck` for the library, the owner's hand, played by the
+  /// owner, or reshuffled into a new library by a mulligan or a search.
+  pub fn zone(&self, id: usize) -> Option<Zone> {
+    self.cards.zone(id)
+  }
+
+  /// The card `id` once it is played and all its reveal tokens are in.
+  pub fn opened_card(&self, id: usize) -> Option<T> {
+    self.cards.opened_card(id)
+  }
+
+  pub fn library_len(&self) -> usize {
+    self.library().len()
+  }
+
+  /// Ids of the cards in the library, the top first.
+  pub fn library(&self) -> Vec<usize> {
+    self.cards.cards_in(Zone::Deck)
+  }
+
+  pub fn hand(&self) -> Vec<usize> {
+    self.cards.cards_in(Zone::Hand(self.owner))
+  }
+
+  /// Seat that shuffles the library next, if it is being shuffled. The owner shuffles last.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    self
+      .shuffle
+      .as_ref()
+      .map(|shuffle| (self.owner + 1 + shuffle.shuffles) % self.num_of_players())
+  }
+
+  /// Shuffle and remask the library, as the next shuffler does locally, with the parameters
+  /// `cache` derives for its size.
+  pub fn shuffle_library<R: Rng>(
+    &self,
+    rng: &mut R,
+    cache: &mut ParameterCache,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let deck = &self
+      .shuffle
+      .as_ref()
+      .ok_or(GameErrors::IllegalAction("shuffle without a reshuffle"))?
+      .deck;
+
+    reshuffle(rng, cache, self.cards.joint_pk(), deck)
+  }
+
+  /// Verify and adopt the shuffle of the library by `seat`. Once every seat has shuffled, the
+  /// shuffled cards form the library under new ids.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    cache: &mut ParameterCache,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    let num_of_players = self.num_of_players();
+    let joint_pk = *self.cards.joint_pk();
+    let shuffle = self.shuffle.as_mut().unwrap();
+
+    verify_reshuffle(
+      cache,
+      &joint_pk,
+      &shuffle.deck,
+      &shuffled_deck,
+      shuffle_proof,
+    )?;
+    shuffle.deck = shuffled_deck;
+    shuffle.shuffles += 1;
+    if shuffle.shuffles < num_of_players {
+      return Ok(());
+    }
+
+    let shuffle = self.shuffle.take().unwrap();
+    for card in shuffle.deck {
+      self.cards.add_card(card, Zone::Deck)?;
+    }
+    Ok(())
+  }
+
+  /// Draw `count` cards from the top of the library for the owner to peek at.
+  pub fn draw(&mut self, count: usize) -> anyhow::Result<Vec<usize>> {
+    self.check_idle()?;
+    let library = self.library();
+    if count > library.len() {
+      return Err(GameErrors::NotEnoughCards(library.len()).into());
+    }
+
+    self.cards.deal(self.owner, count)?;
+    Ok(library[..count].to_vec())
+  }
+
+  /// Play the card `id` from the hand face up. It opens once the owner's token is in.
+  pub fn play(&mut self, id: usize) -> anyhow::Result<()> {
+    if self.zone(id) != Some(Zone::Hand(self.owner)) {
+      return Err(GameErrors::CardNotFound.into());
+    }
+
+    self
+      .cards
+      .move_card(id, Zone::Played(self.owner), Visibility::Public)?;
+    Ok(())
+  }
+
+  /// Put the hand back into the library and have every seat shuffle it again.
+  pub fn mulligan(&mut self) -> anyhow::Result<()> {
+    self.check_idle()?;
+    let mut returned = self.library();
+    returned.extend(self.hand());
+    self.reshuffle(returned)
+  }
+
+  /// Let the owner peek at the whole library, once the others have published their tokens for
+  /// it.
+  pub fn search(&mut self) -> anyhow::Result<()> {
+    self.check_idle()?;
+    for id in self.library() {
+      self
+        .cards
+        .move_card(id, Zone::Deck, Visibility::KnownTo(vec![self.owner]))?;
+    }
+    self.searching = true;
+    Ok(())
+  }
+
+  /// End the search, putting the cards `found` into the hand, and have every seat shuffle the
+  /// rest of the library again.
+  pub fn finish_search(&mut self, found: &[usize]) -> anyhow::Result<()> {
+    if !self.searching {
+      return Err(GameErrors::IllegalAction("no search in progress").into());
+    }
+    for (i, id) in found.iter().enumerate() {
+      if self.zone(*id) != Some(Zone::Deck) || found[..i].contains(id) {
+        return Err(GameErrors::CardNotFound.into());
+      }
+    }
+
+    for id in found {
+      self
+        .cards
+        .move_card(*id, Zone::Hand(self.owner), Visibility::FaceDown)?;
+    }
+    self.searching = false;
+    self.reshuffle(self.library())
+  }
+
+  /// Reveal tokens still missing, as pairs of seat and card id: the others' tokens for the cards
+  /// the owner may peek at, and every token for the cards played.
+  pub fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    self.cards.awaited_tokens()
+  }
+
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    self.cards.add_reveal_token(seat, id, token)
+  }
+
+  /// Verified reveal tokens received so far for the card `id`.
+  pub fn reveal_tokens(&self, id: usize) -> Vec<RevealShare> {
+    self.cards.reveal_tokens(id)
+  }
+
+  /// Privately open the card `id` as the owner, `player`, once the others' tokens for it are in.
+  pub fn peek<R: Rng>(&self, rng: &mut R, player: &Player, id: usize) -> anyhow::Result<T> {
+    let card = self
+      .card(id)
+      .ok_or(GameErrors::InvalidCardIndex(id as u32))?;
+    let mut tokens = self.reveal_tokens(id);
+    tokens.push(player.compute_reveal_token(rng, self.parameters, card)?);
+    let plaintext = CardProtocol::unmask(self.parameters, &tokens, card)?;
+    let opened = self.card_mapping.get(&plaintext);
+    Ok(*opened.ok_or(GameErrors::InvalidCard)?)
+  }
+
+  /// Check the deck list the owner reveals at the end of the game, with its masking factors,
+  /// against the commitment.
+  pub fn audit<R: Rng>(&self, rng: &mut R, list: &[T], alphas: &[Scalar]) -> anyhow::Result<()> {
+    if list.len() != self.commitment.len() || alphas.len() != self.commitment.len() {
+      return Err(GameErrors::DeckListMismatch.into());
+    }
+
+    let joint_pk = self.cards.joint_pk();
+    for ((card, alpha), committed) in list.iter().zip(alphas).zip(&self.commitment) {
+      let plaintext = encoding(self.card_mapping, card)?;
+      let (masked, _) = CardProtocol::mask(rng, self.parameters, joint_pk, plaintext, alpha)?;
+      if masked != *committed {
+        return Err(GameErrors::DeckListMismatch.into());
+      }
+    }
+    Ok(())
+  }
+
+  // Retire the cards `ids` and shuffle them into a new library
+  fn reshuffle(&mut self, ids: Vec<usize>) -> anyhow::Result<()> {
+    let deck = ids
+      .iter()
+      .map(|id| *self.cards.card(*id).unwrap())
+      .collect();
+    for id in ids {
+      self
+        .cards
+        .move_card(id, Zone::Reshuffled, Visibility::FaceDown)?;
+    }
+    self.shuffle = Some(Shuffle { deck, shuffles: 0 });
+    Ok(())
+  }
+
+  fn check_idle(&self) -> Result<(), GameErrors> {
+    if self.shuffle.is_some() {
+      Err(GameErrors::IllegalAction("the library is being shuffled"))
+    } else if self.searching {
+      Err(GameErrors::IllegalAction("the library is being searched"))
+    } else {
+      Ok(())
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{deliver_tokens, key_info, players};
+
+  use rand::thread_rng;
+
+  // A pool of twenty cards, numbered, and the players
+  fn setup<R: Rng>(rng: &mut R) -> (CardParameters, HashMap<Card, u32>, Vec<Player>, PublicKey) {
+    let parameters = CardProtocol::setup(rng, 4, 10).unwrap();
+    let card_mapping = (0..20).map(|number| (Card::rand(rng), number)).collect();
+    let players = players(rng, &parameters, 3);
+    let joint_pk = CardProtocol::compute_aggregate_key(&parameters, &key_info(&players)).unwrap();
+    (parameters, card_mapping, players, joint_pk)
+  }
+
+  /// Two copies of every card of the pool, forty in all.
+  fn list() -> Vec<u32> {
+    (0..20).flat_map(|number| [number; 2]).collect()
+  }
+
+  fn shuffle_all<R: Rng>(rng: &mut R, deck: &mut PrivateDeck<u32>, cache: &mut ParameterCache) {
+    while let Some(seat) = deck.next_shuffler() {
+      let (shuffled, proof) = deck.shuffle_library(rng, cache).unwrap();
+      deck.shuffle(seat, cache, shuffled, &proof).unwrap();
+    }
+  }
+
+  #[test]
+  fn draws_privately_and_audits_the_list() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players, joint_pk) = setup(rng);
+    let mut cache = ParameterCache::new(&parameters);
+    let (commitment, list) =
+      commit_deck(rng, &parameters, &joint_pk, &card_mapping, &list()).unwrap();
+    let mut deck = PrivateDeck::new(
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      1,
+      commitment,
+    )
+    .unwrap();
+
+    assert!(deck.draw(7).is_err());
+    assert_eq!(deck.next_shuffler(), Some(2));
+    shuffle_all(rng, &mut deck, &mut cache);
+    assert_eq!(deck.library_len(), 40);
+
+    let hand = deck.draw(7).unwrap();
+    assert_eq!(deck.awaited_tokens().len(), 14);
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    let peeked: Vec<u32> = hand
+      .iter()
+      .map(|id| deck.peek(rng, &players[1], *id).unwrap())
+      .collect();
+    assert!(deck.peek(rng, &players[0], hand[0]).is_err());
+    assert!(deck.reveal_tokens(deck.library()[0]).is_empty());
+
+    deck.play(hand[2]).unwrap();
+    assert_eq!(deck.awaited_tokens(), vec![(1, hand[2])]);
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert_eq!(deck.opened_card(hand[2]), Some(peeked[2]));
+
+    // The true list opens the commitment, a list with two cards swapped does not
+    deck.audit(rng, &list.cards, &list.alphas).unwrap();
+    let mut swapped = list.cards.clone();
+    swapped.swap(0, 39);
+    assert_eq!(
+      deck
+        .audit(rng, &swapped, &list.alphas)
+        .unwrap_err()
+        .downcast::<GameErrors>()
+        .unwrap(),
+      GameErrors::DeckListMismatch
+    );
+    assert!(deck
+      .audit(rng, &list.cards[1..], &list.alphas[1..])
+      .is_err());
+  }
+
+  #[test]
+  fn mulligans_and_searches_the_library() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players, joint_pk) = setup(rng);
+    let mut cache = ParameterCache::new(&parameters);
+    let (commitment, _) = commit_deck(rng, &parameters, &joint_pk, &card_mapping, &list()).unwrap();
+    let mut deck = PrivateDeck::new(
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      0,
+      commitment,
+    )
+    .unwrap();
+    shuffle_all(rng, &mut deck, &mut cache);
+
+    let first_hand = deck.draw(7).unwrap();
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    deck.mulligan().unwrap();
+    assert!(deck.hand().is_empty());
+    assert_eq!(deck.zone(first_hand[0]), Some(Zone::Reshuffled));
+    shuffle_all(rng, &mut deck, &mut cache);
+    assert_eq!(deck.library_len(), 40);
+    let hand = deck.draw(7).unwrap();
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+
+    // Search the library for a copy of card 5, or take its top card if both copies are in hand
+    deck.search().unwrap();
+    assert!(deck.draw(1).is_err());
+    assert_eq!(deck.awaited_tokens().len(), 2 * 33);
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    let library: Vec<u32> = deck
+      .library()
+      .iter()
+      .map(|id| deck.peek(rng, &players[0], *id).unwrap())
+      .collect();
+    let mut pool = library.clone();
+    pool.sort_unstable();
+    let held: Vec<u32> = hand
+      .iter()
+      .map(|id| deck.peek(rng, &players[0], *id).unwrap())
+      .collect();
+    let mut all: Vec<u32> = pool.iter().chain(&held).copied().collect();
+    all.sort_unstable();
+    assert_eq!(all, list());
+
+    let i = library
+      .iter()
+      .position(|card| *card == 5)
+      .unwrap_or_default();
+    let found = deck.library()[i];
+    assert!(deck.finish_search(&[found, found]).is_err());
+    deck.finish_search(&[found]).unwrap();
+    assert_eq!(deck.hand().len(), 8);
+    assert_eq!(deck.peek(rng, &players[0], found).unwrap(), library[i]);
+
+    shuffle_all(rng, &mut deck, &mut cache);
+    assert_eq!(deck.library_len(), 32);
+    assert!(deck.awaited_tokens().is_empty());
+  }
+}
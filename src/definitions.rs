@@ -0,0 +1,322 @@
+//! Decks described in a TOML or JSON file rather than in code, for games that do not play with
+//! `ClassicPlayingCard`.
+//!
+//! A definition lists the kinds of card of the deck, each with an id, a display name, free-form
+//! attributes and how many copies of it the deck holds:
+//!
+//! ```toml
+//! name = "Uno"
+//!
+//! [[cards]]
+//! id = "red-skip"
+//! name = "Red Skip"
+//! copies = 2
+//! attributes = { colour = "red", action = true }
+//! ```
+//!
+//! The shuffle argument works on an `m × n` deck, so the definition picks the smallest `m × n`
+//! with `2 <= m <= n` that holds every copy, the most balanced one if several do, and fills the
+//! rest with padding cards. Padding is masked and shuffled like any other card; it decodes to no
+//! definition when it is opened.
+
+use crate::{Card, CardParameters, CardProtocol, GameErrors, MaskedCard, RevealShare};
+
+use ark_ff::UniformRand;
+use ark_std::rand::Rng;
+use barnett_smart_protocol::BarnettSmartProtocol;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct DeckDefinition {
+  pub name: String,
+  pub cards: Vec<CardDefinition>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct CardDefinition {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub attributes: BTreeMap<String, Attribute>,
+  #[serde(default = "one")]
+  pub copies: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Attribute {
+  Flag(bool),
+  Number(i64),
+  Text(String),
+}
+
+/// What a plaintext of a defined deck stands for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DefinedCard {
+  /// Copy `copy` of the card at position `kind` of the definition.
+  Card {
+    kind: usize,
+    copy: u32,
+  },
+  Padding,
+}
+
+/// The smallest `m × n` with `2 <= m <= n` that holds `num_of_cards` cards, the most balanced one
+/// if several do.
+pub fn dimensions(num_of_cards: usize) -> (usize, usize) {
+  (num_of_cards.max(4)..)
+    .find_map(|size| {
+      (2..=size)
+        .take_while(|m| m * m <= size)
+        .filter(|m| size % m == 0)
+        .last()
+        .map(|m| (m, size / m))
+    })
+    .unwrap()
+}
+
+fn one() -> u32 {
+  1
+}
+
+impl DeckDefinition {
+  pub fn from_toml(source: &str) -> anyhow::Result<Self> {
+    let definition: Self = toml::from_str(source)?;
+    definition.check()?;
+    Ok(definition)
+  }
+
+  pub fn from_json(source: &str) -> anyhow::Result<Self> {
+    let definition: Self = serde_json::from_str(source)?;
+    definition.check()?;
+    Ok(definition)
+  }
+
+  /// Read a definition from a `.json` file, or from a TOML file otherwise.
+  pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+    let source = std::fs::read_to_string(&path)?;
+    match path
+      .as_ref()
+      .extension()
+      .and_then(|extension| extension.to_str())
+    {
+      Some("json") => Self::from_json(&source),
+      _ => Self::from_toml(&source),
+    }
+  }
+
+  /// Number of cards in the deck, every copy counted and padding left out.
+  pub fn num_of_cards(&self) -> usize {
+    self.cards.iter().map(|card| card.copies as usize).sum()
+  }
+
+  /// The `(m, n)` the protocol is set up with, `m × n` being the size of the padded deck.
+  pub fn dimensions(&self) -> (usize, usize) {
+    dimensions(self.num_of_cards())
+  }
+
+  /// Number of padding cards added to the deck.
+  pub fn padding(&self) -> usize {
+    let (m, n) = self.dimensions();
+    m * n - self.num_of_cards()
+  }
+
+  /// Every card of the padded deck: the copies of each card in the order they are defined, then
+  /// the padding.
+  pub fn layout(&self) -> Vec<DefinedCard> {
+    self
+      .cards
+      .iter()
+      .enumerate()
+      .flat_map(|(kind, card)| (0..card.copies).map(move |copy| DefinedCard::Card { kind, copy }))
+      .chain(std::iter::repeat(DefinedCard::Padding).take(self.padding()))
+      .collect()
+  }
+
+  /// Set up the protocol for the padded deck.
+  pub fn setup<R: Rng>(&self, rng: &mut R) -> anyhow::Result<CardParameters> {
+    let (m, n) = self.dimensions();
+    Ok(CardProtocol::setup(rng, m, n)?)
+  }
+
+  /// Give every card of the padded deck a plaintext of its own.
+  pub fn encode<R: Rng>(&self, rng: &mut R) -> HashMap<Card, DefinedCard> {
+    self
+      .layout()
+      .into_iter()
+      .map(|card| (Card::rand(rng), card))
+      .collect()
+  }
+
+  /// The definition of `card`, `None` for padding.
+  pub fn decode(&self, card: DefinedCard) -> Option<&CardDefinition> {
+    match card {
+      DefinedCard::Card { kind, .. } => self.cards.get(kind),
+      DefinedCard::Padding => None,
+    }
+  }
+
+  /// Open `card` with the reveal tokens of every player and decode it.
+  pub fn open(
+    &self,
+    parameters: &CardParameters,
+    reveal_tokens: &[RevealShare],
+    card_mapping: &HashMap<Card, DefinedCard>,
+    card: &MaskedCard,
+  ) -> anyhow::Result<Option<&CardDefinition>> {
+    let plaintext = CardProtocol::unmask(parameters, &reveal_tokens.to_vec(), card)?;
+    let opened = card_mapping
+      .get(&plaintext)
+      .ok_or(GameErrors::InvalidCard)?;
+    Ok(self.decode(*opened))
+  }
+
+  fn check(&self) -> Result<(), GameErrors> {
+    if self.cards.is_empty() {
+      return Err(GameErrors::InvalidDeckDefinition(format!(
+        "{} has no cards",
+        self.name
+      )));
+    }
+
+    let mut ids = HashSet::new();
+    for card in &self.cards {
+      if !ids.insert(&card.id) {
+        return Err(GameErrors::InvalidDeckDefinition(format!(
+          "card {} is defined twice",
+          card.id
+        )));
+      }
+      if card.copies == 0 {
+        return Err(GameErrors::InvalidDeckDefinition(format!(
+          "card {} has no copies",
+          card.id
+        )));
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Scalar;
+
+  use rand::thread_rng;
+
+  const TOML: &str = r#"
+    name = "Tiny Uno"
+
+    [[cards]]
+    id = "red-0"
+    name = "Red 0"
+    attributes = { colour = "red", number = 0 }
+
+    [[cards]]
+    id = "red-skip"
+    name = "Red Skip"
+    copies = 2
+    attributes = { colour = "red", action = true }
+
+    [[cards]]
+    id = "wild"
+    name = "Wild"
+    copies = 4
+  "#;
+
+  const JSON: &str = r#"{
+    "name": "Tiny Uno",
+    "cards": [
+      { "id": "red-0", "name": "Red 0", "attributes": { "colour": "red", "number": 0 } },
+      {
+        "id": "red-skip",
+        "name": "Red Skip",
+        "copies": 2,
+        "attributes": { "colour": "red", "action": true }
+      },
+      { "id": "wild", "name": "Wild", "copies": 4 }
+    ]
+  }"#;
+
+  #[test]
+  fn reads_toml_and_json_alike() {
+    let definition = DeckDefinition::from_toml(TOML).unwrap();
+    assert_eq!(DeckDefinition::from_json(JSON).unwrap(), definition);
+    assert_eq!(definition.num_of_cards(), 7);
+    assert_eq!(definition.dimensions(), (2, 4));
+    assert_eq!(definition.padding(), 1);
+    assert_eq!(
+      definition.cards[1].attributes.get("action"),
+      Some(&Attribute::Flag(true))
+    );
+    assert_eq!(
+      definition.cards[0].attributes.get("number"),
+      Some(&Attribute::Number(0))
+    );
+
+    let duplicate = TOML.replace("\"red-skip\"", "\"red-0\"");
+    assert!(DeckDefinition::from_toml(&duplicate).is_err());
+    let no_copies = TOML.replace("copies = 4", "copies = 0");
+    assert!(DeckDefinition::from_toml(&no_copies).is_err());
+  }
+
+  #[test]
+  fn picks_the_smallest_balanced_deck() {
+    let sized = |size: u32| DeckDefinition {
+      name: String::from("Sized"),
+      cards: vec![CardDefinition {
+        id: String::from("card"),
+        name: String::from("Card"),
+        attributes: BTreeMap::new(),
+        copies: size,
+      }],
+    };
+
+    assert_eq!(sized(52).dimensions(), (4, 13));
+    assert_eq!(sized(53).dimensions(), (6, 9));
+    assert_eq!(sized(78).dimensions(), (6, 13));
+    assert_eq!(sized(108).dimensions(), (9, 12));
+    assert_eq!(sized(144).dimensions(), (12, 12));
+    assert_eq!(sized(1).dimensions(), (2, 2));
+    assert_eq!(sized(53).padding(), 1);
+  }
+
+  #[test]
+  fn opens_to_the_defined_cards() {
+    let rng = &mut thread_rng();
+    let definition = DeckDefinition::from_toml(TOML).unwrap();
+    let parameters = definition.setup(rng).unwrap();
+    let card_mapping = definition.encode(rng);
+    assert_eq!(card_mapping.len(), 8);
+    let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+    let mut opened: Vec<Option<String>> = card_mapping
+      .keys()
+      .map(|card| {
+        let alpha = Scalar::rand(rng);
+        let (masked, _) = CardProtocol::mask(rng, &parameters, &pk, card, &alpha).unwrap();
+        let (token, proof) =
+          CardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, &masked).unwrap();
+        definition
+          .open(&parameters, &[(token, proof, pk)], &card_mapping, &masked)
+          .unwrap()
+          .map(|card| card.id.clone())
+      })
+      .collect();
+    opened.sort();
+
+    let mut expected = vec![None, Some("red-0")];
+    expected.extend(vec![Some("red-skip"); 2]);
+    expected.extend(vec![Some("wild"); 4]);
+    assert_eq!(
+      opened,
+      expected
+        .into_iter()
+        .map(|id| id.map(String::from))
+        .collect::<Vec<_>>()
+    );
+  }
+}
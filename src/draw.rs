@@ -0,0 +1,649 @@
+//! Dealing, discarding and redrawing from a masked deck, for draw, stud and trick-taking games.
+//!
+//! `DrawDeck` keeps a `zones::Deck` of classic playing cards under the rules of these games.
+//! Cards are dealt from the top of the stub, either face down for the player to peek at or face
+//! up for everyone, and a player who discards hands back cards that everyone saw being dealt to
+//! it, so the discards are known to be genuine. A discard keeps its visibility and no reveal
+//! token is accepted for it, so it can never be opened.
+//!
+//! A card can also be played from a hand without telling which one it is: the player remasks it
+//! with a proof that the new masked card is one of its hand, and the new card comes into play
+//! face up under an id of its own. The hand keeps its masked cards as far as the others can
+//! tell, so if the player plays the same card again, hidden or openly, the copy opens to a card it
+//! already played and is refused. Each hidden play counts against the cards the seat has left,
+//! so it cannot play or discard more cards than it was dealt.
+//!
+//! When the stub cannot cover a draw and reshuffling is allowed, the remaining stub is dealt and
+//! the earlier discards go through a new shuffle chain, starting left of the drawing player, to
+//! form the new stub. The drawing player's own discards of this draw are kept out of it.
+
+use crate::reshuffle::{reshuffle, verify_reshuffle, ParameterCache};
+use crate::zones::{Deck, Visibility, Zone};
+use crate::{
+  Card, CardParameters, ClassicPlayingCard, GameErrors, MaskedCard, MembershipProof,
+  ProofKeyOwnership, PublicKey, RevealShare, ShuffleProof,
+};
+
+use ark_std::rand::Rng;
+use barnett_smart_protocol::discrete_log_cards::membership;
+use std::collections::HashMap;
+
+// The discard pile being shuffled back into the stub for a draw the stub could not cover
+struct Reshuffle {
+  pile: Vec<usize>,
+  deck: Vec<MaskedCard>,
+  first_shuffler: usize,
+  shuffles: usize,
+  seat: usize,
+  owed: usize,
+}
+
+pub struct DrawDeck<'a> {
+  parameters: &'a CardParameters,
+  cards: Deck<'a, ClassicPlayingCard>,
+  reshuffle_discards: bool,
+  shown: Vec<bool>,
+  hidden_plays: Vec<usize>,
+  reshuffle: Option<Reshuffle>,
+}
+
+impl<'a> DrawDeck<'a> {
+  /// Start dealing from `deck`, once every one of `players` has shuffled it. Players are listed
+  /// in seat order with their proofs of key ownership.
+  pub fn new(
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    deck: Vec<MaskedCard>,
+    reshuffle_discards: bool,
+  ) -> anyhow::Result<Self> {
+    Ok(Self {
+      parameters,
+      cards: Deck::new(parameters, card_mapping, players, deck)?,
+      reshuffle_discards,
+      shown: vec![false; players.len()],
+      hidden_plays: vec![0; players.len()],
+      reshuffle: None,
+    })
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.cards.num_of_players()
+  }
+
+  /// The aggregate key the deck is masked with.
+  pub fn joint_pk(&self) -> &PublicKey {
+    self.cards.joint_pk()
+  }
+
+  pub fn card(&self, id: usize) -> Option<&MaskedCard> {
+    self.cards.card(id)
+  }
+
+  pub fn zone(&self, id: usize) -> Option<Zone> {
+    self.cards.zone(id)
+  }
+
+  pub fn visibility(&self, id: usize) -> Option<&Visibility> {
+    self.cards.visibility(id)
+  }
+
+  /// The card `id` once it is public and all its reveal tokens are in.
+  pub fn opened_card(&self, id: usize) -> Option<ClassicPlayingCard> {
+    self.cards.opened_card(id)
+  }
+
+  /// Ids of the cards held by `seat`, in the order they were dealt.
+  pub fn hand(&self, seat: usize) -> Vec<usize> {
+    self.cards.cards_in(Zone::Hand(seat))
+  }
+
+  /// Number of cards `seat` can still play or discard: its hand, less the cards it played
+  /// hidden from it.
+  pub fn cards_left(&self, seat: usize) -> usize {
+    self.hand(seat).len() - self.hidden_plays.get(seat).copied().unwrap_or(0)
+  }
+
+  /// Ids of the cards discarded and not shuffled back yet.
+  pub fn discards(&self) -> Vec<usize> {
+    self.cards.cards_in(Zone::Discard)
+  }
+
+  pub fn stub_len(&self) -> usize {
+    self.cards.cards_in(Zone::Deck).len()
+  }
+
+  /// Ids of the community cards.
+  pub fn board(&self) -> Vec<usize> {
+    self.cards.cards_in(Zone::Board)
+  }
+
+  /// Deal `count` cards face down from the top of the stub to `seat`. Returns their ids.
+  pub fn deal(&mut self, seat: usize, count: usize) -> anyhow::Result<Vec<usize>> {
+    self.deal_as(seat, count, Visibility::FaceDown)
+  }
+
+  /// Deal `count` cards from the top of the stub to `seat`, which peeks at them, showing them to
+  /// the seats `visibility` adds. Cards dealt to a seat that has shown down are public.
+  pub fn deal_as(
+    &mut self,
+    seat: usize,
+    count: usize,
+    visibility: Visibility,
+  ) -> anyhow::Result<Vec<usize>> {
+    self.check_seat(seat)?;
+    self.check_stub(count)?;
+    self.deal_from_stub(seat, count, visibility)
+  }
+
+  /// Deal `count` community cards face up.
+  pub fn deal_to_board(&mut self, count: usize) -> anyhow::Result<Vec<usize>> {
+    self.check_stub(count)?;
+    let ids = self.stub(count);
+    self.cards.deal_to_board(count)?;
+    Ok(ids)
+  }
+
+  /// Put `count` cards from the top of the stub aside without opening them.
+  pub fn burn(&mut self, count: usize) -> anyhow::Result<Vec<usize>> {
+    self.check_stub(count)?;
+    let ids = self.stub(count);
+    self.cards.burn(count)?;
+    Ok(ids)
+  }
+
+  /// Discard the cards `ids` from the hand of `seat` and deal as many replacements. Returns the
+  /// ids of the replacements dealt right away; when the discards have to be reshuffled first,
+  /// the rest follows once every player has shuffled them.
+  pub fn discard(&mut self, seat: usize, ids: &[usize]) -> anyhow::Result<Vec<usize>> {
+    self.check_seat(seat)?;
+    if self.reshuffle.is_some() {
+      return Err(GameErrors::IllegalAction("draw during a reshuffle").into());
+    }
+    for (i, id) in ids.iter().enumerate() {
+      if self.zone(*id) != Some(Zone::Hand(seat)) || ids[..i].contains(id) {
+        return Err(GameErrors::CardNotFound.into());
+      }
+    }
+    if ids.len() > self.cards_left(seat) {
+      return Err(GameErrors::NotEnoughCards(self.cards_left(seat)).into());
+    }
+
+    let pile = self.discards();
+    let stub_len = self.stub_len();
+    let owed = ids.len().saturating_sub(stub_len);
+    if owed > 0 && (!self.reshuffle_discards || owed > pile.len()) {
+      return Err(GameErrors::NotEnoughCards(stub_len + pile.len()).into());
+    }
+
+    self.cards.discard(seat, ids)?;
+    let dealt = self.deal_from_stub(seat, ids.len() - owed, Visibility::FaceDown)?;
+    if owed > 0 {
+      self.reshuffle = Some(Reshuffle {
+        deck: pile
+          .iter()
+          .map(|id| *self.cards.card(*id).unwrap())
+          .collect(),
+        pile,
+        first_shuffler: (seat + 1) % self.num_of_players(),
+        shuffles: 0,
+        seat,
+        owed,
+      });
+    }
+
+    Ok(dealt)
+  }
+
+  /// Play the card `id` from the hand of `seat` face up, as to a trick. It opens once the owner's
+  /// own reveal token is in too.
+  pub fn play(&mut self, seat: usize, id: usize) -> anyhow::Result<()> {
+    self.check_seat(seat)?;
+    if self.zone(id) != Some(Zone::Hand(seat)) || self.cards_left(seat) == 0 {
+      return Err(GameErrors::CardNotFound.into());
+    }
+
+    self
+      .cards
+      .move_card(id, Zone::Played(seat), Visibility::Public)?;
+    Ok(())
+  }
+
+  /// Verify that `card` is a remask of one of the cards in the hand of `seat`, from the proof of
+  /// `Player::prove_in_hand`, and play it face up. Returns its id. It opens once every player's
+  /// reveal token for it is in, the owner's included. The same masked card is refused a second
+  /// time, whether the first one has opened yet or not, and a card that opens to one already
+  /// played from the hand, hidden or openly, is refused when it opens. A refused card still
+  /// counts against `cards_left`.
+  pub fn play_hidden(
+    &mut self,
+    seat: usize,
+    card: MaskedCard,
+    proof: &MembershipProof,
+  ) -> anyhow::Result<usize> {
+    self.check_seat(seat)?;
+    if self.cards_left(seat) == 0 {
+      return Err(GameErrors::CardNotFound.into());
+    }
+    if let Some(played) = self.cards.find(&card) {
+      return Err(GameErrors::CardAlreadyPlayed(played as u32).into());
+    }
+    let hand: Vec<MaskedCard> = self
+      .hand(seat)
+      .into_iter()
+      .map(|id| *self.cards.card(id).unwrap())
+      .collect();
+    membership::verify_membership(self.parameters, self.joint_pk(), &hand, &card, proof)?;
+
+    let id = self.cards.add_remask(card, Zone::Played(seat))?;
+    self.hidden_plays[seat] += 1;
+    self
+      .cards
+      .move_card(id, Zone::Played(seat), Visibility::Public)?;
+    Ok(id)
+  }
+
+  /// Throw the whole hand of `seat` away without drawing, as at the end of a round of a game
+  /// that keeps dealing from the same stub. Cards dealt to it next stay private until it shows
+  /// again.
+  pub fn discard_hand(&mut self, seat: usize) -> anyhow::Result<Vec<usize>> {
+    self.check_seat(seat)?;
+    let ids = self.hand(seat);
+    self.cards.discard(seat, &ids)?;
+    self.shown[seat] = false;
+    self.hidden_plays[seat] = 0;
+    Ok(ids)
+  }
+
+  /// Seat that shuffles the discard pile next, while it is being reshuffled.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    self
+      .reshuffle
+      .as_ref()
+      .map(|reshuffle| (reshuffle.first_shuffler + reshuffle.shuffles) % self.num_of_players())
+  }
+
+  /// The discard pile as shuffled so far.
+  pub fn pile(&self) -> Option<&[MaskedCard]> {
+    self
+      .reshuffle
+      .as_ref()
+      .map(|reshuffle| reshuffle.deck.as_slice())
+  }
+
+  /// Shuffle and remask the discard pile, as the next shuffler does locally, with the
+  /// parameters `cache` derives for its size.
+  pub fn shuffle_pile<R: Rng>(
+    &self,
+    rng: &mut R,
+    cache: &mut ParameterCache,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let pile = self
+      .pile()
+      .ok_or(GameErrors::IllegalAction("shuffle without a reshuffle"))?;
+
+    reshuffle(rng, cache, self.joint_pk(), pile)
+  }
+
+  /// Verify and adopt the shuffle of the discard pile by `seat`. Once every player has
+  /// shuffled, the pile becomes the new stub and the draw that needed it is completed.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    cache: &mut ParameterCache,
+    shuffled_pile: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    let num_of_players = self.num_of_players();
+    let joint_pk = *self.cards.joint_pk();
+    let reshuffle = self.reshuffle.as_mut().unwrap();
+
+    verify_reshuffle(
+      cache,
+      &joint_pk,
+      &reshuffle.deck,
+      &shuffled_pile,
+      shuffle_proof,
+    )?;
+    reshuffle.deck = shuffled_pile;
+    reshuffle.shuffles += 1;
+    if reshuffle.shuffles < num_of_players {
+      return Ok(());
+    }
+
+    let reshuffle = self.reshuffle.take().unwrap();
+    for id in reshuffle.pile {
+      self
+        .cards
+        .move_card(id, Zone::Reshuffled, Visibility::FaceDown)?;
+    }
+    for card in reshuffle.deck {
+      self.cards.add_card(card, Zone::Deck)?;
+    }
+    self.deal_from_stub(reshuffle.seat, reshuffle.owed, Visibility::FaceDown)?;
+    Ok(())
+  }
+
+  /// Reveal tokens still missing: every player's token for the public cards, and the others'
+  /// tokens for a card only its owner knows. Pairs of seat and card id.
+  pub fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    self.cards.awaited_tokens()
+  }
+
+  /// Verify and record the reveal token of `seat` for the card `id`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    self.cards.add_reveal_token(seat, id, token)
+  }
+
+  /// Verified reveal tokens received so far for the card `id`. The owner of a card combines
+  /// them with its own token to peek at it.
+  pub fn reveal_tokens(&self, id: usize) -> Vec<RevealShare> {
+    self.cards.reveal_tokens(id)
+  }
+
+  /// Show the hand of `seat` at showdown, for everyone to open it with its reveal tokens.
+  pub fn show(&mut self, seat: usize) -> anyhow::Result<()> {
+    self.check_seat(seat)?;
+    self.shown[seat] = true;
+    for id in self.hand(seat) {
+      self
+        .cards
+        .move_card(id, Zone::Hand(seat), Visibility::Public)?;
+    }
+    Ok(())
+  }
+
+  /// The cards of `seat` once it has shown down and all their reveal tokens are in.
+  pub fn open_hand(&self, seat: usize) -> anyhow::Result<Vec<ClassicPlayingCard>> {
+    self.check_seat(seat)?;
+    self
+      .hand(seat)
+      .into_iter()
+      .map(|id| match self.opened_card(id) {
+        Some(card) if self.shown[seat] => Ok(card),
+        _ => Err(GameErrors::CardNotOpened(id as u32).into()),
+      })
+      .collect()
+  }
+
+  // The ids of the `count` cards on top of the stub
+  fn stub(&self, count: usize) -> Vec<usize> {
+    self.cards.cards_in(Zone::Deck)[..count].to_vec()
+  }
+
+  fn deal_from_stub(
+    &mut self,
+    seat: usize,
+    count: usize,
+    visibility: Visibility,
+  ) -> anyhow::Result<Vec<usize>> {
+    let visibility = if self.shown[seat] {
+      Visibility::Public
+    } else {
+      visibility
+    };
+    let ids = self.stub(count);
+    self.cards.deal_as(seat, count, visibility)?;
+    Ok(ids)
+  }
+
+  fn check_stub(&self, count: usize) -> Result<(), GameErrors> {
+    if self.reshuffle.is_some() {
+      return Err(GameErrors::IllegalAction("deal during a reshuffle"));
+    }
+    let stub_len = self.stub_len();
+    if count > stub_len {
+      return Err(GameErrors::NotEnoughCards(stub_len));
+    }
+    Ok(())
+  }
+
+  fn check_seat(&self, seat: usize) -> Result<(), GameErrors> {
+    if seat < self.num_of_players() {
+      Ok(())
+    } else {
+      Err(GameErrors::InvalidSeat(seat as u32))
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{deliver_tokens, key_info, setup, shuffled_deck};
+  use crate::{open_card, Player};
+
+  use rand::thread_rng;
+
+  fn new_deck<'a, R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[Player],
+    reshuffle_discards: bool,
+  ) -> DrawDeck<'a> {
+    let deck = shuffled_deck(rng, parameters, card_mapping, players);
+    DrawDeck::new(
+      parameters,
+      card_mapping,
+      &key_info(players),
+      deck,
+      reshuffle_discards,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn discards_stay_masked() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, mut players) = setup(rng, 2);
+    let mut deck = new_deck(rng, &parameters, &card_mapping, &players, false);
+
+    for seat in 0..2 {
+      deck.deal(seat, 5).unwrap();
+    }
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    for (seat, player) in players.iter_mut().enumerate() {
+      for id in deck.hand(seat) {
+        let card = *deck.card(id).unwrap();
+        player.receive_card(card);
+        player
+          .peek_at_card(
+            rng,
+            &parameters,
+            &mut deck.reveal_tokens(id),
+            &card_mapping,
+            &card,
+          )
+          .unwrap();
+      }
+    }
+
+    // Seat 0 throws away three cards and draws three new ones
+    let thrown: Vec<usize> = deck.hand(0)[..3].to_vec();
+    assert!(deck.discard(1, &thrown).is_err());
+    assert!(deck.discard(0, &[thrown[0], thrown[0]]).is_err());
+    for id in &thrown {
+      let card = *deck.card(*id).unwrap();
+      assert!(players[0].discard(&card).unwrap().is_some());
+      assert!(players[0].discard(&card).is_err());
+    }
+    let drawn = deck.discard(0, &thrown).unwrap();
+    assert_eq!(drawn, vec![10, 11, 12]);
+    assert_eq!(deck.hand(0).len(), 5);
+    assert_eq!(deck.discards(), thrown);
+    assert_eq!(players[0].cards.len(), 2);
+
+    // Nobody can hand out a token for a discard, the owner included
+    for seat in 0..2 {
+      let token = players[seat]
+        .compute_reveal_token(rng, &parameters, deck.card(thrown[0]).unwrap())
+        .unwrap();
+      assert!(deck.add_reveal_token(seat, thrown[0], token).is_err());
+    }
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert_eq!(deck.reveal_tokens(drawn[0]).len(), 1);
+
+    // At showdown the kept and drawn cards open, the discards never do
+    deck.show(0).unwrap();
+    assert!(deck.open_hand(0).is_err());
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    let hand = deck.open_hand(0).unwrap();
+    assert_eq!(hand.len(), 5);
+    let kept: Vec<ClassicPlayingCard> = players[0]
+      .opened_cards
+      .iter()
+      .map(|card| card.unwrap())
+      .collect();
+    assert_eq!(hand[..2], kept[..]);
+    assert_eq!(deck.reveal_tokens(thrown[0]).len(), 1);
+
+    // Without reshuffling, a draw the stub cannot cover is refused
+    let stub = deck.stub_len();
+    deck.deal(1, stub - 2).unwrap();
+    assert!(deck.discard(0, &deck.hand(0)[..3].to_vec()).is_err());
+    assert_eq!(deck.hand(0).len(), 5);
+  }
+
+  #[test]
+  fn plays_a_card_without_telling_which() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, 2);
+    let mut deck = new_deck(rng, &parameters, &card_mapping, &players, false);
+
+    deck.deal(0, 5).unwrap();
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    let ids = deck.hand(0);
+    let hand: Vec<MaskedCard> = ids.iter().map(|id| *deck.card(*id).unwrap()).collect();
+    let mut tokens = deck.reveal_tokens(ids[3]);
+    tokens.push(
+      players[0]
+        .compute_reveal_token(rng, &parameters, &hand[3])
+        .unwrap(),
+    );
+    let expected = open_card(&parameters, &tokens, &card_mapping, &hand[3]).unwrap();
+
+    let (card, proof) = players[0]
+      .prove_in_hand(rng, &parameters, deck.joint_pk(), &hand, &hand[3])
+      .unwrap();
+    assert!(deck.play_hidden(1, card, &proof).is_err());
+    let played = deck.play_hidden(0, card, &proof).unwrap();
+    // Sending the same play again stores nothing, before or after it opens
+    let err = deck.play_hidden(0, card, &proof).unwrap_err();
+    assert_eq!(
+      err.downcast::<GameErrors>().unwrap(),
+      GameErrors::CardAlreadyPlayed(played as u32)
+    );
+    assert_eq!(deck.zone(played), Some(Zone::Played(0)));
+    assert_eq!(deck.hand(0), ids);
+    assert_eq!(deck.awaited_tokens().len(), 2);
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert_eq!(deck.opened_card(played), Some(expected));
+    assert!(deck.play_hidden(0, card, &proof).is_err());
+    assert_eq!(deck.awaited_tokens(), vec![]);
+
+    // The same card played a second time is caught when it opens
+    let (card, proof) = players[0]
+      .prove_in_hand(rng, &parameters, deck.joint_pk(), &hand, &hand[3])
+      .unwrap();
+    let replayed = deck.play_hidden(0, card, &proof).unwrap();
+    let mut results = vec![];
+    for (seat, id) in deck.awaited_tokens() {
+      let token = players[seat]
+        .compute_reveal_token(rng, &parameters, deck.card(id).unwrap())
+        .unwrap();
+      results.push(deck.add_reveal_token(seat, id, token));
+    }
+    let err = results.pop().unwrap().unwrap_err();
+    assert_eq!(
+      err.downcast::<GameErrors>().unwrap(),
+      GameErrors::CardAlreadyPlayed(replayed as u32)
+    );
+    assert_eq!(deck.opened_card(replayed), None);
+
+    let (card, proof) = players[0]
+      .prove_in_hand(rng, &parameters, deck.joint_pk(), &hand, &hand[1])
+      .unwrap();
+    let played = deck.play_hidden(0, card, &proof).unwrap();
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert!(deck.opened_card(played).is_some());
+    assert_eq!(deck.cards_left(0), 2);
+
+    // So is the original played openly after it was played hidden
+    deck.play(0, ids[3]).unwrap();
+    assert_eq!(deck.cards_left(0), 1);
+    assert_eq!(deck.awaited_tokens(), vec![(0, ids[3])]);
+    for (seat, id) in deck.awaited_tokens() {
+      let token = players[seat]
+        .compute_reveal_token(rng, &parameters, deck.card(id).unwrap())
+        .unwrap();
+      let err = deck.add_reveal_token(seat, id, token).unwrap_err();
+      assert_eq!(
+        err.downcast::<GameErrors>().unwrap(),
+        GameErrors::CardAlreadyPlayed(ids[3] as u32)
+      );
+    }
+    assert_eq!(deck.opened_card(ids[3]), None);
+    // and the hand cannot throw away more cards than it has left
+    let err = deck.discard(0, &[ids[0], ids[2]]).unwrap_err();
+    assert_eq!(
+      err.downcast::<GameErrors>().unwrap(),
+      GameErrors::NotEnoughCards(1)
+    );
+    deck.discard(0, &[ids[0]]).unwrap();
+    assert_eq!(deck.cards_left(0), 1);
+  }
+
+  #[test]
+  fn reshuffles_the_discards_when_the_stub_runs_out() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, 2);
+    let mut deck = new_deck(rng, &parameters, &card_mapping, &players, true);
+
+    for seat in 0..2 {
+      deck.deal(seat, 5).unwrap();
+    }
+    // Both players throw away their whole hand until only two cards are left in the stub
+    for draw in 0..8 {
+      let seat = draw % 2;
+      deck.discard(seat, &deck.hand(seat)).unwrap();
+    }
+    assert_eq!(deck.stub_len(), 2);
+    assert_eq!(deck.discards().len(), 40);
+
+    let thrown = deck.hand(0);
+    let drawn = deck.discard(0, &thrown).unwrap();
+    assert_eq!(drawn.len(), 2);
+    assert!(deck.discard(1, &deck.hand(1)).is_err());
+
+    // The earlier 40 discards are shuffled by both players, starting left of the drawer
+    let mut cache = ParameterCache::new(&parameters);
+    assert_eq!(deck.pile().unwrap().len(), 40);
+    assert_eq!(deck.next_shuffler(), Some(1));
+    let (pile, proof) = deck.shuffle_pile(rng, &mut cache).unwrap();
+    assert!(deck.shuffle(0, &mut cache, pile.clone(), &proof).is_err());
+    deck.shuffle(1, &mut cache, pile, &proof).unwrap();
+    let (pile, proof) = deck.shuffle_pile(rng, &mut cache).unwrap();
+    deck.shuffle(0, &mut cache, pile, &proof).unwrap();
+
+    assert_eq!(deck.next_shuffler(), None);
+    assert_eq!(deck.hand(0).len(), 5);
+    assert_eq!(deck.stub_len(), 37);
+    assert_eq!(deck.discards(), thrown);
+    assert_eq!(deck.zone(0), Some(Zone::Reshuffled));
+
+    // Reshuffled cards open like any other
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    deck.show(0).unwrap();
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert_eq!(deck.open_hand(0).unwrap().len(), 5);
+  }
+}
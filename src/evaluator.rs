@@ -0,0 +1,613 @@
+//! Poker hand evaluation for `ClassicPlayingCard`s.
+//!
+//! `evaluate` ranks the best five-card hand that can be made from five or more cards (typically
+//! the two hole cards and five community cards of Texas Hold'em). Hands are compared through the
+//! total order of `HandRank`; equal ranks split the pot.
+//!
+//! Short-deck hands, dealt from Six to Ace, rank a flush above a full house and let the ace play
+//! low in A-6-7-8-9. Omaha hands must use exactly two hole cards and three board cards, and split
+//! games also rank the eight-or-better low with `LowRank`.
+
+use crate::{ClassicPlayingCard, GameErrors, Value};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum HandCategory {
+  HighCard,
+  OnePair,
+  TwoPair,
+  ThreeOfAKind,
+  Straight,
+  Flush,
+  FullHouse,
+  FourOfAKind,
+  StraightFlush,
+}
+
+impl HandCategory {
+  const VALUES: [Self; 9] = [
+    Self::HighCard,
+    Self::OnePair,
+    Self::TwoPair,
+    Self::ThreeOfAKind,
+    Self::Straight,
+    Self::Flush,
+    Self::FullHouse,
+    Self::FourOfAKind,
+    Self::StraightFlush,
+  ];
+}
+
+/// Strength of a five-card hand. The category sits in the high bits and the values that break
+/// ties within it (pair values first, then kickers) follow in decreasing order of importance,
+/// four bits each, so that comparing two ranks is a single integer comparison. Short-deck ranks
+/// carry a flag above the category and only compare meaningfully with each other.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct HandRank(u32);
+
+const SHORT_DECK: u32 = 1 << 24;
+
+impl HandRank {
+  fn new(category: HandCategory, values: &[usize], short_deck: bool) -> Self {
+    let mut score = (short_deck_order(category, short_deck) as u32) << 20;
+    for (i, value) in values.iter().enumerate() {
+      score |= (*value as u32) << (16 - 4 * i);
+    }
+    if short_deck {
+      score |= SHORT_DECK;
+    }
+
+    Self(score)
+  }
+
+  pub fn category(&self) -> HandCategory {
+    let category = HandCategory::VALUES[((self.0 >> 20) & 0xf) as usize];
+    short_deck_order(category, self.0 & SHORT_DECK != 0)
+  }
+}
+
+/// In a short deck a flush is rarer than a full house and ranks above it. Swapping the two is
+/// its own inverse, which turns a category into its place in the order and back.
+fn short_deck_order(category: HandCategory, short_deck: bool) -> HandCategory {
+  match (category, short_deck) {
+    (HandCategory::Flush, true) => HandCategory::FullHouse,
+    (HandCategory::FullHouse, true) => HandCategory::Flush,
+    (category, _) => category,
+  }
+}
+
+impl std::fmt::Debug for HandRank {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:?}({:05x})", self.category(), self.0 & 0xfffff)
+  }
+}
+
+/// Rank the best five-card hand among `cards`, which must be distinct.
+pub fn evaluate(cards: &[ClassicPlayingCard]) -> Result<HandRank, GameErrors> {
+  evaluate_with(cards, false)
+}
+
+/// Rank the best five-card hand among short-deck `cards`, from Six to Ace.
+pub fn evaluate_short_deck(cards: &[ClassicPlayingCard]) -> Result<HandRank, GameErrors> {
+  evaluate_with(cards, true)
+}
+
+fn evaluate_with(cards: &[ClassicPlayingCard], short_deck: bool) -> Result<HandRank, GameErrors> {
+  if cards.len() < 5 {
+    return Err(GameErrors::NotEnoughCards(cards.len()));
+  }
+
+  // Bit `v` is set when a card of value `v` (Two = 0, ..., Ace = 12) is present
+  let mut counts = [0u8; 13];
+  let mut suits = [0u16; 4];
+  let mut values = 0u16;
+  for card in cards {
+    let value = card.value as usize;
+    counts[value] += 1;
+    suits[card.suite as usize] |= 1 << value;
+    values |= 1 << value;
+  }
+
+  let rank = |category, values: &[usize]| HandRank::new(category, values, short_deck);
+  let straight = |values: u16| {
+    // Without the Two to Five, the ace plays low right below the Six
+    let low_ace = ((values >> Value::Ace as u16) & 1) << Value::Five as u16;
+    straight_high(if short_deck { values | low_ace } else { values })
+  };
+  let flush = suits.iter().copied().find(|suit| suit.count_ones() >= 5);
+
+  if let Some(high) = flush.and_then(straight) {
+    return Ok(rank(HandCategory::StraightFlush, &[high]));
+  }
+
+  if let Some(quads) = highest_with_count(&counts, 4, None) {
+    let kicker = top_values(values & !(1 << quads), 1);
+    return Ok(rank(HandCategory::FourOfAKind, &[quads, kicker[0]]));
+  }
+
+  if let Some(suit) = flush.filter(|_| short_deck) {
+    return Ok(rank(HandCategory::Flush, &top_values(suit, 5)));
+  }
+
+  if let Some(trips) = highest_with_count(&counts, 3, None) {
+    if let Some(pair) = highest_with_count(&counts, 2, Some(trips)) {
+      return Ok(rank(HandCategory::FullHouse, &[trips, pair]));
+    }
+  }
+
+  if let Some(suit) = flush {
+    return Ok(rank(HandCategory::Flush, &top_values(suit, 5)));
+  }
+
+  if let Some(high) = straight(values) {
+    return Ok(rank(HandCategory::Straight, &[high]));
+  }
+
+  if let Some(trips) = highest_with_count(&counts, 3, None) {
+    let mut ranks = vec![trips];
+    ranks.extend(top_values(values & !(1 << trips), 2));
+    return Ok(rank(HandCategory::ThreeOfAKind, &ranks));
+  }
+
+  if let Some(high_pair) = highest_with_count(&counts, 2, None) {
+    if let Some(low_pair) = highest_with_count(&counts, 2, Some(high_pair)) {
+      let kicker = top_values(values & !(1 << high_pair) & !(1 << low_pair), 1);
+      return Ok(rank(
+        HandCategory::TwoPair,
+        &[high_pair, low_pair, kicker[0]],
+      ));
+    }
+
+    let mut ranks = vec![high_pair];
+    ranks.extend(top_values(values & !(1 << high_pair), 3));
+    return Ok(rank(HandCategory::OnePair, &ranks));
+  }
+
+  Ok(rank(HandCategory::HighCard, &top_values(values, 5)))
+}
+
+/// Indices of the hands that share the best rank. More than one index means a split pot.
+pub fn winners<H: Ord>(hands: &[H]) -> Vec<usize> {
+  let best = match hands.iter().max() {
+    Some(best) => best,
+    None => return Vec::new(),
+  };
+
+  hands
+    .iter()
+    .enumerate()
+    .filter(|(_, hand)| *hand == best)
+    .map(|(i, _)| i)
+    .collect()
+}
+
+/// Strength of an eight-or-better low: five distinct values of eight or less, the ace playing
+/// low. Straights and flushes do not count against a low. The highest card is compared first
+/// and lower cards make a better low, which compares greater so that `winners` finds it.
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+pub struct LowRank(u32);
+
+impl Ord for LowRank {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    other.0.cmp(&self.0)
+  }
+}
+
+impl PartialOrd for LowRank {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl std::fmt::Debug for LowRank {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Low({:05x})", self.0)
+  }
+}
+
+/// Rank the best eight-or-better low among `cards`, or `None` if they do not qualify.
+pub fn evaluate_low(cards: &[ClassicPlayingCard]) -> Result<Option<LowRank>, GameErrors> {
+  if cards.len() < 5 {
+    return Err(GameErrors::NotEnoughCards(cards.len()));
+  }
+
+  // Bit `v` is set when a card of low value `v` (Ace = 1, ..., Eight = 8) is present
+  let values = cards
+    .iter()
+    .map(|card| low_value(card.value))
+    .filter(|value| *value <= 8)
+    .fold(0u16, |values, value| values | (1 << value));
+  if values.count_ones() < 5 {
+    return Ok(None);
+  }
+
+  let score = (1..=8)
+    .filter(|value| values & (1 << value) != 0)
+    .take(5)
+    .enumerate()
+    .fold(0, |score, (i, value)| score | (value as u32) << (4 * i));
+  Ok(Some(LowRank(score)))
+}
+
+/// Rank the best Omaha hand, made of exactly two of the `hole` cards and three of the `board`
+/// cards.
+pub fn evaluate_omaha(
+  hole: &[ClassicPlayingCard],
+  board: &[ClassicPlayingCard],
+) -> Result<HandRank, GameErrors> {
+  let mut best = None;
+  for cards in omaha_hands(hole, board)? {
+    best = best.max(Some(evaluate(&cards)?));
+  }
+
+  best.ok_or(GameErrors::NotEnoughCards(hole.len() + board.len()))
+}
+
+/// Rank the best Omaha eight-or-better low, made of exactly two of the `hole` cards and three
+/// of the `board` cards.
+pub fn evaluate_omaha_low(
+  hole: &[ClassicPlayingCard],
+  board: &[ClassicPlayingCard],
+) -> Result<Option<LowRank>, GameErrors> {
+  let mut best = None;
+  for cards in omaha_hands(hole, board)? {
+    best = best.max(evaluate_low(&cards)?);
+  }
+
+  Ok(best)
+}
+
+fn omaha_hands(
+  hole: &[ClassicPlayingCard],
+  board: &[ClassicPlayingCard],
+) -> Result<Vec<Vec<ClassicPlayingCard>>, GameErrors> {
+  if hole.len() < 2 || board.len() < 3 {
+    return Err(GameErrors::NotEnoughCards(hole.len() + board.len()));
+  }
+
+  let mut hands = vec![];
+  for two in combinations(hole, 2) {
+    for three in combinations(board, 3) {
+      hands.push([two.as_slice(), three.as_slice()].concat());
+    }
+  }
+  Ok(hands)
+}
+
+fn combinations(cards: &[ClassicPlayingCard], k: usize) -> Vec<Vec<ClassicPlayingCard>> {
+  if k == 0 {
+    return vec![vec![]];
+  }
+
+  (0..cards.len())
+    .flat_map(|first| {
+      combinations(&cards[first + 1..], k - 1)
+        .into_iter()
+        .map(move |mut rest| {
+          rest.insert(0, cards[first]);
+          rest
+        })
+    })
+    .collect()
+}
+
+fn low_value(value: Value) -> usize {
+  match value {
+    Value::Ace => 1,
+    value => value as usize + 2,
+  }
+}
+
+/// Highest value of a straight contained in `values`. The ace also plays low, so the wheel
+/// A-2-3-4-5 is a five-high straight.
+fn straight_high(values: u16) -> Option<usize> {
+  // Shift by one to make room for the low ace at bit 0
+  let extended = (values << 1) | ((values >> Value::Ace as u16) & 1);
+  (4..=13)
+    .rev()
+    .find(|high| (extended >> (high - 4)) & 0b11111 == 0b11111)
+    .map(|high| high - 1)
+}
+
+fn highest_with_count(counts: &[u8; 13], count: u8, except: Option<usize>) -> Option<usize> {
+  (0..13)
+    .rev()
+    .find(|value| counts[*value] >= count && Some(*value) != except)
+}
+
+fn top_values(values: u16, n: usize) -> Vec<usize> {
+  (0..13)
+    .rev()
+    .filter(|value| values & (1 << value) != 0)
+    .take(n)
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Suite;
+
+  use std::collections::{HashMap, HashSet};
+
+  fn deck() -> Vec<ClassicPlayingCard> {
+    Value::VALUES
+      .iter()
+      .flat_map(|value| {
+        Suite::VALUES
+          .iter()
+          .map(move |suite| ClassicPlayingCard::new(*value, *suite))
+      })
+      .collect()
+  }
+
+  fn hand(cards: &str) -> Vec<ClassicPlayingCard> {
+    cards
+      .split_whitespace()
+      .map(|card| {
+        let (value, suite) = card.split_at(card.len() - 1);
+        let value = match value {
+          "2" => Value::Two,
+          "3" => Value::Three,
+          "4" => Value::Four,
+          "5" => Value::Five,
+          "6" => Value::Six,
+          "7" => Value::Seven,
+          "8" => Value::Eight,
+          "9" => Value::Nine,
+          "T" => Value::Ten,
+          "J" => Value::Jack,
+          "Q" => Value::Queen,
+          "K" => Value::King,
+          "A" => Value::Ace,
+          _ => panic!("unknown value {}", value),
+        };
+        let suite = match suite {
+          "c" => Suite::Club,
+          "d" => Suite::Diamond,
+          "h" => Suite::Heart,
+          "s" => Suite::Spade,
+          _ => panic!("unknown suite {}", suite),
+        };
+        ClassicPlayingCard::new(value, suite)
+      })
+      .collect()
+  }
+
+  fn rank(cards: &str) -> HandRank {
+    evaluate(&hand(cards)).unwrap()
+  }
+
+  #[test]
+  fn counts_every_five_card_hand() {
+    let deck = deck();
+    let mut counts: HashMap<HandCategory, usize> = HashMap::new();
+    let mut distinct: HashMap<HandCategory, HashSet<HandRank>> = HashMap::new();
+
+    for a in 0..52 {
+      for b in a + 1..52 {
+        for c in b + 1..52 {
+          for d in c + 1..52 {
+            for e in d + 1..52 {
+              let rank = evaluate(&[deck[a], deck[b], deck[c], deck[d], deck[e]]).unwrap();
+              *counts.entry(rank.category()).or_default() += 1;
+              distinct.entry(rank.category()).or_default().insert(rank);
+            }
+          }
+        }
+      }
+    }
+
+    let expected = [
+      (HandCategory::StraightFlush, 40, 10),
+      (HandCategory::FourOfAKind, 624, 156),
+      (HandCategory::FullHouse, 3744, 156),
+      (HandCategory::Flush, 5108, 1277),
+      (HandCategory::Straight, 10200, 10),
+      (HandCategory::ThreeOfAKind, 54912, 858),
+      (HandCategory::TwoPair, 123552, 858),
+      (HandCategory::OnePair, 1098240, 2860),
+      (HandCategory::HighCard, 1302540, 1277),
+    ];
+    for (category, hands, classes) in expected {
+      assert_eq!(counts[&category], hands, "{:?}", category);
+      assert_eq!(distinct[&category].len(), classes, "{:?}", category);
+    }
+  }
+
+  // Takes a few minutes in debug builds: `cargo test --release -- --ignored`
+  #[test]
+  #[ignore]
+  fn counts_every_seven_card_hand() {
+    let deck = deck();
+    let mut counts = [0usize; 9];
+
+    for a in 0..52 {
+      for b in a + 1..52 {
+        for c in b + 1..52 {
+          for d in c + 1..52 {
+            for e in d + 1..52 {
+              for f in e + 1..52 {
+                for g in f + 1..52 {
+                  let cards = [
+                    deck[a], deck[b], deck[c], deck[d], deck[e], deck[f], deck[g],
+                  ];
+                  counts[evaluate(&cards).unwrap().category() as usize] += 1;
+                }
+              }
+            }
+          }
+        }
+      }
+    }
+
+    assert_eq!(
+      counts,
+      [23294460, 58627800, 31433400, 6461620, 6180020, 4047644, 3473184, 224848, 41584]
+    );
+  }
+
+  #[test]
+  fn ranks_categories_and_kickers() {
+    assert_eq!(rank("2c 3d 4h 5s Ad").category(), HandCategory::Straight);
+    assert!(rank("2c 3d 4h 5s Ad") < rank("2c 3d 4h 5s 6d"));
+    assert_eq!(
+      rank("Ah 2h 3h 4h 5h").category(),
+      HandCategory::StraightFlush
+    );
+    assert!(rank("Ah 2h 3h 4h 5h") < rank("2h 3h 4h 5h 6h"));
+    assert!(rank("Qc Kc Ad Ah 2s") < rank("Qc Kc Ad Ah 3s"));
+    assert!(rank("Ac Ad 9h 9s Ks") > rank("Ac Ad 9h 9s Qs"));
+    assert!(rank("Ac Ad Ah 2s 2d") > rank("Kc Kd Kh As Ad"));
+    assert!(rank("2c 2d 2h 2s 3d") > rank("Ac Kd Kh Ks Ad"));
+    assert!(rank("Tc Jc Qc Kc Ac") > rank("2c 2d 2h 2s 3d"));
+  }
+
+  #[test]
+  fn picks_the_best_five_out_of_seven() {
+    // Three pairs: the lowest pair cannot play and the kicker comes from the rest
+    assert_eq!(rank("Ac Ad Kh Ks Qd Qh 2c"), rank("Ac Ad Kh Ks Qd"));
+    // Two sets make a full house
+    assert_eq!(rank("9c 9d 9h 5s 5d 5h 2c"), rank("9c 9d 9h 5s 5d"));
+    // A flush on the board is beaten by a straight flush in hand
+    assert_eq!(
+      rank("2h 3h 4h 5h 9h Ah Kd").category(),
+      HandCategory::StraightFlush
+    );
+    // A six-card straight plays its top five
+    assert_eq!(rank("2c 3d 4h 5s 6d 7c Kd"), rank("3d 4h 5s 6d 7c"));
+    assert_eq!(
+      evaluate(&hand("Ac Ad 2c 2d")),
+      Err(GameErrors::NotEnoughCards(4))
+    );
+  }
+
+  #[test]
+  fn detects_split_pots() {
+    let board = "Ac Kd Qh Js 9c";
+    let hands = [
+      rank(&format!("{} 2c 3d", board)),
+      rank(&format!("{} 2h 3s", board)),
+      rank(&format!("{} Tc 2d", board)),
+      rank(&format!("{} Th 3h", board)),
+    ];
+    assert_eq!(winners(&hands), vec![2, 3]);
+    assert_eq!(winners(&hands[..2]), vec![0, 1]);
+    assert!(winners::<HandRank>(&[]).is_empty());
+  }
+
+  #[test]
+  fn counts_every_short_deck_hand() {
+    let deck: Vec<ClassicPlayingCard> = deck()
+      .into_iter()
+      .filter(|card| card.value >= Value::Six)
+      .collect();
+    assert_eq!(deck.len(), 36);
+    let mut counts = [0usize; 9];
+
+    for a in 0..36 {
+      for b in a + 1..36 {
+        for c in b + 1..36 {
+          for d in c + 1..36 {
+            for e in d + 1..36 {
+              let cards = [deck[a], deck[b], deck[c], deck[d], deck[e]];
+              counts[evaluate_short_deck(&cards).unwrap().category() as usize] += 1;
+            }
+          }
+        }
+      }
+    }
+
+    assert_eq!(
+      counts,
+      [122400, 193536, 36288, 16128, 6120, 480, 1728, 288, 24]
+    );
+  }
+
+  #[test]
+  fn ranks_short_deck_hands() {
+    let short = |cards: &str| evaluate_short_deck(&hand(cards)).unwrap();
+
+    assert_eq!(short("Ac 6d 7h 8s 9c").category(), HandCategory::Straight);
+    assert!(short("Ac 6d 7h 8s 9c") < short("6d 7h 8s 9c Td"));
+    assert!(short("Ac 6d 7h 8s 9c") > short("Ac Ad 7h 7s 9c"));
+    assert_eq!(
+      short("Ah 6h 7h 8h 9h").category(),
+      HandCategory::StraightFlush
+    );
+    // A flush beats a full house, and wins over one in the same seven cards
+    assert!(short("6h 7h 8h 9h Jh") > short("Ac Ad Ah Ks Kd"));
+    assert_eq!(
+      short("6h 7h 8h 9h Jh Jc Jd").category(),
+      HandCategory::Flush
+    );
+    assert!(short("Tc Td Th Ts 6d") > short("6h 7h 8h 9h Jh"));
+    // The regular rules still apply to a full deck
+    assert!(rank("2h 7h 8h 9h Jh") < rank("2c 2d 2s Ks Kd"));
+    assert_eq!(rank("Ac 6d 7h 8s 9c").category(), HandCategory::HighCard);
+  }
+
+  fn low(cards: &str) -> Option<LowRank> {
+    evaluate_low(&hand(cards)).unwrap()
+  }
+
+  #[test]
+  fn ranks_eight_or_better_lows() {
+    // The wheel is the best low even though it is a straight
+    assert!(low("Ac 2d 3h 4s 5c") > low("Ac 2d 3h 4s 6c"));
+    assert!(low("Ac 2d 3h 4s 6c") > low("Ac 2d 3h 5s 6c"));
+    // Highest cards are compared first
+    assert!(low("7c 5d 4h 3s 2c") > low("8c 3d 2h As 4c"));
+    assert!(low("8c 7d 6h 5s 4c").is_some());
+    assert_eq!(low("9c 7d 6h 5s 4c"), None);
+    // Pairs do not count
+    assert_eq!(low("Ac Ad 2h 3s 4c Kd 9h"), None);
+    assert_eq!(low("Ac Ad 2h 3s 4c 8d 9h"), low("Ac 2h 3s 4c 8d"));
+  }
+
+  #[test]
+  fn plays_exactly_two_hole_cards_in_omaha() {
+    let omaha = |hole: &str, board: &str| evaluate_omaha(&hand(hole), &hand(board)).unwrap();
+    let omaha_low =
+      |hole: &str, board: &str| evaluate_omaha_low(&hand(hole), &hand(board)).unwrap();
+
+    // Four hearts on the board and one in hand is no flush
+    assert_eq!(
+      omaha("Ah Kc Qd Js", "2h 5h 8h 9h Tc").category(),
+      HandCategory::Straight
+    );
+    assert_eq!(
+      omaha("Ah Kh Qd Js", "2h 5h 8h 9c Tc").category(),
+      HandCategory::Flush
+    );
+    // Four of a kind in hand only plays as a pair
+    assert_eq!(
+      omaha("Ac Ad Ah As", "2c 5d 8h 9c Jc").category(),
+      HandCategory::OnePair
+    );
+    // A full board needs two hole cards to join it
+    assert_eq!(
+      omaha("2c 3d 4h 7s", "Kc Kd Kh Qs Qc").category(),
+      HandCategory::ThreeOfAKind
+    );
+
+    // A low needs two low hole cards and three low board cards
+    assert_eq!(omaha_low("Ac Kd Qh Js", "2h 3h 4c 9h Tc"), None);
+    assert_eq!(
+      omaha_low("Ac 2d Qh Js", "2h 3h 4c 9h Tc"),
+      None,
+      "the 2 pairs with the board"
+    );
+    assert_eq!(
+      omaha_low("Ac 2d Qh Js", "3h 5h 4c 9h Tc"),
+      low("Ac 2d 3h 4c 5h")
+    );
+    assert_eq!(
+      omaha_low("Ac 2d Qh Js", "6h 7h 4c 9h 8c"),
+      low("7h 6h 4c 2d Ac")
+    );
+    assert_eq!(
+      evaluate_omaha(&hand("Ac Kd"), &hand("2h 3h")),
+      Err(GameErrors::NotEnoughCards(4))
+    );
+  }
+}
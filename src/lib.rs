@@ -1,18 +1,36 @@
 use anyhow;
-// use ark_ff::{to_bytes, UniformRand};
-use ark_std::{rand::Rng, One};
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
 use barnett_smart_protocol::discrete_log_cards;
+use barnett_smart_protocol::table;
 use barnett_smart_protocol::BarnettSmartProtocol;
-// use napi::bindgen_prelude::*;
-// use napi_derive::napi;
-// use proof_essentials::utils::permutation::Permutation;
-// use proof_essentials::utils::rand::sample_vector;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
 use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
 use rand::thread_rng;
 use std::collections::HashMap;
-// use std::iter::Iterator;
 use thiserror::Error;
 
+pub mod betting;
+pub mod blackjack;
+pub mod ccg;
+pub mod definitions;
+pub mod draw;
+pub mod evaluator;
+pub mod mahjong;
+pub mod pot;
+pub mod reshuffle;
+pub mod session;
+pub mod stud;
+#[cfg(test)]
+mod test_util;
+pub mod texas_holdem;
+pub mod tricks;
+pub mod zones;
+
+use evaluator::HandRank;
+
 // Choose elliptic curve setting
 type Curve = starknet_curve::Projective;
 type Scalar = starknet_curve::Fr;
@@ -21,7 +39,6 @@ type Scalar = starknet_curve::Fr;
 type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
 type CardParameters = discrete_log_cards::Parameters<Curve>;
 type PublicKey = discrete_log_cards::PublicKey<Curve>;
-type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
 
 type Card = discrete_log_cards::Card<Curve>;
 type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
@@ -30,6 +47,13 @@ type RevealToken = discrete_log_cards::RevealToken<Curve>;
 type ProofKeyOwnership = schnorr_identification::proof::Proof<Curve>;
 type RemaskingProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
 type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+type ShuffleProof = <CardProtocol<'static> as BarnettSmartProtocol>::ZKProofShuffle;
+type ExclusionProof = discrete_log_cards::exclusion::ExclusionProof<Curve>;
+type MembershipProof = discrete_log_cards::membership::MembershipProof<Curve>;
+type CutProof = discrete_log_cards::cut::CutProof<Curve>;
+
+// A verified reveal token with the key of the player who computed it
+type RevealShare = (RevealToken, RevealProof, PublicKey);
 
 #[derive(Error, Debug, PartialEq)]
 pub enum GameErrors {
@@ -38,9 +62,75 @@ pub enum GameErrors {
 
   #[error("Invalid card")]
   InvalidCard,
+
+  #[error("No card at position {0} of the deck")]
+  InvalidCardIndex(u32),
+
+  #[error("Card at position {0} has already been dealt")]
+  CardAlreadyDealt(u32),
+
+  #[error("The table has not been started")]
+  TableNotStarted,
+
+  #[error("The table has already been started")]
+  TableAlreadyStarted,
+
+  #[error("No player in seat {0}")]
+  InvalidSeat(u32),
+
+  #[error("A hand needs at least five cards, got {0}")]
+  NotEnoughCards(usize),
+
+  #[error("It is not the turn of seat {0}")]
+  NotYourTurn(u32),
+
+  #[error("Illegal action: {0}")]
+  IllegalAction(&'static str),
+
+  #[error("Amount {0} is outside of the allowed range {1}..={2}")]
+  InvalidAmount(u64, u64, u64),
+
+  #[error("Card at position {0} has not been opened")]
+  CardNotOpened(u32),
+
+  #[error("Expected one entry for each of the {0} seats")]
+  WrongSeatCount(u32),
+
+  #[error("Cannot deal a hand to {0} players")]
+  InvalidPlayerCount(u32),
+
+  #[error("Not waiting for a reveal token of seat {0} for card {1}")]
+  UnexpectedRevealToken(u32, u32),
+
+  #[error("Seat {0} must show, no other hand has been shown for a pot it contests")]
+  CannotMuck(u32),
+
+  #[error("The current hand is still being played")]
+  HandInProgress,
+
+  #[error("No completed hand to close")]
+  HandNotComplete,
+
+  #[error("Seat {0} has not sent a new key since the last hand")]
+  StaleKey(u32),
+
+  #[error("The cards do not make the {0}-card deck of this game")]
+  WrongDeck(u32),
+
+  #[error("Seat {0} must follow the suit led")]
+  MustFollowSuit(u32),
+
+  #[error("Card {0} opens to a card its player has already played")]
+  CardAlreadyPlayed(u32),
+
+  #[error("Invalid deck definition: {0}")]
+  InvalidDeckDefinition(String),
+
+  #[error("The deck list does not match the committed deck")]
+  DeckListMismatch,
 }
 
-#[derive(PartialEq, Clone, Copy, Eq)]
+#[derive(PartialEq, Clone, Copy, Eq, Hash, PartialOrd, Ord)]
 pub enum Suite {
   Club,
   Diamond,
@@ -52,7 +142,7 @@ impl Suite {
   const VALUES: [Self; 4] = [Self::Club, Self::Diamond, Self::Heart, Self::Spade];
 }
 
-#[derive(PartialEq, PartialOrd, Clone, Copy, Eq)]
+#[derive(PartialEq, PartialOrd, Clone, Copy, Eq, Hash, Ord)]
 pub enum Value {
   Two,
   Three,
@@ -87,7 +177,7 @@ impl Value {
   ];
 }
 
-#[derive(PartialEq, Clone, Eq, Copy)]
+#[derive(PartialEq, Clone, Eq, Copy, Hash, PartialOrd, Ord)]
 pub struct ClassicPlayingCard {
   value: Value,
   suite: Suite,
@@ -128,72 +218,765 @@ impl std::fmt::Debug for ClassicPlayingCard {
   }
 }
 
+#[napi]
 pub fn setup_protocol(m: u32, n: u32) -> u32 {
   m * n
 }
 
-#[derive(Clone)] // Derivamos Clone para la estructura
-struct Player {
-  name: Vec<u8>,
-  sk: SecretKey,
-  pk: PublicKey,
-  proof_key: ProofKeyOwnership,
-  cards: Vec<MaskedCard>,
-  opened_cards: Vec<Option<ClassicPlayingCard>>,
+#[napi]
+#[derive(Clone)]
+pub struct Player {
+  player: table::Player<Curve, ClassicPlayingCard>,
+}
+
+// The keys and cards live in the player state shared with the wasm bindings, and are read
+// through it everywhere else in the crate
+impl std::ops::Deref for Player {
+  type Target = table::Player<Curve, ClassicPlayingCard>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.player
+  }
+}
+
+impl std::ops::DerefMut for Player {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.player
+  }
 }
 
 impl Player {
-    pub fn new<R: Rng>(rng: &mut R, pp: &CardParameters, name: &Vec<u8>) -> anyhow::Result<Self> {
-    let (pk, sk) = CardProtocol::player_keygen(rng, pp)?;
-    let proof_key = CardProtocol::prove_key_ownership(rng, pp, &pk, &sk, name)?;
+  pub fn new<R: Rng>(rng: &mut R, pp: &CardParameters, name: &Vec<u8>) -> anyhow::Result<Self> {
     Ok(Self {
-      name: name.clone(),
-      sk,
-      pk,
-      proof_key,
-      cards: vec![],
-      opened_cards: vec![],
+      player: table::Player::new(rng, pp, name)?,
     })
   }
 
-    pub fn receive_card(&mut self, card: MaskedCard) {
-    self.cards.push(card);
-    self.opened_cards.push(None);
+  /// Throw a card away. Returns what it was if the player had peeked at it.
+  pub fn discard(&mut self, card: &MaskedCard) -> Result<Option<ClassicPlayingCard>, GameErrors> {
+    self
+      .player
+      .discard(card)
+      .map_err(|_| GameErrors::CardNotFound)
   }
 
-    pub fn peek_at_card(
+  pub fn peek_at_card<R: Rng>(
     &mut self,
+    rng: &mut R,
     parameters: &CardParameters,
     reveal_tokens: &mut Vec<(RevealToken, RevealProof, PublicKey)>,
     card_mappings: &HashMap<Card, ClassicPlayingCard>,
     card: &MaskedCard,
   ) -> Result<(), anyhow::Error> {
-    let i = self.cards.iter().position(|&x| x == *card);
+    self
+      .player
+      .peek_at_card(rng, parameters, reveal_tokens, card_mappings, card)?;
+    Ok(())
+  }
+
+  pub fn compute_reveal_token<R: Rng>(
+    &self,
+    rng: &mut R,
+    pp: &CardParameters,
+    card: &MaskedCard,
+  ) -> anyhow::Result<(RevealToken, RevealProof, PublicKey)> {
+    Ok(self.player.compute_reveal_token(rng, pp, card)?)
+  }
+
+  /// Prove that `card`, dealt to this player, opens to none of `excluded` without opening it.
+  /// `reveal_tokens` are the verified tokens of every other player for the card.
+  pub fn prove_exclusion<R: Rng>(
+    &self,
+    rng: &mut R,
+    pp: &CardParameters,
+    reveal_tokens: &[RevealShare],
+    card: &MaskedCard,
+    excluded: &[Card],
+  ) -> anyhow::Result<ExclusionProof> {
+    let tokens: Vec<RevealToken> = reveal_tokens.iter().map(|(token, _, _)| *token).collect();
+
+    Ok(discrete_log_cards::exclusion::prove_exclusion(
+      rng, pp, &self.pk, &self.sk, card, &tokens, excluded,
+    )?)
+  }
+
+  /// Remask `card` to play it without telling which of `hand`, the masked cards the others saw
+  /// dealt to this player, it is. Returns the new masked card with a proof that it is a remask
+  /// of one of `hand`.
+  pub fn prove_in_hand<R: Rng>(
+    &self,
+    rng: &mut R,
+    pp: &CardParameters,
+    joint_pk: &PublicKey,
+    hand: &[MaskedCard],
+    card: &MaskedCard,
+  ) -> anyhow::Result<(MaskedCard, MembershipProof)> {
+    let index = hand.iter().position(|x| x == card);
+    let index = index.ok_or(GameErrors::CardNotFound)?;
+    let alpha = Scalar::rand(rng);
+
+    Ok(discrete_log_cards::membership::prove_membership(
+      rng, pp, joint_pk, hand, index, &alpha,
+    )?)
+  }
+
+  /// Cut `deck` at `offset`, which only this player knows, remasking every card under
+  /// `joint_pk`. Returns the cut deck with a proof that it is a rotation of `deck`, which the
+  /// others check with `verify_cut`.
+  pub fn cut<R: Rng>(
+    &self,
+    rng: &mut R,
+    pp: &CardParameters,
+    joint_pk: &PublicKey,
+    deck: &[MaskedCard],
+    offset: usize,
+  ) -> anyhow::Result<(Vec<MaskedCard>, CutProof)> {
+    let masking_factors: Vec<Scalar> = (0..deck.len()).map(|_| Scalar::rand(rng)).collect();
+
+    Ok(discrete_log_cards::cut::cut_and_remask(
+      rng,
+      pp,
+      joint_pk,
+      deck,
+      offset,
+      &masking_factors,
+    )?)
+  }
+}
+
+/// The N-API surface for a player. The secret key never leaves the Rust side: JS only ever sees
+/// the player's name, public key and serialized reveal tokens.
+#[napi]
+impl Player {
+  #[napi(constructor)]
+  pub fn js_new(table: &Table, name: String) -> napi::Result<Self> {
+    let rng = &mut thread_rng();
+    Self::new(rng, &table.table.parameters, &name.into_bytes()).map_err(to_napi_error)
+  }
 
-    let i = i.ok_or(GameErrors::CardNotFound)?;
+  #[napi(getter)]
+  pub fn name(&self) -> String {
+    String::from_utf8_lossy(&self.name).into_owned()
+  }
 
-    //TODO add function to create that without the proof
+  #[napi]
+  pub fn public_key(&self) -> napi::Result<Buffer> {
+    serialize(&self.pk)
+  }
+
+  /// Serialized `(token, proof, public key)` triple for the card at `index` of the table deck.
+  #[napi(js_name = "computeRevealToken")]
+  pub fn js_compute_reveal_token(&self, table: &Table, index: u32) -> napi::Result<Buffer> {
+    let card = table.table.card_at(index).map_err(to_napi_error)?;
     let rng = &mut thread_rng();
-    let own_reveal_token = self.compute_reveal_token(rng, parameters, card)?;
-    reveal_tokens.push(own_reveal_token);
+    let token = self
+      .compute_reveal_token(rng, &table.table.parameters, card)
+      .map_err(to_napi_error)?;
+
+    serialize(&token)
+  }
+
+  /// Privately open a card dealt to this player using the reveal tokens of every other player.
+  #[napi(js_name = "peekAtCard")]
+  pub fn js_peek_at_card(
+    &mut self,
+    table: &Table,
+    index: u32,
+    reveal_tokens: Vec<Buffer>,
+  ) -> napi::Result<String> {
+    let card = table.table.card_at(index).map_err(to_napi_error)?;
+    let mut reveal_tokens = deserialize_tokens(&reveal_tokens)?;
+    let rng = &mut thread_rng();
+    let opened = self
+      .player
+      .peek_at_card(
+        rng,
+        &table.table.parameters,
+        &mut reveal_tokens,
+        &table.table.card_mapping,
+        card,
+      )
+      .map_err(to_napi_error)?;
+
+    Ok(format!("{:?}", opened))
+  }
+
+  /// The cards this player has opened so far, `null` for cards that are still masked.
+  #[napi]
+  pub fn opened_cards(&self) -> Vec<Option<String>> {
+    self
+      .opened_cards
+      .iter()
+      .map(|card| card.map(|c| format!("{:?}", c)))
+      .collect()
+  }
+}
+
+/// A table holds the public state every player agrees on, as `table::Table` does, and the cards
+/// being passed between seats.
+#[napi]
+pub struct Table {
+  table: table::Table<Curve, ClassicPlayingCard>,
+  pass: Option<CardPass>,
+}
+
+// Cards on their way face down from one seat to another, remasked by every player in turn
+struct CardPass {
+  from: u32,
+  to: u32,
+  indices: Vec<u32>,
+  cards: Vec<MaskedCard>,
+  remasks: usize,
+}
+
+impl Table {
+  pub fn new<R: Rng>(rng: &mut R, m: usize, n: usize) -> anyhow::Result<Self> {
+    let parameters = CardProtocol::setup(rng, m, n)?;
+    let card_mapping = encode_cards(rng, m * n);
+
+    Ok(Self {
+      table: table::Table::new(parameters, card_mapping),
+      pass: None,
+    })
+  }
+
+  /// Set up the table another player created, from its parameters and the plaintexts of its
+  /// cards as listed by `export_cards`, so that both hold the same table and can verify each
+  /// other's shuffles.
+  pub fn from_export(parameters: CardParameters, plaintexts: Vec<Card>) -> anyhow::Result<Self> {
+    let mut deck = classic_deck(parameters.m() * parameters.n());
+    deck.sort();
+    let num_of_cards = deck.len();
+    let card_mapping: HashMap<Card, ClassicPlayingCard> =
+      plaintexts.iter().copied().zip(deck).collect();
+    if plaintexts.len() != num_of_cards || card_mapping.len() != num_of_cards {
+      return Err(GameErrors::WrongDeck(num_of_cards as u32).into());
+    }
+
+    Ok(Self {
+      table: table::Table::new(parameters, card_mapping),
+      pass: None,
+    })
+  }
+
+  /// The plaintext of every card, in `deck_order`.
+  pub fn export_cards(&self) -> Vec<Card> {
+    deck_order(&self.table.card_mapping)
+      .into_iter()
+      .map(|(card, _)| *card)
+      .collect()
+  }
+
+  fn joint_pk(&self) -> anyhow::Result<&PublicKey> {
+    Ok(self.table.joint_pk()?)
+  }
+
+  fn card_at(&self, index: u32) -> anyhow::Result<&MaskedCard> {
+    Ok(self.table.card_at(index)?)
+  }
+
+  /// Compute the aggregate key of all joined players and mask the initial deck with it.
+  pub fn start<R: Rng>(&mut self, rng: &mut R) -> anyhow::Result<()> {
+    Ok(self.table.start(rng)?)
+  }
 
-    let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card)?;
-    let opened_card = card_mappings.get(&unmasked_card);
-    let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
+  /// Shuffle and remask the current deck, returning the new deck with its proof.
+  pub fn shuffle<R: Rng>(&self, rng: &mut R) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    Ok(self.table.shuffle(rng)?)
+  }
+
+  /// Verify a shuffle of the current deck and adopt the shuffled deck.
+  pub fn apply_shuffle(
+    &mut self,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    Ok(self.table.apply_shuffle(shuffled_deck, shuffle_proof)?)
+  }
 
-    self.opened_cards[i] = Some(*opened_card);
+  /// Start passing the cards at `indices`, dealt to `from`, face down to `to`, as in the Hearts
+  /// pass. `sender`, the player at `from`, must hold them. Every player then remasks them in
+  /// turn, starting left of `from`, so that the reveal tokens given out for them before the pass
+  /// no longer open them and `to` peeks at them with tokens of its own. They leave the hand of
+  /// `sender` once the pass is complete, with `receive_cards`. The cards keep their positions in
+  /// the deck, so the pass does not hide which cards were passed, and `sender` still knows them.
+  pub fn pass_cards(
+    &mut self,
+    sender: &Player,
+    from: u32,
+    to: u32,
+    indices: &[u32],
+  ) -> anyhow::Result<()> {
+    self.joint_pk()?;
+    if self.pass.is_some() {
+      return Err(GameErrors::IllegalAction("pass during another pass").into());
+    }
+    if to as usize >= self.table.players.len() || to == from {
+      return Err(GameErrors::InvalidSeat(to).into());
+    }
+    for (i, index) in indices.iter().enumerate() {
+      let card = self.card_at(*index)?;
+      let i_index = *index as usize;
+      if self.table.owners[i_index] != Some(from)
+        || self.table.opened_cards[i_index].is_some()
+        || indices[..i].contains(index)
+        || !sender.cards.contains(card)
+      {
+        return Err(GameErrors::CardNotFound.into());
+      }
+    }
+
+    let cards: Vec<MaskedCard> = indices
+      .iter()
+      .map(|index| self.table.deck[*index as usize])
+      .collect();
+    self.pass = Some(CardPass {
+      from,
+      to,
+      indices: indices.to_vec(),
+      cards,
+      remasks: 0,
+    });
     Ok(())
   }
 
-    pub fn compute_reveal_token<R: Rng>(
+  /// Seat that remasks the cards being passed next.
+  pub fn next_remasker(&self) -> Option<u32> {
+    let num_of_seats = self.table.players.len() as u32;
+    self
+      .pass
+      .as_ref()
+      .map(|pass| (pass.from + 1 + pass.remasks as u32) % num_of_seats)
+  }
+
+  /// Remask the cards being passed, as the next remasker does locally before sending them to the
+  /// others with a proof for every card.
+  pub fn remask_passed<R: Rng>(
     &self,
     rng: &mut R,
-    pp: &CardParameters,
-    card: &MaskedCard,
-  ) -> anyhow::Result<(RevealToken, RevealProof, PublicKey)> {
-    let (reveal_token, reveal_proof) =
-      CardProtocol::compute_reveal_token(rng, &pp, &self.sk, &self.pk, card)?;
+  ) -> anyhow::Result<(Vec<MaskedCard>, Vec<RemaskingProof>)> {
+    let joint_pk = self.joint_pk()?;
+    let pass = self
+      .pass
+      .as_ref()
+      .ok_or(GameErrors::IllegalAction("remask without a pass"))?;
+
+    let mut remasked = Vec::with_capacity(pass.cards.len());
+    let mut proofs = Vec::with_capacity(pass.cards.len());
+    for card in &pass.cards {
+      let alpha = Scalar::rand(rng);
+      let (card, proof) =
+        CardProtocol::remask(rng, &self.table.parameters, joint_pk, card, &alpha)?;
+      remasked.push(card);
+      proofs.push(proof);
+    }
+    Ok((remasked, proofs))
+  }
+
+  /// Verify and adopt the remask of the cards being passed by `seat`. Once every player has
+  /// remasked them, they take the place of the passed cards in the deck and belong to the
+  /// receiving seat.
+  pub fn apply_remask(
+    &mut self,
+    seat: u32,
+    cards: Vec<MaskedCard>,
+    proofs: &[RemaskingProof],
+  ) -> anyhow::Result<()> {
+    if self.next_remasker() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat).into());
+    }
+    let joint_pk = *self.joint_pk()?;
+    let num_of_seats = self.table.players.len();
+    let pass = self.pass.as_mut().unwrap();
+    if cards.len() != pass.cards.len() || proofs.len() != pass.cards.len() {
+      return Err(GameErrors::IllegalAction("remask of a different number of cards").into());
+    }
+
+    for ((original, remasked), proof) in pass.cards.iter().zip(&cards).zip(proofs) {
+      CardProtocol::verify_remask(&self.table.parameters, &joint_pk, original, remasked, proof)?;
+    }
+    pass.cards = cards;
+    pass.remasks += 1;
+    if pass.remasks < num_of_seats {
+      return Ok(());
+    }
+
+    let pass = self.pass.take().unwrap();
+    for (index, card) in pass.indices.iter().zip(pass.cards) {
+      self.table.deck[*index as usize] = card;
+      self.table.owners[*index as usize] = Some(pass.to);
+    }
+    Ok(())
+  }
+
+  /// Hand `receiver`, the player at `seat`, the cards the table records for it that it does not
+  /// hold yet, such as cards passed to it, and take back the cards it passed away. Returns the
+  /// positions of the cards received.
+  pub fn receive_cards(&self, receiver: &mut Player, seat: u32) -> Vec<u32> {
+    let hole_cards: Vec<MaskedCard> = self
+      .hole_cards(seat)
+      .iter()
+      .map(|index| self.table.deck[*index as usize])
+      .collect();
+    let passed: Vec<MaskedCard> = receiver
+      .cards
+      .iter()
+      .filter(|card| !hole_cards.contains(card))
+      .copied()
+      .collect();
+    for card in &passed {
+      receiver
+        .discard(card)
+        .expect("the receiver holds the cards it passed");
+    }
+
+    let received: Vec<u32> = self
+      .hole_cards(seat)
+      .into_iter()
+      .filter(|index| !receiver.cards.contains(&self.table.deck[*index as usize]))
+      .collect();
+    for index in &received {
+      receiver.receive_card(self.table.deck[*index as usize]);
+    }
+    received
+  }
+
+  /// Positions of the cards dealt to `seat`.
+  fn hole_cards(&self, seat: u32) -> Vec<u32> {
+    (0..self.table.owners.len() as u32)
+      .filter(|index| self.table.owners[*index as usize] == Some(seat))
+      .collect()
+  }
+
+  fn opened_card(&self, index: u32) -> anyhow::Result<ClassicPlayingCard> {
+    self.card_at(index)?;
+    Ok(self.table.opened_cards[index as usize].ok_or(GameErrors::CardNotOpened(index))?)
+  }
+
+  /// Rank the best hand of `seat` out of the cards dealt to it and the `board`, all of which
+  /// must have been opened with `open_card`.
+  pub fn rank_hand(&self, seat: u32, board: &[u32]) -> anyhow::Result<HandRank> {
+    let cards = self
+      .hole_cards(seat)
+      .into_iter()
+      .chain(board.iter().copied())
+      .map(|index| self.opened_card(index))
+      .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(evaluator::evaluate(&cards)?)
+  }
+
+  /// Build the main and side pots from what each seat put in and share them among the best
+  /// hands. A seat that folded, or did not open all of its cards, is treated as having mucked.
+  pub fn settle(
+    &self,
+    board: &[u32],
+    contributions: &[u64],
+    folded: &[bool],
+    button: u32,
+  ) -> anyhow::Result<Vec<u64>> {
+    let num_of_seats = self.table.players.len();
+    if contributions.len() != num_of_seats || folded.len() != num_of_seats {
+      return Err(GameErrors::WrongSeatCount(num_of_seats as u32).into());
+    }
+    if button as usize >= num_of_seats {
+      return Err(GameErrors::InvalidSeat(button).into());
+    }
+
+    let hands = (0..num_of_seats as u32)
+      .map(|seat| {
+        let shown = self
+          .hole_cards(seat)
+          .iter()
+          .all(|index| self.table.opened_cards[*index as usize].is_some());
+        if folded[seat as usize] || !shown {
+          Ok(None)
+        } else {
+          self.rank_hand(seat, board).map(Some)
+        }
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let pots = pot::build_pots(contributions, folded);
+    Ok(pot::distribute(&pots, &hands, button as usize))
+  }
+}
+
+#[napi]
+impl Table {
+  #[napi(constructor)]
+  pub fn js_new(m: u32, n: u32) -> napi::Result<Self> {
+    let rng = &mut thread_rng();
+    Self::new(rng, m as usize, n as usize).map_err(to_napi_error)
+  }
+
+  /// Set up the table another player created, from the bytes of its `exportParameters` and
+  /// `exportCards`.
+  #[napi(factory, js_name = "fromExport")]
+  pub fn js_from_export(parameters: Buffer, cards: Buffer) -> napi::Result<Self> {
+    Self::from_export(deserialize(&parameters)?, deserialize(&cards)?).map_err(to_napi_error)
+  }
+
+  /// The serialized protocol parameters, to be sent to the other players with `exportCards`.
+  #[napi(js_name = "exportParameters")]
+  pub fn export_parameters(&self) -> napi::Result<Buffer> {
+    serialize(&self.table.parameters)
+  }
+
+  /// The serialized plaintexts of the cards, in card order.
+  #[napi(js_name = "exportCards")]
+  pub fn js_export_cards(&self) -> napi::Result<Buffer> {
+    serialize(&self.export_cards())
+  }
+
+  /// Register a player and verify their proof of key ownership. Returns the player's seat.
+  #[napi]
+  pub fn join(&mut self, player: &Player) -> napi::Result<u32> {
+    self.table.join(&player.player).map_err(to_napi_error)
+  }
+
+  #[napi(js_name = "start")]
+  pub fn js_start(&mut self) -> napi::Result<()> {
+    let rng = &mut thread_rng();
+    self.start(rng).map_err(to_napi_error)
+  }
+
+  /// Shuffle the deck locally. Returns the serialized `(deck, proof)` pair so that it can be
+  /// broadcast to the other players, who adopt it with `applyShuffle`.
+  #[napi(js_name = "shuffle")]
+  pub fn js_shuffle(&mut self) -> napi::Result<Buffer> {
+    let rng = &mut thread_rng();
+    let (shuffled_deck, shuffle_proof) = self.shuffle(rng).map_err(to_napi_error)?;
+
+    // Same layout as the `(deck, proof)` tuple read back by `applyShuffle`
+    let mut bytes = Vec::new();
+    shuffled_deck.serialize(&mut bytes).map_err(to_napi_error)?;
+    shuffle_proof.serialize(&mut bytes).map_err(to_napi_error)?;
 
-    Ok((reveal_token, reveal_proof, self.pk))
+    self
+      .apply_shuffle(shuffled_deck, &shuffle_proof)
+      .map_err(to_napi_error)?;
+    Ok(bytes.into())
   }
+
+  #[napi(js_name = "applyShuffle")]
+  pub fn js_apply_shuffle(&mut self, shuffle: Buffer) -> napi::Result<()> {
+    let (shuffled_deck, shuffle_proof): (Vec<MaskedCard>, ShuffleProof) = deserialize(&shuffle)?;
+    self
+      .apply_shuffle(shuffled_deck, &shuffle_proof)
+      .map_err(to_napi_error)
+  }
+
+  /// Give the card at `index` of the deck to the player sitting at `seat`.
+  #[napi]
+  pub fn deal(&mut self, player: &mut Player, seat: u32, index: u32) -> napi::Result<()> {
+    self
+      .table
+      .deal(&mut player.player, seat, index)
+      .map_err(to_napi_error)
+  }
+
+  /// Publicly open the card at `index` using the reveal tokens of every player.
+  #[napi]
+  pub fn open_card(&mut self, index: u32, reveal_tokens: Vec<Buffer>) -> napi::Result<String> {
+    let reveal_tokens = deserialize_tokens(&reveal_tokens)?;
+    let opened = self
+      .table
+      .open_card(index, &reveal_tokens)
+      .map_err(to_napi_error)?;
+
+    Ok(format!("{:?}", opened))
+  }
+
+  /// Start passing the cards at `indices` from `sender`, sitting at `from`, face down to `to`.
+  #[napi(js_name = "passCards")]
+  pub fn js_pass_cards(
+    &mut self,
+    sender: &Player,
+    from: u32,
+    to: u32,
+    indices: Vec<u32>,
+  ) -> napi::Result<()> {
+    self
+      .pass_cards(sender, from, to, &indices)
+      .map_err(to_napi_error)
+  }
+
+  /// Seat that remasks the cards being passed next, `null` when no cards are being passed.
+  #[napi(getter, js_name = "nextRemasker")]
+  pub fn js_next_remasker(&self) -> Option<u32> {
+    self.next_remasker()
+  }
+
+  /// Remask the cards being passed as `seat`. Returns the serialized `(cards, proofs)` pair so
+  /// that it can be broadcast to the other players, who adopt it with `applyRemask`.
+  #[napi(js_name = "remaskPassed")]
+  pub fn js_remask_passed(&mut self, seat: u32) -> napi::Result<Buffer> {
+    let rng = &mut thread_rng();
+    let (cards, proofs) = self.remask_passed(rng).map_err(to_napi_error)?;
+
+    // Same layout as the `(cards, proofs)` tuple read back by `applyRemask`
+    let mut bytes = Vec::new();
+    cards.serialize(&mut bytes).map_err(to_napi_error)?;
+    proofs.serialize(&mut bytes).map_err(to_napi_error)?;
+
+    self
+      .apply_remask(seat, cards, &proofs)
+      .map_err(to_napi_error)?;
+    Ok(bytes.into())
+  }
+
+  #[napi(js_name = "applyRemask")]
+  pub fn js_apply_remask(&mut self, seat: u32, remask: Buffer) -> napi::Result<()> {
+    let (cards, proofs): (Vec<MaskedCard>, Vec<RemaskingProof>) = deserialize(&remask)?;
+    self
+      .apply_remask(seat, cards, &proofs)
+      .map_err(to_napi_error)
+  }
+
+  /// Give `player`, sitting at `seat`, the cards passed to it and take back the ones it passed.
+  /// Returns the positions of the cards received.
+  #[napi(js_name = "receiveCards")]
+  pub fn js_receive_cards(&self, player: &mut Player, seat: u32) -> Vec<u32> {
+    self.receive_cards(player, seat)
+  }
+
+  #[napi(getter)]
+  pub fn deck_size(&self) -> u32 {
+    self.table.deck.len() as u32
+  }
+
+  /// The seat holding the card at `index`, `null` if the card has not been dealt.
+  #[napi]
+  pub fn owner(&self, index: u32) -> napi::Result<Option<u32>> {
+    self.table.owner(index).map_err(to_napi_error)
+  }
+
+  /// Share the pots at showdown. `contributions` and `folded` hold one entry per seat; hands are
+  /// made of each seat's cards and the `board` positions, as opened with `openCard`. Returns the
+  /// chips won by every seat.
+  #[napi(js_name = "settle")]
+  pub fn js_settle(
+    &self,
+    board: Vec<u32>,
+    contributions: Vec<i64>,
+    folded: Vec<bool>,
+    button: u32,
+  ) -> napi::Result<Vec<i64>> {
+    let contributions = contributions
+      .iter()
+      .map(|c| u64::try_from(*c).map_err(|_| GameErrors::IllegalAction("negative contribution")))
+      .collect::<Result<Vec<u64>, _>>()
+      .map_err(to_napi_error)?;
+    let payouts = self
+      .settle(&board, &contributions, &folded, button)
+      .map_err(to_napi_error)?;
+    Ok(payouts.into_iter().map(|payout| payout as i64).collect())
+  }
+
+  /// The publicly opened cards of the deck, `null` for cards that are still masked.
+  #[napi]
+  pub fn opened_cards(&self) -> Vec<Option<String>> {
+    self
+      .table
+      .opened_cards
+      .iter()
+      .map(|card| card.map(|c| format!("{:?}", c)))
+      .collect()
+  }
+}
+
+/// Check that `cut_deck` is `deck` cut and remasked, from the proof of `Player::cut`.
+pub fn verify_cut(
+  parameters: &CardParameters,
+  joint_pk: &PublicKey,
+  deck: &[MaskedCard],
+  cut_deck: &[MaskedCard],
+  proof: &CutProof,
+) -> anyhow::Result<()> {
+  Ok(discrete_log_cards::cut::verify_cut(
+    parameters, joint_pk, deck, cut_deck, proof,
+  )?)
+}
+
+//Every player will have to calculate this function for cards that are in play
+pub fn open_card(
+  parameters: &CardParameters,
+  reveal_tokens: &Vec<(RevealToken, RevealProof, PublicKey)>,
+  card_mappings: &HashMap<Card, ClassicPlayingCard>,
+  card: &MaskedCard,
+) -> Result<ClassicPlayingCard, anyhow::Error> {
+  let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card)?;
+  let opened_card = card_mappings.get(&unmasked_card);
+  let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
+
+  Ok(*opened_card)
+}
+
+pub fn encode_cards<R: Rng>(rng: &mut R, num_of_cards: usize) -> HashMap<Card, ClassicPlayingCard> {
+  encode_deck(rng, &classic_deck(num_of_cards))
+}
+
+// Past 52 cards the deck repeats, as in a multi-deck shoe: every copy of a card gets a plaintext
+// of its own
+fn classic_deck(num_of_cards: usize) -> Vec<ClassicPlayingCard> {
+  Value::VALUES
+    .iter()
+    .flat_map(|value| {
+      Suite::VALUES
+        .iter()
+        .map(move |suite| ClassicPlayingCard::new(*value, *suite))
+    })
+    .cycle()
+    .take(num_of_cards)
+    .collect()
+}
+
+/// The entries of `card_mapping` in card order, the order the initial deck is masked in. Copies
+/// of a card in a multi-deck shoe are ordered by their serialized plaintext, so that every player
+/// builds the same deck.
+pub fn deck_order<T: Ord>(card_mapping: &HashMap<Card, T>) -> Vec<(&Card, &T)> {
+  let mut cards: Vec<(&Card, &T)> = card_mapping.iter().collect();
+  cards.sort_by_cached_key(|(card, value)| {
+    let mut bytes = vec![];
+    card
+      .serialize(&mut bytes)
+      .expect("serializing into a Vec cannot fail");
+    (*value, bytes)
+  });
+  cards
+}
+
+/// Map a random plaintext card to each of `deck`, for games that do not use the 52-card deck.
+pub fn encode_deck<R: Rng>(
+  rng: &mut R,
+  deck: &[ClassicPlayingCard],
+) -> HashMap<Card, ClassicPlayingCard> {
+  deck
+    .iter()
+    .map(|classic| (Card::rand(rng), *classic))
+    .collect()
+}
+
+fn to_napi_error<E: std::fmt::Display>(err: E) -> napi::Error {
+  napi::Error::from_reason(err.to_string())
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T) -> napi::Result<Buffer> {
+  let mut bytes = Vec::with_capacity(value.serialized_size());
+  value.serialize(&mut bytes).map_err(to_napi_error)?;
+  Ok(bytes.into())
+}
+
+fn deserialize<T: CanonicalDeserialize>(bytes: &[u8]) -> napi::Result<T> {
+  T::deserialize(bytes).map_err(to_napi_error)
+}
+
+fn deserialize_tokens(
+  reveal_tokens: &[Buffer],
+) -> napi::Result<Vec<(RevealToken, RevealProof, PublicKey)>> {
+  reveal_tokens
+    .iter()
+    .map(|token| deserialize(token))
+    .collect()
 }
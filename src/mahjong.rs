@@ -0,0 +1,767 @@
+//! Building the wall and dealing Mahjong over the mental poker protocol.
+//!
+//! `MahjongHand` is the public state of one hand that every player keeps in sync. The 144 tiles
+//! are masked in tile order and shuffled by the four players in turn, the protocol being set up
+//! for the `DIMENSIONS` plan. The shuffled deck is the wall: tile ids `2k` and `2k + 1` are the
+//! top and bottom of stack `k`, eighteen stacks in front of every seat in seat order.
+//!
+//! The dice that break the wall are rolled by commit and reveal: every seat commits to a seed with
+//! `dice_commitment`, then opens it once all the commitments are in, and the dice come from the
+//! hash of the seeds. Nobody can choose the roll, and everyone can check it. The last seat to open
+//! its seed does learn the roll first, though, and can refuse to open it, leaving the hand stuck:
+//! by aborting whenever it dislikes the roll it biases the dice, so the rules played on top should
+//! penalize a seat that does not open its seed. The dice count the sides counter-clockwise from
+//! the dealer, then the stacks from the right end of that side, where the live wall starts. The last fourteen tiles of the wall are the dead wall.
+//!
+//! Tiles are dealt through a zoned `Deck`: each seat peeks at its tiles with the reveal tokens the
+//! others publish for them, and a discard is opened with its owner's token. A seat that holds a
+//! bonus tile or a concealed kong lays it open and draws a replacement from the far end of the
+//! dead wall, which the live wall gives up a tile to. The hand ends in an exhaustive draw once the
+//! live wall runs out. Claims on discards and scoring are left to the rules played on top.
+
+use crate::zones::{Deck, Visibility, Zone};
+use crate::{
+  deck_order, Card, CardParameters, CardProtocol, GameErrors, MaskedCard, Player,
+  ProofKeyOwnership, PublicKey, RevealShare, Scalar, ShuffleProof,
+};
+
+use ark_ff::UniformRand;
+use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use blake2::{Blake2s, Digest};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use std::collections::{HashMap, VecDeque};
+
+pub const NUM_OF_PLAYERS: usize = 4;
+pub const NUM_OF_TILES: usize = 144;
+/// The `(m, n)` the protocol is set up with for the wall. The shuffle proof and its verification
+/// grow with `m + n`, which the square plan keeps the smallest for 144 tiles.
+pub const DIMENSIONS: (usize, usize) = (12, 12);
+const STACKS_PER_SIDE: usize = 18;
+const DEAD_WALL: usize = 14;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Wind {
+  East,
+  South,
+  West,
+  North,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Dragon {
+  Red,
+  Green,
+  White,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Tile {
+  /// Suited tiles, ranked 1 to 9.
+  Dots(u8),
+  Bamboo(u8),
+  Characters(u8),
+  Wind(Wind),
+  Dragon(Dragon),
+  /// Bonus tiles, numbered 1 to 4.
+  Flower(u8),
+  Season(u8),
+}
+
+impl Tile {
+  /// The 144 tiles of a set: four of every suited and honour tile, and one of every bonus tile.
+  pub fn all() -> Vec<Tile> {
+    let suited =
+      (1..=9).flat_map(|rank| [Tile::Dots(rank), Tile::Bamboo(rank), Tile::Characters(rank)]);
+    let winds = [Wind::East, Wind::South, Wind::West, Wind::North].map(Tile::Wind);
+    let dragons = [Dragon::Red, Dragon::Green, Dragon::White].map(Tile::Dragon);
+    let mut tiles: Vec<Tile> = suited
+      .chain(winds)
+      .chain(dragons)
+      .flat_map(|tile| [tile; 4])
+      .chain((1..=4).flat_map(|number| [Tile::Flower(number), Tile::Season(number)]))
+      .collect();
+    tiles.sort();
+    tiles
+  }
+
+  pub fn is_bonus(&self) -> bool {
+    matches!(self, Tile::Flower(_) | Tile::Season(_))
+  }
+}
+
+/// Give every tile of a set a plaintext of its own.
+pub fn encode_tiles<R: Rng>(rng: &mut R) -> HashMap<Card, Tile> {
+  Tile::all()
+    .into_iter()
+    .map(|tile| (Card::rand(rng), tile))
+    .collect()
+}
+
+/// The commitment `seat` publishes to the seed it rolls the dice with.
+pub fn dice_commitment(seat: usize, seed: &[u8; 32]) -> [u8; 32] {
+  let mut hasher = Blake2s::new();
+  hasher.update([seat as u8]);
+  hasher.update(seed);
+  hasher.finalize().into()
+}
+
+/// Ids of the wall in the order they are drawn, for the `dealer` and the `dice` rolled.
+pub fn wall_order(dealer: usize, dice: (u8, u8)) -> VecDeque<usize> {
+  let total = (dice.0 + dice.1) as usize;
+  let side = (dealer + total - 1) % NUM_OF_PLAYERS;
+  let num_of_stacks = NUM_OF_TILES / 2;
+  let first = side * STACKS_PER_SIDE + total;
+  (0..num_of_stacks)
+    .map(|k| (first + k) % num_of_stacks)
+    .flat_map(|stack| [2 * stack, 2 * stack + 1])
+    .collect()
+}
+
+// Two dice from the hash of every seed. Bytes from 252 up are skipped so that every face is as
+// likely as any other.
+fn roll(seeds: &[[u8; 32]]) -> (u8, u8) {
+  let mut hasher = Blake2s::new();
+  for seed in seeds {
+    hasher.update(seed);
+  }
+  let mut digest: [u8; 32] = hasher.finalize().into();
+  let mut dice = vec![];
+  loop {
+    dice.extend(
+      digest
+        .iter()
+        .filter(|byte| **byte < 252)
+        .map(|byte| byte % 6 + 1),
+    );
+    if dice.len() >= 2 {
+      return (dice[0], dice[1]);
+    }
+    digest = Blake2s::digest(&digest).into();
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+  /// Every player shuffles and remasks the tiles in turn.
+  Shuffle,
+  /// Every player commits to a seed for the dice, then opens it.
+  Dice,
+  /// Every player hands out reveal tokens for the tiles dealt to the others.
+  Deal,
+  Play,
+  /// The live wall has run out.
+  Complete,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Awaiting {
+  Shuffle { seat: usize },
+  DiceCommitment { seat: usize },
+  DiceSeed { seat: usize },
+  RevealToken { seat: usize, tile: usize },
+  Discard { seat: usize },
+}
+
+pub struct MahjongHand<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, Tile>,
+  joint_pk: PublicKey,
+  players: Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)>,
+  dealer: usize,
+  phase: Phase,
+  shuffles: usize,
+  // The tiles being shuffled, until they are handed to `tiles` to build the wall
+  deck: Vec<MaskedCard>,
+  tiles: Option<Deck<'a, Tile>>,
+  commitments: Vec<Option<[u8; 32]>>,
+  seeds: Vec<Option<[u8; 32]>>,
+  dice: Option<(u8, u8)>,
+  // Ids of the tiles left in the wall in the order they are drawn, the dead wall last
+  wall: VecDeque<usize>,
+  // Seat that discards next
+  turn: usize,
+  // Bonus tiles and concealed kongs every seat has laid open
+  exposed: Vec<Vec<usize>>,
+}
+
+impl<'a> MahjongHand<'a> {
+  /// Start a hand between `players`, listed in seat order with their proofs of key ownership.
+  /// `card_mapping` must hold the 144 tiles, as from `encode_tiles`, and `parameters` be set up
+  /// for `DIMENSIONS`.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, Tile>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    dealer: usize,
+  ) -> anyhow::Result<Self> {
+    let tiles = deck_order(card_mapping);
+    if !tiles.iter().map(|(_, tile)| **tile).eq(Tile::all()) {
+      return Err(GameErrors::WrongDeck(NUM_OF_TILES as u32).into());
+    }
+    if players.len() != NUM_OF_PLAYERS {
+      return Err(GameErrors::InvalidPlayerCount(players.len() as u32).into());
+    }
+    if dealer >= NUM_OF_PLAYERS {
+      return Err(GameErrors::InvalidSeat(dealer as u32).into());
+    }
+
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &players.to_vec())?;
+
+    let deck = tiles
+      .into_iter()
+      .map(|(card, _)| {
+        CardProtocol::mask(rng, parameters, &joint_pk, card, &Scalar::one())
+          .map(|(masked, _)| masked)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      players: players.to_vec(),
+      dealer,
+      phase: Phase::Shuffle,
+      shuffles: 0,
+      deck,
+      tiles: None,
+      commitments: vec![None; NUM_OF_PLAYERS],
+      seeds: vec![None; NUM_OF_PLAYERS],
+      dice: None,
+      wall: VecDeque::new(),
+      turn: dealer,
+      exposed: vec![vec![]; NUM_OF_PLAYERS],
+    })
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  pub fn dealer(&self) -> usize {
+    self.dealer
+  }
+
+  /// The dice that broke the wall, once every seed is open.
+  pub fn dice(&self) -> Option<(u8, u8)> {
+    self.dice
+  }
+
+  /// The tiles in play, once every player has shuffled them.
+  pub fn tiles(&self) -> Option<&Deck<'a, Tile>> {
+    self.tiles.as_ref()
+  }
+
+  /// Ids of the tiles in the hand of `seat`, in the order it got them.
+  pub fn hand(&self, seat: usize) -> Vec<usize> {
+    self
+      .tiles
+      .as_ref()
+      .map(|tiles| tiles.cards_in(Zone::Hand(seat)))
+      .unwrap_or_default()
+  }
+
+  /// Ids of the bonus tiles and kongs `seat` has laid open.
+  pub fn exposed(&self, seat: usize) -> &[usize] {
+    self
+      .exposed
+      .get(seat)
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+  }
+
+  /// The tiles discarded so far, in order.
+  pub fn discards(&self) -> Vec<Tile> {
+    self.tiles.as_ref().map_or(vec![], |tiles| {
+      tiles
+        .cards_in(Zone::Discard)
+        .into_iter()
+        .filter_map(|id| tiles.opened_card(id))
+        .collect()
+    })
+  }
+
+  /// Tiles left to draw before the dead wall.
+  pub fn live_tiles(&self) -> usize {
+    self.wall.len().saturating_sub(DEAD_WALL)
+  }
+
+  /// Seat that shuffles next. The first shuffler sits left of the dealer.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Shuffle => Some((self.dealer + 1 + self.shuffles) % NUM_OF_PLAYERS),
+      _ => None,
+    }
+  }
+
+  /// Seat that discards next. It has just drawn, or holds the dealer's fourteen tiles.
+  pub fn to_discard(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Play if self.awaited_tokens().is_empty() => Some(self.turn),
+      _ => None,
+    }
+  }
+
+  /// Everything the hand needs before it can move on. Reveal tokens are keyed by tile id in
+  /// `tiles`.
+  pub fn awaiting(&self) -> Vec<Awaiting> {
+    match self.phase {
+      Phase::Shuffle => self
+        .next_shuffler()
+        .map(|seat| Awaiting::Shuffle { seat })
+        .into_iter()
+        .collect(),
+      Phase::Dice if self.commitments.iter().any(Option::is_none) => (0..NUM_OF_PLAYERS)
+        .filter(|seat| self.commitments[*seat].is_none())
+        .map(|seat| Awaiting::DiceCommitment { seat })
+        .collect(),
+      Phase::Dice => (0..NUM_OF_PLAYERS)
+        .filter(|seat| self.seeds[*seat].is_none())
+        .map(|seat| Awaiting::DiceSeed { seat })
+        .collect(),
+      Phase::Play if self.awaited_tokens().is_empty() => self
+        .to_discard()
+        .map(|seat| Awaiting::Discard { seat })
+        .into_iter()
+        .collect(),
+      Phase::Complete => vec![],
+      _ => self.awaited_tokens(),
+    }
+  }
+
+  /// Shuffle and remask the tiles, as the next shuffler does locally before sending the result
+  /// to the others.
+  pub fn shuffle_deck<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let num_of_tiles = self.deck.len();
+    let permutation = Permutation::new(rng, num_of_tiles);
+    let masking_factors: Vec<Scalar> = sample_vector(rng, num_of_tiles);
+
+    Ok(CardProtocol::shuffle_and_remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &masking_factors,
+      &permutation,
+    )?)
+  }
+
+  /// Verify and adopt the shuffle of `seat`.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    CardProtocol::verify_shuffle(
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &shuffled_deck,
+      shuffle_proof,
+    )?;
+
+    self.deck = shuffled_deck;
+    self.shuffles += 1;
+    self.advance()
+  }
+
+  /// Record the commitment of `seat` to its seed for the dice.
+  pub fn commit_dice(&mut self, seat: usize, commitment: [u8; 32]) -> anyhow::Result<()> {
+    if !self.awaiting().contains(&Awaiting::DiceCommitment { seat }) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    self.commitments[seat] = Some(commitment);
+    Ok(())
+  }
+
+  /// Open the seed `seat` committed to. Once every seed is open, the dice break the wall and the
+  /// tiles are dealt.
+  pub fn open_dice(&mut self, seat: usize, seed: [u8; 32]) -> anyhow::Result<()> {
+    if !self.awaiting().contains(&Awaiting::DiceSeed { seat }) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    if self.commitments[seat] != Some(dice_commitment(seat, &seed)) {
+      return Err(GameErrors::IllegalAction("seed does not match its commitment").into());
+    }
+
+    self.seeds[seat] = Some(seed);
+    self.advance()
+  }
+
+  /// Verify and record the reveal token of `seat` for the tile `id` of `tiles`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    let awaited = Awaiting::RevealToken { seat, tile: id };
+    if !self.awaited_tokens().contains(&awaited) {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+    }
+
+    self
+      .tiles
+      .as_mut()
+      .unwrap()
+      .add_reveal_token(seat, id, token)?;
+    self.advance()
+  }
+
+  /// Privately open the tile `id` in the hand of `player`, once the others' tokens for it are in.
+  pub fn peek<R: Rng>(&self, rng: &mut R, player: &Player, id: usize) -> anyhow::Result<Tile> {
+    let tiles = self.tiles.as_ref().ok_or(GameErrors::TableNotStarted)?;
+    let card = tiles
+      .card(id)
+      .ok_or(GameErrors::InvalidCardIndex(id as u32))?;
+    let mut tokens = tiles.reveal_tokens(id);
+    tokens.push(player.compute_reveal_token(rng, self.parameters, card)?);
+    self.open(card, &tokens)
+  }
+
+  /// Discard the tile `id` from the hand of `seat`, opened with the owner's reveal token for it.
+  /// The next seat then draws from the live wall, unless it has run out.
+  pub fn discard(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+    self.check_turn(seat)?;
+    self.check_held(seat, &[id])?;
+    self.open_with(seat, id, &token)?;
+
+    let tiles = self.tiles.as_mut().unwrap();
+    tiles.move_card(id, Zone::Discard, Visibility::Public)?;
+    tiles.add_reveal_token(seat, id, token)?;
+
+    if self.live_tiles() == 0 {
+      self.phase = Phase::Complete;
+      return Ok(());
+    }
+    self.turn = (seat + 1) % NUM_OF_PLAYERS;
+    let drawn = self.wall.pop_front().unwrap();
+    self.draw(self.turn, drawn)
+  }
+
+  /// Lay open the bonus tile `id` from the hand of `seat` with the owner's reveal token for it,
+  /// and draw a replacement.
+  pub fn declare_bonus(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    self.check_turn(seat)?;
+    self.check_held(seat, &[id])?;
+    if !self.open_with(seat, id, &token)?.is_bonus() {
+      return Err(GameErrors::IllegalAction("not a bonus tile").into());
+    }
+
+    self.expose(seat, id, token)?;
+    self.replace(seat)
+  }
+
+  /// Lay open the four identical tiles `ids` from the hand of `seat`, with the owner's reveal
+  /// tokens for them, and draw a replacement.
+  pub fn declare_kong(
+    &mut self,
+    seat: usize,
+    ids: &[usize],
+    tokens: Vec<RevealShare>,
+  ) -> anyhow::Result<()> {
+    self.check_turn(seat)?;
+    self.check_held(seat, ids)?;
+    if ids.len() != 4 || tokens.len() != ids.len() {
+      return Err(GameErrors::IllegalAction("a kong is four tiles").into());
+    }
+
+    let opened = ids
+      .iter()
+      .zip(&tokens)
+      .map(|(id, token)| self.open_with(seat, *id, token))
+      .collect::<anyhow::Result<Vec<Tile>>>()?;
+    if opened[0].is_bonus() || opened.iter().any(|tile| *tile != opened[0]) {
+      return Err(GameErrors::IllegalAction("a kong is four identical tiles").into());
+    }
+
+    for (id, token) in ids.iter().zip(tokens) {
+      self.expose(seat, *id, token)?;
+    }
+    self.replace(seat)
+  }
+
+  fn advance(&mut self) -> anyhow::Result<()> {
+    match self.phase {
+      Phase::Shuffle if self.shuffles == NUM_OF_PLAYERS => {
+        let deck = std::mem::take(&mut self.deck);
+        self.tiles = Some(Deck::new(
+          self.parameters,
+          self.card_mapping,
+          &self.players,
+          deck,
+        )?);
+        self.phase = Phase::Dice;
+      }
+      Phase::Dice if self.seeds.iter().all(Option::is_some) => {
+        let seeds: Vec<[u8; 32]> = self.seeds.iter().flatten().copied().collect();
+        let dice = roll(&seeds);
+        self.dice = Some(dice);
+        self.wall = wall_order(self.dealer, dice);
+        self.deal()?;
+        self.phase = Phase::Deal;
+      }
+      Phase::Deal if self.awaited_tokens().is_empty() => self.phase = Phase::Play,
+      _ => {}
+    }
+    Ok(())
+  }
+
+  // Four tiles at a time to every seat three times over, starting with the dealer, then one
+  // more each, and a fourteenth for the dealer
+  fn deal(&mut self) -> anyhow::Result<()> {
+    let dealer = self.dealer;
+    let seats = (0..NUM_OF_PLAYERS).map(move |i| (dealer + i) % NUM_OF_PLAYERS);
+    let mut order: Vec<usize> = vec![];
+    for _ in 0..3 {
+      order.extend(seats.clone().flat_map(|seat| [seat; 4]));
+    }
+    order.extend(seats);
+    order.push(dealer);
+
+    for seat in order {
+      let id = self.wall.pop_front().unwrap();
+      self.draw(seat, id)?;
+    }
+    Ok(())
+  }
+
+  fn draw(&mut self, seat: usize, id: usize) -> anyhow::Result<()> {
+    let tiles = self.tiles.as_mut().unwrap();
+    tiles.move_card(id, Zone::Hand(seat), Visibility::KnownTo(vec![seat]))?;
+    Ok(())
+  }
+
+  fn expose(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+    let tiles = self.tiles.as_mut().unwrap();
+    tiles.move_card(id, Zone::Board, Visibility::Public)?;
+    tiles.add_reveal_token(seat, id, token)?;
+    self.exposed[seat].push(id);
+    Ok(())
+  }
+
+  // Draw from the far end of the dead wall
+  fn replace(&mut self, seat: usize) -> anyhow::Result<()> {
+    let id = self
+      .wall
+      .pop_back()
+      .ok_or(GameErrors::IllegalAction("no tile left to replace"))?;
+    self.draw(seat, id)
+  }
+
+  fn open(&self, card: &MaskedCard, tokens: &[RevealShare]) -> anyhow::Result<Tile> {
+    let plaintext = CardProtocol::unmask(self.parameters, &tokens.to_vec(), card)?;
+    let tile = self.card_mapping.get(&plaintext);
+    Ok(*tile.ok_or(GameErrors::InvalidCard)?)
+  }
+
+  // Open the tile `id` held by `seat` with its owner's `token`, without changing the hand
+  fn open_with(&self, seat: usize, id: usize, token: &RevealShare) -> anyhow::Result<Tile> {
+    let tiles = self.tiles.as_ref().unwrap();
+    let card = tiles.card(id).unwrap();
+    if token.2 != self.players[seat].0 {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+    }
+    CardProtocol::verify_reveal(self.parameters, &token.2, &token.0, card, &token.1)?;
+
+    let mut tokens = tiles.reveal_tokens(id);
+    tokens.extend(std::iter::once(token).cloned());
+    self.open(card, &tokens)
+  }
+
+  fn check_turn(&self, seat: usize) -> Result<(), GameErrors> {
+    if self.to_discard() == Some(seat) {
+      Ok(())
+    } else {
+      Err(GameErrors::NotYourTurn(seat as u32))
+    }
+  }
+
+  fn check_held(&self, seat: usize, ids: &[usize]) -> Result<(), GameErrors> {
+    let tiles = self.tiles.as_ref().unwrap();
+    for (i, id) in ids.iter().enumerate() {
+      if tiles.zone(*id) != Some(Zone::Hand(seat)) || ids[..i].contains(id) {
+        return Err(GameErrors::CardNotFound);
+      }
+    }
+    Ok(())
+  }
+
+  fn awaited_tokens(&self) -> Vec<Awaiting> {
+    self.tiles.as_ref().map_or(vec![], |tiles| {
+      tiles
+        .awaited_tokens()
+        .into_iter()
+        .map(|(seat, tile)| Awaiting::RevealToken { seat, tile })
+        .collect()
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{key_info, players, run_protocol, Protocol, Step};
+
+  use rand::thread_rng;
+
+  impl Protocol for MahjongHand<'_> {
+    fn step(&self) -> Option<Step> {
+      match self.awaiting().first().copied()? {
+        Awaiting::Shuffle { seat } => Some(Step::Shuffle { seat }),
+        Awaiting::RevealToken { seat, tile } => Some(Step::RevealToken { seat, card: tile }),
+        _ => None,
+      }
+    }
+
+    fn parameters(&self) -> &CardParameters {
+      self.parameters
+    }
+
+    fn card(&self, id: usize) -> MaskedCard {
+      *self.tiles().unwrap().card(id).unwrap()
+    }
+
+    fn shuffle_as<R: Rng>(&mut self, rng: &mut R, seat: usize) -> anyhow::Result<()> {
+      let (deck, proof) = self.shuffle_deck(rng)?;
+      self.shuffle(seat, deck, &proof)
+    }
+
+    fn add_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+      self.add_reveal_token(seat, id, token)
+    }
+  }
+
+  /// Run the hand up to the next discard: shuffles, dice and reveal tokens.
+  fn run_to_discard<R: Rng>(rng: &mut R, hand: &mut MahjongHand, players: &[Player]) {
+    let mut seeds: Vec<[u8; 32]> = (0..NUM_OF_PLAYERS).map(|_| rng.gen()).collect();
+    loop {
+      run_protocol(rng, hand, players);
+      match hand.awaiting().first().copied() {
+        Some(Awaiting::DiceCommitment { seat }) => {
+          let commitment = dice_commitment(seat, &seeds[seat]);
+          hand.commit_dice(seat, commitment).unwrap();
+        }
+        Some(Awaiting::DiceSeed { seat }) => {
+          // A seed other than the one committed to is refused
+          let mut other = seeds[seat];
+          other[0] ^= 1;
+          assert!(hand.open_dice(seat, other).is_err());
+          hand.open_dice(seat, seeds[seat]).unwrap();
+          seeds[seat] = [0; 32];
+        }
+        _ => return,
+      }
+    }
+  }
+
+  fn own_token<R: Rng>(rng: &mut R, hand: &MahjongHand, player: &Player, id: usize) -> RevealShare {
+    let card = hand.tiles().unwrap().card(id).unwrap();
+    player
+      .compute_reveal_token(rng, hand.parameters, card)
+      .unwrap()
+  }
+
+  #[test]
+  fn breaks_the_wall_where_the_dice_say() {
+    assert_eq!(Tile::all().len(), NUM_OF_TILES);
+    assert_eq!(DIMENSIONS.0 * DIMENSIONS.1, NUM_OF_TILES);
+
+    // Seven counts round to the seat across from the dealer, then seven stacks into its side
+    let wall = wall_order(1, (3, 4));
+    assert_eq!(wall.len(), NUM_OF_TILES);
+    assert_eq!(wall.front(), Some(&(2 * (3 * STACKS_PER_SIDE + 7))));
+    let mut ids: Vec<usize> = wall.into_iter().collect();
+    ids.sort_unstable();
+    assert!(ids.into_iter().eq(0..NUM_OF_TILES));
+
+    let seeds = [[1; 32], [2; 32], [3; 32], [4; 32]];
+    let (first, second) = roll(&seeds);
+    assert!((1..=6).contains(&first) && (1..=6).contains(&second));
+    assert_eq!(roll(&seeds), (first, second));
+    assert_ne!(dice_commitment(0, &seeds[0]), dice_commitment(1, &seeds[0]));
+  }
+
+  #[test]
+  fn deals_and_plays_to_an_exhaustive_draw() {
+    let rng = &mut thread_rng();
+    let parameters = CardProtocol::setup(rng, DIMENSIONS.0, DIMENSIONS.1).unwrap();
+    let card_mapping = encode_tiles(rng);
+    let players = players(rng, &parameters, NUM_OF_PLAYERS);
+    let mut hand =
+      MahjongHand::new(rng, &parameters, &card_mapping, &key_info(&players), 2).unwrap();
+
+    assert!(hand.commit_dice(0, [0; 32]).is_err());
+    run_to_discard(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Play);
+    assert!(hand.dice().is_some());
+    let sizes: Vec<usize> = (0..NUM_OF_PLAYERS)
+      .map(|seat| hand.hand(seat).len())
+      .collect();
+    assert_eq!(sizes, vec![13, 13, 14, 13]);
+    assert_eq!(hand.live_tiles(), NUM_OF_TILES - 53 - DEAD_WALL);
+
+    let mut refused_plain_bonus = false;
+    while hand.phase() == Phase::Play {
+      let seat = hand.to_discard().unwrap();
+      let ids = hand.hand(seat);
+      let held: Vec<Tile> = ids
+        .iter()
+        .map(|id| hand.peek(rng, &players[seat], *id).unwrap())
+        .collect();
+      match held.iter().position(Tile::is_bonus) {
+        Some(i) => {
+          let replaced = hand.live_tiles();
+          let bonus_token = own_token(rng, &hand, &players[seat], ids[i]);
+          hand.declare_bonus(seat, ids[i], bonus_token).unwrap();
+          assert_eq!(hand.live_tiles(), replaced.saturating_sub(1));
+          assert_eq!(hand.tiles().unwrap().opened_card(ids[i]), Some(held[i]));
+        }
+        None => {
+          if !refused_plain_bonus {
+            let plain_token = own_token(rng, &hand, &players[seat], ids[0]);
+            assert!(hand.declare_bonus(seat, ids[0], plain_token).is_err());
+            let other_token = own_token(rng, &hand, &players[seat], ids[0]);
+            assert!(hand
+              .discard((seat + 1) % NUM_OF_PLAYERS, ids[0], other_token)
+              .is_err());
+            refused_plain_bonus = true;
+          }
+          let discard_token = own_token(rng, &hand, &players[seat], ids[0]);
+          hand.discard(seat, ids[0], discard_token).unwrap();
+          assert_eq!(hand.discards().last(), Some(&held[0]));
+        }
+      }
+      run_to_discard(rng, &mut hand, &players);
+    }
+
+    assert_eq!(hand.phase(), Phase::Complete);
+    assert_eq!(hand.live_tiles(), 0);
+    let exposed: usize = (0..NUM_OF_PLAYERS)
+      .map(|seat| hand.exposed(seat).len())
+      .sum();
+    let held: usize = (0..NUM_OF_PLAYERS).map(|seat| hand.hand(seat).len()).sum();
+    assert_eq!(held, 4 * 13);
+    assert_eq!(
+      held + exposed + hand.discards().len(),
+      NUM_OF_TILES - hand.wall.len()
+    );
+  }
+}
@@ -0,0 +1,329 @@
+//! Main and side pots, and their distribution at showdown.
+//!
+//! Pots are built from what every seat put in over the hand (`Betting::contributions`). A seat
+//! that went all-in can only win the chips its own contribution was matched with, so each
+//! distinct all-in amount starts a new side pot with fewer eligible seats.
+
+use crate::evaluator::{winners, HandRank, LowRank};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Pot {
+  pub amount: u64,
+  /// Seats that have not folded and matched every chip of this pot, in seat order.
+  pub eligible: Vec<usize>,
+}
+
+/// Split the chips into the main pot followed by the side pots. Folded seats add to the pots
+/// their contributions reach but are never eligible.
+pub fn build_pots(contributions: &[u64], folded: &[bool]) -> Vec<Pot> {
+  let mut levels: Vec<u64> = contributions
+    .iter()
+    .zip(folded)
+    .filter(|(_, folded)| !**folded)
+    .map(|(contribution, _)| *contribution)
+    .collect();
+  levels.sort_unstable();
+  levels.dedup();
+
+  let mut pots: Vec<Pot> = Vec::new();
+  let mut previous = 0;
+  for level in levels {
+    let amount = contributions
+      .iter()
+      .map(|contribution| (*contribution).min(level).saturating_sub(previous))
+      .sum();
+    let eligible = (0..contributions.len())
+      .filter(|seat| !folded[*seat] && contributions[*seat] >= level)
+      .collect::<Vec<_>>();
+    previous = level;
+
+    match pots.last_mut() {
+      Some(pot) if pot.eligible == eligible => pot.amount += amount,
+      _ if amount > 0 => pots.push(Pot { amount, eligible }),
+      _ => {}
+    }
+  }
+
+  // Chips folded above the largest live contribution go to the last pot
+  let rest: u64 = contributions
+    .iter()
+    .map(|contribution| contribution.saturating_sub(previous))
+    .sum();
+  if let Some(pot) = pots.last_mut() {
+    pot.amount += rest;
+  }
+
+  pots
+}
+
+/// Whether `seat` may muck at showdown: every contested pot it could win must already have a
+/// hand `shown` by another seat, which the mucked hand concedes to. This way each pot still goes
+/// to a hand opened in public.
+pub fn may_muck(pots: &[Pot], seat: usize, shown: &[bool]) -> bool {
+  pots
+    .iter()
+    .filter(|pot| pot.eligible.len() > 1 && pot.eligible.contains(&seat))
+    .all(|pot| {
+      pot
+        .eligible
+        .iter()
+        .any(|other| *other != seat && shown[*other])
+    })
+}
+
+/// Give each pot to the best eligible hand and return what every seat wins.
+///
+/// `hands` holds the rank of every hand shown down, `None` for seats that folded or mucked. A
+/// pot with a single eligible seat goes to it without a showdown. Tied hands split the pot
+/// evenly and the odd chips go one by one to the winners closest to the left of the button.
+pub fn distribute(pots: &[Pot], hands: &[Option<HandRank>], button: usize) -> Vec<u64> {
+  let mut payouts = vec![0; hands.len()];
+  for pot in pots {
+    award(pot.amount, &pot.eligible, hands, button, &mut payouts);
+  }
+
+  payouts
+}
+
+/// Like `distribute` for high-low split games: each pot is halved between the best high hand
+/// and the best qualifying low, the odd chip going to the high half. A pot where no eligible
+/// seat shows a qualifying low goes entirely to the high hand.
+pub fn distribute_hi_lo(
+  pots: &[Pot],
+  highs: &[Option<HandRank>],
+  lows: &[Option<LowRank>],
+  button: usize,
+) -> Vec<u64> {
+  let mut payouts = vec![0; highs.len()];
+  for pot in pots {
+    let has_low = pot.eligible.len() > 1 && pot.eligible.iter().any(|seat| lows[*seat].is_some());
+    if has_low {
+      let low_half = pot.amount / 2;
+      award(
+        pot.amount - low_half,
+        &pot.eligible,
+        highs,
+        button,
+        &mut payouts,
+      );
+      award(low_half, &pot.eligible, lows, button, &mut payouts);
+    } else {
+      award(pot.amount, &pot.eligible, highs, button, &mut payouts);
+    }
+  }
+
+  payouts
+}
+
+fn award<H: Ord + Copy>(
+  amount: u64,
+  eligible: &[usize],
+  hands: &[Option<H>],
+  button: usize,
+  payouts: &mut [u64],
+) {
+  let num_of_seats = hands.len();
+  let shown: Vec<usize> = eligible
+    .iter()
+    .copied()
+    .filter(|seat| hands[*seat].is_some())
+    .collect();
+  let contenders = if eligible.len() == 1 || shown.is_empty() {
+    eligible.to_vec()
+  } else {
+    shown
+  };
+  let ranks: Vec<Option<H>> = contenders.iter().map(|seat| hands[*seat]).collect();
+
+  let mut pot_winners: Vec<usize> = winners(&ranks).into_iter().map(|i| contenders[i]).collect();
+  pot_winners.sort_by_key(|seat| (seat + num_of_seats - button - 1) % num_of_seats);
+
+  let share = amount / pot_winners.len() as u64;
+  let odd_chips = (amount % pot_winners.len() as u64) as usize;
+  for (i, seat) in pot_winners.iter().enumerate() {
+    payouts[*seat] += share + if i < odd_chips { 1 } else { 0 };
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::evaluator::evaluate;
+  use crate::{ClassicPlayingCard, Suite, Value};
+
+  fn rank(values: [Value; 5], suites: [Suite; 5]) -> Option<HandRank> {
+    let cards: Vec<ClassicPlayingCard> = values
+      .iter()
+      .zip(suites.iter())
+      .map(|(value, suite)| ClassicPlayingCard::new(*value, *suite))
+      .collect();
+    Some(evaluate(&cards).unwrap())
+  }
+
+  fn high_card(top: Value) -> Option<HandRank> {
+    rank(
+      [Value::Two, Value::Four, Value::Six, Value::Eight, top],
+      [
+        Suite::Club,
+        Suite::Club,
+        Suite::Club,
+        Suite::Club,
+        Suite::Heart,
+      ],
+    )
+  }
+
+  #[test]
+  fn builds_side_pots_from_all_ins() {
+    let contributions = [100, 30, 100, 60, 20];
+    let folded = [false, false, false, false, true];
+    assert_eq!(
+      build_pots(&contributions, &folded),
+      vec![
+        // 30 from each of the four live seats plus the folded 20
+        Pot {
+          amount: 140,
+          eligible: vec![0, 1, 2, 3]
+        },
+        Pot {
+          amount: 90,
+          eligible: vec![0, 2, 3]
+        },
+        Pot {
+          amount: 80,
+          eligible: vec![0, 2]
+        },
+      ]
+    );
+
+    // An uncalled bet forms a pot only its owner can win
+    assert_eq!(
+      build_pots(&[50, 20, 20], &[false, true, false]),
+      vec![
+        Pot {
+          amount: 60,
+          eligible: vec![0, 2]
+        },
+        Pot {
+          amount: 30,
+          eligible: vec![0]
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn gives_each_pot_to_the_best_eligible_hand() {
+    let contributions = [100, 30, 100, 60];
+    let pots = build_pots(&contributions, &[false; 4]);
+    // The short stack has the best hand, the second best was covered by the big stacks
+    let hands = [
+      high_card(Value::Ten),
+      high_card(Value::Ace),
+      high_card(Value::Nine),
+      high_card(Value::King),
+    ];
+    assert_eq!(distribute(&pots, &hands, 0), vec![80, 120, 0, 90]);
+
+    // A mucked hand gives up its claim
+    let hands = [high_card(Value::Ten), high_card(Value::Ace), None, None];
+    assert_eq!(distribute(&pots, &hands, 0), vec![170, 120, 0, 0]);
+  }
+
+  #[test]
+  fn splits_ties_and_gives_odd_chips_left_of_the_button() {
+    let pots = build_pots(&[11, 11, 11], &[false, false, false]);
+    let hands = [
+      high_card(Value::Ace),
+      high_card(Value::King),
+      rank(
+        [
+          Value::Two,
+          Value::Four,
+          Value::Six,
+          Value::Eight,
+          Value::Ace,
+        ],
+        [
+          Suite::Diamond,
+          Suite::Club,
+          Suite::Club,
+          Suite::Club,
+          Suite::Spade,
+        ],
+      ),
+    ];
+    // Seats 0 and 2 split 33; with the button on seat 1, seat 2 is first to its left
+    assert_eq!(distribute(&pots, &hands, 1), vec![16, 0, 17]);
+    // With the button on seat 2, seat 0 is first
+    assert_eq!(distribute(&pots, &hands, 2), vec![17, 0, 16]);
+  }
+
+  #[test]
+  fn splits_high_and_low() {
+    use crate::evaluator::evaluate_low;
+
+    let low = |values: [Value; 5]| {
+      let cards: Vec<ClassicPlayingCard> = values
+        .iter()
+        .map(|value| ClassicPlayingCard::new(*value, Suite::Club))
+        .collect();
+      evaluate_low(&cards).unwrap()
+    };
+    let wheel = low([
+      Value::Ace,
+      Value::Two,
+      Value::Three,
+      Value::Four,
+      Value::Five,
+    ]);
+    let eight = low([
+      Value::Ace,
+      Value::Two,
+      Value::Three,
+      Value::Four,
+      Value::Eight,
+    ]);
+
+    let pots = build_pots(&[11, 11, 11], &[false; 3]);
+    let highs = [
+      high_card(Value::Ace),
+      high_card(Value::King),
+      high_card(Value::Queen),
+    ];
+    // The high hand takes the odd chip
+    assert_eq!(
+      distribute_hi_lo(&pots, &highs, &[None, eight, wheel], 0),
+      vec![17, 0, 16]
+    );
+    // Two players tie for low and quarter the pot
+    assert_eq!(
+      distribute_hi_lo(&pots, &highs, &[None, wheel, wheel], 0),
+      vec![17, 8, 8]
+    );
+    // Without a qualifying low the high hand scoops
+    assert_eq!(
+      distribute_hi_lo(&pots, &highs, &[None, None, None], 0),
+      vec![33, 0, 0]
+    );
+  }
+
+  #[test]
+  fn mucks_only_against_a_shown_hand() {
+    let pots = build_pots(&[100, 30, 100], &[false; 3]);
+    // Seat 1 only contests the main pot, seats 0 and 2 also the side pot
+    assert!(!may_muck(&pots, 0, &[false, false, false]));
+    assert!(may_muck(&pots, 0, &[false, false, true]));
+    assert!(!may_muck(&pots, 0, &[false, true, false]));
+    assert!(may_muck(&pots, 1, &[false, false, true]));
+    // The uncalled part of a bet is nobody else's to win
+    let pots = build_pots(&[50, 20], &[false, false]);
+    assert!(may_muck(&pots, 0, &[false, true]));
+  }
+
+  #[test]
+  fn uncontested_pot_needs_no_showdown() {
+    let pots = build_pots(&[2, 1, 10], &[true, true, false]);
+    assert_eq!(distribute(&pots, &[None, None, None], 0), vec![0, 0, 13]);
+  }
+}
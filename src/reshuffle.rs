@@ -0,0 +1,220 @@
+//! Shuffling any number of masked cards, such as a discard pile going back into the stock.
+//!
+//! The shuffle argument works on an `m × n` deck, so a subset of `size` cards is padded to the
+//! smallest such deck that holds it, as picked by `definitions::dimensions`. The padding is the
+//! identity ciphertext, which no card decrypts to. The shuffler leaves it in place and does not
+//! remask it. The verifier appends the same padding to both decks, so a proof that moved a card
+//! of the subset into the padding, or padding into the subset, does not verify.
+//!
+//! The parameters for each padded size come from a `ParameterCache`. Their commitment key is
+//! derived from a hash of the parameters of the game and of the size, so every player derives
+//! the same key without a setup of its own, and nobody knows a trapdoor to it.
+
+use crate::definitions::dimensions;
+use crate::{
+  CardParameters, CardProtocol, Curve, GameErrors, MaskedCard, PublicKey, Scalar, ShuffleProof,
+};
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{rand::Rng, Zero};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use blake2::{Blake2s, Digest};
+use proof_essentials::homomorphic_encryption::el_gamal;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::HashMap;
+
+/// Parameters for shuffles of every size, derived from the parameters of the game the first
+/// time a size is needed.
+pub struct ParameterCache<'a> {
+  parameters: &'a CardParameters,
+  sized: HashMap<usize, CardParameters>,
+}
+
+impl<'a> ParameterCache<'a> {
+  pub fn new(parameters: &'a CardParameters) -> Self {
+    Self {
+      parameters,
+      sized: HashMap::new(),
+    }
+  }
+
+  /// Parameters for a shuffle of `size` cards once padded.
+  pub fn get(&mut self, size: usize) -> anyhow::Result<&CardParameters> {
+    let (m, n) = dimensions(size);
+    if !self.sized.contains_key(&(m * n)) {
+      let mut hasher = Blake2s::new();
+      let mut bytes = vec![];
+      self.parameters.serialize(&mut bytes)?;
+      hasher.update(&bytes);
+      hasher.update((m as u64).to_le_bytes());
+      hasher.update((n as u64).to_le_bytes());
+      let rng = &mut ChaCha20Rng::from_seed(hasher.finalize().into());
+      self
+        .sized
+        .insert(m * n, self.parameters.with_size(rng, m, n));
+    }
+
+    Ok(&self.sized[&(m * n)])
+  }
+}
+
+/// Number of cards a subset of `size` cards is padded to.
+pub fn padded_size(size: usize) -> usize {
+  let (m, n) = dimensions(size);
+  m * n
+}
+
+/// Shuffle and remask `cards`, and prove it. The proof verifies with `verify_reshuffle`.
+pub fn reshuffle<R: Rng>(
+  rng: &mut R,
+  cache: &mut ParameterCache,
+  joint_pk: &PublicKey,
+  cards: &[MaskedCard],
+) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+  if cards.is_empty() {
+    return Err(GameErrors::IllegalAction("reshuffle no cards").into());
+  }
+  let size = cards.len();
+  let parameters = cache.get(size)?;
+  let padded = pad(cards);
+
+  // The padding keeps its place and is not remasked
+  let mut mapping = Permutation::new(rng, size).mapping;
+  mapping.extend(size..padded.len());
+  let permutation = Permutation::from(&mapping);
+  let mut masking_factors: Vec<Scalar> = sample_vector(rng, size);
+  masking_factors.resize(padded.len(), Scalar::zero());
+
+  let (mut shuffled, proof) = CardProtocol::shuffle_and_remask(
+    rng,
+    parameters,
+    joint_pk,
+    &padded,
+    &masking_factors,
+    &permutation,
+  )?;
+  shuffled.truncate(size);
+
+  Ok((shuffled, proof))
+}
+
+/// Verify that `reshuffled` is `cards` shuffled and remasked.
+pub fn verify_reshuffle(
+  cache: &mut ParameterCache,
+  joint_pk: &PublicKey,
+  cards: &[MaskedCard],
+  reshuffled: &[MaskedCard],
+  proof: &ShuffleProof,
+) -> anyhow::Result<()> {
+  if cards.is_empty() || reshuffled.len() != cards.len() {
+    return Err(GameErrors::IllegalAction("reshuffle to another number of cards").into());
+  }
+  let parameters = cache.get(cards.len())?;
+
+  Ok(CardProtocol::verify_shuffle(
+    parameters,
+    joint_pk,
+    &pad(cards),
+    &pad(reshuffled),
+    proof,
+  )?)
+}
+
+// `cards` followed by as many identity ciphertexts as the padded deck needs
+fn pad(cards: &[MaskedCard]) -> Vec<MaskedCard> {
+  let identity = <Curve as ProjectiveCurve>::Affine::zero();
+  let mut padded = cards.to_vec();
+  padded.resize(
+    padded_size(cards.len()),
+    el_gamal::Ciphertext(identity, identity),
+  );
+  padded
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{key_info, players};
+  use crate::{Card, RevealShare};
+
+  use ark_ff::UniformRand;
+  use ark_serialize::CanonicalDeserialize;
+  use ark_std::One;
+  use rand::thread_rng;
+
+  #[test]
+  fn reshuffles_a_subset_of_any_size() {
+    let rng = &mut thread_rng();
+    let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+    let players = players(rng, &parameters, 2);
+    let joint_pk = CardProtocol::compute_aggregate_key(&parameters, &key_info(&players)).unwrap();
+
+    let mut bytes = vec![];
+    parameters.serialize(&mut bytes).unwrap();
+    let mut cache = ParameterCache::new(&parameters);
+    // Every player derives the same parameters on its own
+    let other_parameters = CardParameters::deserialize(&bytes[..]).unwrap();
+    let mut other_cache = ParameterCache::new(&other_parameters);
+    let (mut sized, mut other_sized) = (vec![], vec![]);
+    cache.get(7).unwrap().serialize(&mut sized).unwrap();
+    other_cache
+      .get(7)
+      .unwrap()
+      .serialize(&mut other_sized)
+      .unwrap();
+    assert_eq!(sized, other_sized);
+    assert_eq!(padded_size(7), 8);
+    assert_eq!(padded_size(13), 14);
+
+    let plaintexts: Vec<Card> = (0..13).map(|_| Card::rand(rng)).collect();
+    let discards: Vec<MaskedCard> = plaintexts
+      .iter()
+      .map(|card| {
+        CardProtocol::mask(rng, &parameters, &joint_pk, card, &Scalar::one())
+          .unwrap()
+          .0
+      })
+      .collect();
+
+    for size in [7, 13] {
+      let mut pile = discards[..size].to_vec();
+      for _ in &players {
+        let (reshuffled, proof) = reshuffle(rng, &mut cache, &joint_pk, &pile).unwrap();
+        assert_eq!(reshuffled.len(), size);
+        assert!(verify_reshuffle(&mut other_cache, &joint_pk, &pile, &reshuffled, &proof).is_ok());
+
+        // Not for another pile
+        let mut other_pile = pile.clone();
+        other_pile.swap(0, 1);
+        assert!(verify_reshuffle(&mut cache, &joint_pk, &other_pile, &reshuffled, &proof).is_err());
+        pile = reshuffled;
+      }
+
+      let mut opened: Vec<Card> = pile
+        .iter()
+        .map(|card| {
+          let tokens: Vec<RevealShare> = players
+            .iter()
+            .map(|player| player.compute_reveal_token(rng, &parameters, card).unwrap())
+            .collect();
+          CardProtocol::unmask(&parameters, &tokens, card).unwrap()
+        })
+        .collect();
+      let mut expected = plaintexts[..size].to_vec();
+      let key = |card: &Card| {
+        let mut bytes = vec![];
+        card.serialize(&mut bytes).unwrap();
+        bytes
+      };
+      opened.sort_by_key(key);
+      expected.sort_by_key(key);
+      assert_eq!(opened, expected);
+    }
+
+    assert!(reshuffle(rng, &mut cache, &joint_pk, &[]).is_err());
+  }
+}
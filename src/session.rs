@@ -0,0 +1,416 @@
+//! A session of Texas Hold'em hands played one after another at the same table.
+//!
+//! The session keeps the players still seated with their chips and keys, starts a `HoldemHand`
+//! for each deal and carries the results over to the next one: the button moves one seat to the
+//! left, which also moves the blinds and the first shuffler, and players who lost all their chips
+//! leave the table. The session is over once a single player is left.
+//!
+//! Players either keep the keys they joined with for the whole session, or generate a new key
+//! pair before every hand so that a key leaked after a hand says nothing about the next ones.
+
+use crate::texas_holdem::{HoldemConfig, HoldemHand, Phase};
+use crate::{
+  Card, CardParameters, CardProtocol, ClassicPlayingCard, GameErrors, ProofKeyOwnership, PublicKey,
+};
+
+use ark_std::rand::Rng;
+use barnett_smart_protocol::BarnettSmartProtocol;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Keying {
+  /// Every hand is played under the keys the players joined with.
+  Reuse,
+  /// Every player sends a new key with its proof of ownership before each hand.
+  PerHand,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SessionConfig {
+  pub holdem: HoldemConfig,
+  pub keying: Keying,
+}
+
+struct Seat {
+  key: (PublicKey, ProofKeyOwnership, Vec<u8>),
+  // Whether the key was sent since the last hand started
+  fresh_key: bool,
+  // Every key the seat has sent, the current one included
+  used_keys: Vec<PublicKey>,
+  stack: u64,
+}
+
+pub struct Session<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+  config: SessionConfig,
+  seats: Vec<Seat>,
+  button: usize,
+  hands_played: usize,
+  hand: Option<HoldemHand<'a>>,
+}
+
+impl<'a> Session<'a> {
+  /// Seat `players`, in seat order with their proofs of key ownership, with the chips they buy
+  /// in for. The button starts on the first seat.
+  pub fn new(
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    stacks: Vec<u64>,
+    config: SessionConfig,
+  ) -> anyhow::Result<Self> {
+    if stacks.len() != players.len() {
+      return Err(GameErrors::WrongSeatCount(players.len() as u32).into());
+    }
+    for (pk, proof, name) in players {
+      CardProtocol::verify_key_ownership(parameters, pk, name, proof)?;
+    }
+
+    let seats = players
+      .iter()
+      .cloned()
+      .zip(stacks)
+      .map(|(key, stack)| Seat {
+        used_keys: vec![key.0],
+        key,
+        fresh_key: true,
+        stack,
+      })
+      .collect();
+
+    Ok(Self {
+      parameters,
+      card_mapping,
+      config,
+      seats,
+      button: 0,
+      hands_played: 0,
+      hand: None,
+    })
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.seats.len()
+  }
+
+  /// Names of the players still seated, in seat order. Seat numbers change when a player
+  /// busts out.
+  pub fn names(&self) -> Vec<&[u8]> {
+    self
+      .seats
+      .iter()
+      .map(|seat| seat.key.2.as_slice())
+      .collect()
+  }
+
+  pub fn public_keys(&self) -> Vec<PublicKey> {
+    self.seats.iter().map(|seat| seat.key.0).collect()
+  }
+
+  pub fn stacks(&self) -> Vec<u64> {
+    self.seats.iter().map(|seat| seat.stack).collect()
+  }
+
+  pub fn button(&self) -> usize {
+    self.button
+  }
+
+  pub fn hands_played(&self) -> usize {
+    self.hands_played
+  }
+
+  pub fn is_over(&self) -> bool {
+    self.seats.len() < 2
+  }
+
+  /// The hand being played, if any.
+  pub fn hand(&self) -> Option<&HoldemHand<'a>> {
+    self.hand.as_ref()
+  }
+
+  pub fn hand_mut(&mut self) -> Option<&mut HoldemHand<'a>> {
+    self.hand.as_mut()
+  }
+
+  /// Replace the key of `seat` before the next hand. A key the seat has already sent, its
+  /// current one included, is refused.
+  pub fn rekey(
+    &mut self,
+    seat: usize,
+    public_key: PublicKey,
+    proof_key: ProofKeyOwnership,
+  ) -> anyhow::Result<()> {
+    if self.hand.is_some() {
+      return Err(GameErrors::HandInProgress.into());
+    }
+    let name = match self.seats.get(seat) {
+      Some(seat) => seat.key.2.clone(),
+      None => return Err(GameErrors::InvalidSeat(seat as u32).into()),
+    };
+    if self.seats[seat].used_keys.contains(&public_key) {
+      return Err(GameErrors::StaleKey(seat as u32).into());
+    }
+    CardProtocol::verify_key_ownership(self.parameters, &public_key, &name, &proof_key)?;
+
+    self.seats[seat].key = (public_key, proof_key, name);
+    self.seats[seat].fresh_key = true;
+    self.seats[seat].used_keys.push(public_key);
+    Ok(())
+  }
+
+  /// Start the next hand with the current stacks and button. The deck is masked and shuffled
+  /// again from scratch, starting with the player left of the button.
+  pub fn start_hand<R: Rng>(&mut self, rng: &mut R) -> anyhow::Result<&mut HoldemHand<'a>> {
+    if self.hand.is_some() {
+      return Err(GameErrors::HandInProgress.into());
+    }
+    if self.config.keying == Keying::PerHand {
+      if let Some(seat) = self.seats.iter().position(|seat| !seat.fresh_key) {
+        return Err(GameErrors::StaleKey(seat as u32).into());
+      }
+    }
+
+    let players: Vec<_> = self.seats.iter().map(|seat| seat.key.clone()).collect();
+    let hand = HoldemHand::new(
+      rng,
+      self.parameters,
+      self.card_mapping,
+      &players,
+      self.stacks(),
+      self.button,
+      self.config.holdem,
+    )?;
+    for seat in self.seats.iter_mut() {
+      seat.fresh_key = false;
+    }
+
+    Ok(self.hand.insert(hand))
+  }
+
+  /// Close the completed hand: carry the stacks over, remove the players left without chips
+  /// and move the button. Returns the payouts of the hand by seat.
+  pub fn finish_hand(&mut self) -> anyhow::Result<Vec<u64>> {
+    let hand = match self.hand.take() {
+      Some(hand) if hand.phase() == Phase::Complete => hand,
+      hand => {
+        self.hand = hand;
+        return Err(GameErrors::HandNotComplete.into());
+      }
+    };
+
+    let payouts = hand
+      .payouts()
+      .map(|payouts| payouts.to_vec())
+      .unwrap_or_default();
+    for (seat, stack) in self.seats.iter_mut().zip(hand.betting().stacks()) {
+      seat.stack = stack;
+    }
+
+    // The button moves to the next player left of it who still has chips
+    let n = self.seats.len();
+    let next_button = (1..=n)
+      .map(|offset| (self.button + offset) % n)
+      .find(|seat| self.seats[*seat].stack > 0)
+      .unwrap_or(self.button);
+    self.button = (0..next_button)
+      .filter(|seat| self.seats[*seat].stack > 0)
+      .count();
+    self.seats.retain(|seat| seat.stack > 0);
+
+    self.hands_played += 1;
+    Ok(payouts)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::betting::{Action, BettingLimit};
+  use crate::texas_holdem::{Awaiting, Variant};
+  use crate::{encode_cards, Player};
+
+  use rand::thread_rng;
+
+  const CONFIG: HoldemConfig = HoldemConfig {
+    variant: Variant::Holdem,
+    small_blind: 1,
+    big_blind: 2,
+    limit: BettingLimit::NoLimit,
+  };
+
+  fn key_info(player: &Player) -> (PublicKey, ProofKeyOwnership, Vec<u8>) {
+    (player.pk, player.proof_key, player.name.clone())
+  }
+
+  /// Hand out every shuffle and reveal token and answer every betting action with `strategy`,
+  /// until the hand is complete.
+  fn play<R: Rng>(
+    rng: &mut R,
+    parameters: &CardParameters,
+    hand: &mut HoldemHand,
+    players: &[&Player],
+    strategy: fn(&HoldemHand) -> Action,
+  ) {
+    loop {
+      match hand.awaiting().first().copied() {
+        Some(Awaiting::Shuffle { seat }) => {
+          let (deck, proof) = hand.shuffle_deck(rng).unwrap();
+          hand.shuffle(seat, deck, &proof).unwrap();
+        }
+        Some(Awaiting::RevealToken { seat, card }) => {
+          let token = players[seat]
+            .compute_reveal_token(rng, parameters, &hand.deck()[card])
+            .unwrap();
+          hand.add_reveal_token(seat, card, token).unwrap();
+        }
+        Some(Awaiting::Action { seat }) => {
+          let action = strategy(hand);
+          hand.act(seat, action).unwrap();
+        }
+        None => return,
+      }
+    }
+  }
+
+  fn fold(_: &HoldemHand) -> Action {
+    Action::Fold
+  }
+
+  /// The first player to act moves all-in and everyone calls.
+  fn all_in(hand: &HoldemHand) -> Action {
+    if hand.betting().current_bet() > CONFIG.big_blind {
+      Action::Call
+    } else {
+      Action::AllIn
+    }
+  }
+
+  #[test]
+  fn rotates_the_button_and_removes_busted_players() {
+    let rng = &mut thread_rng();
+    let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+    let card_mapping = encode_cards(rng, 52);
+    let players: Vec<Player> = ["Andrija", "Kobi", "Nico"]
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    let keys: Vec<_> = players.iter().map(key_info).collect();
+    let config = SessionConfig {
+      holdem: CONFIG,
+      keying: Keying::Reuse,
+    };
+    let mut session =
+      Session::new(&parameters, &card_mapping, &keys, vec![20, 40, 60], config).unwrap();
+
+    // A quiet hand: everyone folds to the big blind
+    let hand = session.start_hand(rng).unwrap();
+    assert_eq!(hand.button(), 0);
+    assert_eq!(hand.next_shuffler(), Some(1));
+    assert!(session.start_hand(rng).is_err());
+    assert!(session.finish_hand().is_err());
+
+    let all: Vec<&Player> = players.iter().collect();
+    play(rng, &parameters, session.hand_mut().unwrap(), &all, fold);
+    assert_eq!(session.finish_hand().unwrap(), vec![0, 0, 3]);
+    assert_eq!(session.stacks(), vec![20, 39, 61]);
+    assert_eq!(session.button(), 1);
+
+    // Hands go all-in until only one player has chips left
+    while !session.is_over() {
+      let seated: Vec<&Player> = session
+        .names()
+        .iter()
+        .map(|name| players.iter().find(|p| p.name == *name).unwrap())
+        .collect();
+      let button = session.button();
+      let hand = session.start_hand(rng).unwrap();
+      assert_eq!(hand.button(), button);
+      assert_eq!(hand.next_shuffler(), Some((button + 1) % seated.len()));
+      play(rng, &parameters, hand, &seated, all_in);
+      session.finish_hand().unwrap();
+
+      assert_eq!(session.stacks().iter().sum::<u64>(), 120);
+      assert!(session.stacks().iter().all(|stack| *stack > 0));
+      assert!(session.is_over() || session.button() < session.num_of_players());
+    }
+    assert!(session.hands_played() >= 2);
+    assert_eq!(session.stacks(), vec![120]);
+  }
+
+  #[test]
+  fn requires_fresh_keys_when_rekeying_every_hand() {
+    let rng = &mut thread_rng();
+    let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+    let card_mapping = encode_cards(rng, 52);
+    let names = ["Andrija", "Kobi"];
+    let players: Vec<Player> = names
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    let keys: Vec<_> = players.iter().map(key_info).collect();
+    let config = SessionConfig {
+      holdem: CONFIG,
+      keying: Keying::PerHand,
+    };
+    let mut session =
+      Session::new(&parameters, &card_mapping, &keys, vec![100, 100], config).unwrap();
+
+    let hand = session.start_hand(rng).unwrap();
+    play(
+      rng,
+      &parameters,
+      hand,
+      &players.iter().collect::<Vec<_>>(),
+      fold,
+    );
+    assert_eq!(session.finish_hand().unwrap(), vec![0, 3]);
+
+    assert_eq!(
+      session
+        .start_hand(rng)
+        .err()
+        .and_then(|e| e.downcast::<GameErrors>().ok()),
+      Some(GameErrors::StaleKey(0))
+    );
+
+    // Sending the current key again does not make it fresh
+    let resent = session.rekey(0, players[0].pk, players[0].proof_key);
+    assert_eq!(
+      resent.unwrap_err().downcast::<GameErrors>().unwrap(),
+      GameErrors::StaleKey(0)
+    );
+
+    // A key proven under another name is rejected
+    let fresh: Vec<Player> = names
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    assert!(session.rekey(0, fresh[1].pk, fresh[1].proof_key).is_err());
+    for (seat, player) in fresh.iter().enumerate() {
+      session.rekey(seat, player.pk, player.proof_key).unwrap();
+    }
+    assert_eq!(session.public_keys(), vec![fresh[0].pk, fresh[1].pk]);
+
+    let hand = session.start_hand(rng).unwrap();
+    assert_eq!(hand.button(), 1);
+    play(
+      rng,
+      &parameters,
+      hand,
+      &fresh.iter().collect::<Vec<_>>(),
+      fold,
+    );
+    assert_eq!(session.finish_hand().unwrap(), vec![3, 0]);
+    assert_eq!(session.stacks(), vec![100, 100]);
+
+    // Neither is a key used in an earlier hand
+    for player in [&players[1], &fresh[1]] {
+      let reused = session.rekey(1, player.pk, player.proof_key);
+      assert_eq!(
+        reused.unwrap_err().downcast::<GameErrors>().unwrap(),
+        GameErrors::StaleKey(1)
+      );
+    }
+  }
+}
@@ -0,0 +1,844 @@
+//! A hand of fixed-limit seven-card stud played over the mental poker protocol.
+//!
+//! `StudHand` is the public state of one hand that every player keeps in sync, like
+//! `HoldemHand`, but it deals through a `DrawDeck` since every player gets some cards face down
+//! and others face up. Once every player has shuffled the deck, each street burns a card and
+//! deals one card to every seat still in the hand, starting left of the dealer: two down and one
+//! up on third street, one up on fourth to sixth street and one down on seventh street. Face-up
+//! cards are opened to everyone as soon as all their reveal tokens are in, face-down cards are
+//! only peeked at by their owner until showdown.
+//!
+//! The lowest card showing brings it in on third street, suits breaking ties from clubs up. On
+//! later streets the best hand showing acts first, counting only pairs, trips and quads, and
+//! ties go to the seat closest to the dealer's left. With seven players the deck can run out
+//! before seventh street: then a single card is dealt face up to the board and plays in every
+//! hand.
+//!
+//! Showdown works as in `HoldemHand`: the last to bet or raise on seventh street shows first,
+//! otherwise the first to act, and the others follow clockwise, showing or mucking.
+
+use crate::betting::{Action, Betting, BettingLimit, SeatStatus, Street};
+use crate::draw::DrawDeck;
+use crate::evaluator::{self, HandRank};
+use crate::pot;
+use crate::texas_holdem::{Awaiting, Variant};
+use crate::zones::Visibility;
+use crate::{
+  deck_order, Card, CardParameters, CardProtocol, ClassicPlayingCard, GameErrors, MaskedCard,
+  ProofKeyOwnership, PublicKey, RevealShare, Scalar, ShuffleProof, Value,
+};
+
+use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Seven players are the most a 52-card deck deals to, with the community card on seventh
+/// street.
+pub const MAX_PLAYERS: usize = 7;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StudStreet {
+  Third,
+  Fourth,
+  Fifth,
+  Sixth,
+  Seventh,
+}
+
+impl StudStreet {
+  pub fn next(&self) -> Option<Self> {
+    match self {
+      Self::Third => Some(Self::Fourth),
+      Self::Fourth => Some(Self::Fifth),
+      Self::Fifth => Some(Self::Sixth),
+      Self::Sixth => Some(Self::Seventh),
+      Self::Seventh => None,
+    }
+  }
+
+  /// The betting round the street is played as: the small bet on third and fourth street, the
+  /// big bet from fifth street on.
+  fn betting_street(&self) -> Street {
+    match self {
+      Self::Third => Street::Preflop,
+      Self::Fourth => Street::Flop,
+      Self::Fifth => Street::Turn,
+      Self::Sixth | Self::Seventh => Street::River,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StudConfig {
+  pub ante: u64,
+  pub bring_in: u64,
+  pub small_bet: u64,
+  pub big_bet: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+  /// Every player shuffles and remasks the deck in turn.
+  Shuffle,
+  /// Every player hands out reveal tokens for the cards dealt on the street.
+  Deal(StudStreet),
+  Betting(StudStreet),
+  /// Players still in the hand show or muck their face-down cards in turn.
+  Showdown,
+  Complete,
+}
+
+pub struct StudHand<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+  joint_pk: PublicKey,
+  players: Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)>,
+  dealer: usize,
+  config: StudConfig,
+  phase: Phase,
+  shuffles: usize,
+  // The deck being shuffled, until it is handed to `cards` for dealing
+  deck: Vec<MaskedCard>,
+  cards: Option<DrawDeck<'a>>,
+  betting: Betting,
+  bring_in: Option<usize>,
+  // Seat the betting of the current street starts at
+  opener: usize,
+  // Seats still in the hand at showdown, in showing order
+  showdown_order: Vec<usize>,
+  hands: Vec<Option<HandRank>>,
+  mucked: Vec<bool>,
+  payouts: Option<Vec<u64>>,
+}
+
+impl<'a> StudHand<'a> {
+  /// Start a hand between `players`, listed in seat order with their proofs of key ownership,
+  /// and post the antes. `card_mapping` must hold the 52 cards, masked in card order into the
+  /// initial deck as in `HoldemHand::new`.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    stacks: Vec<u64>,
+    dealer: usize,
+    config: StudConfig,
+  ) -> anyhow::Result<Self> {
+    let num_of_players = players.len();
+    let cards = deck_order(card_mapping);
+    let expected = Variant::Holdem.deck();
+    if !cards
+      .iter()
+      .map(|(_, classic)| **classic)
+      .eq(expected.iter().copied())
+    {
+      return Err(GameErrors::WrongDeck(expected.len() as u32).into());
+    }
+    if !(2..=MAX_PLAYERS).contains(&num_of_players) {
+      return Err(GameErrors::InvalidPlayerCount(num_of_players as u32).into());
+    }
+    if stacks.len() != num_of_players {
+      return Err(GameErrors::WrongSeatCount(num_of_players as u32).into());
+    }
+    if dealer >= num_of_players {
+      return Err(GameErrors::InvalidSeat(dealer as u32).into());
+    }
+
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &players.to_vec())?;
+
+    let deck = cards
+      .into_iter()
+      .map(|(card, _)| {
+        CardProtocol::mask(rng, parameters, &joint_pk, card, &Scalar::one())
+          .map(|(masked, _)| masked)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let limit = BettingLimit::FixedLimit {
+      small_bet: config.small_bet,
+      big_bet: config.big_bet,
+    };
+    let mut betting = Betting::new(stacks, limit, config.small_bet);
+    for seat in 0..num_of_players {
+      betting.post_ante(seat, config.ante)?;
+    }
+
+    Ok(Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      players: players.to_vec(),
+      dealer,
+      config,
+      phase: Phase::Shuffle,
+      shuffles: 0,
+      deck,
+      cards: None,
+      betting,
+      bring_in: None,
+      opener: dealer,
+      showdown_order: vec![],
+      hands: vec![None; num_of_players],
+      mucked: vec![false; num_of_players],
+      payouts: None,
+    })
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.players.len()
+  }
+
+  pub fn dealer(&self) -> usize {
+    self.dealer
+  }
+
+  pub fn betting(&self) -> &Betting {
+    &self.betting
+  }
+
+  /// The cards in play, once every player has shuffled the deck. Face-down cards are peeked at
+  /// with the reveal tokens it holds, like the hole cards of `HoldemHand`.
+  pub fn cards(&self) -> Option<&DrawDeck<'a>> {
+    self.cards.as_ref()
+  }
+
+  /// Seat that shuffles next. The first shuffler sits left of the dealer.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Shuffle => Some(self.seat_after_dealer(1 + self.shuffles)),
+      _ => None,
+    }
+  }
+
+  /// Everything the hand needs before it can move on. Reveal tokens are keyed by card id in
+  /// `cards`.
+  pub fn awaiting(&self) -> Vec<Awaiting> {
+    match self.phase {
+      Phase::Shuffle => self
+        .next_shuffler()
+        .map(|seat| Awaiting::Shuffle { seat })
+        .into_iter()
+        .collect(),
+      Phase::Betting(_) => self
+        .betting
+        .to_act()
+        .map(|seat| Awaiting::Action { seat })
+        .into_iter()
+        .collect(),
+      Phase::Complete => vec![],
+      _ => self.awaited_tokens(),
+    }
+  }
+
+  /// The face-up cards of `seat` opened so far, in the order they were dealt.
+  pub fn up_cards(&self, seat: usize) -> Vec<ClassicPlayingCard> {
+    self
+      .cards
+      .as_ref()
+      .map(|cards| {
+        cards
+          .hand(seat)
+          .into_iter()
+          .filter_map(|id| cards.opened_card(id))
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  /// The community card, when the deck ran out on seventh street.
+  pub fn board(&self) -> Vec<ClassicPlayingCard> {
+    self
+      .cards
+      .as_ref()
+      .map(|cards| {
+        cards
+          .board()
+          .into_iter()
+          .filter_map(|id| cards.opened_card(id))
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  /// Seat that brought it in on third street.
+  pub fn bring_in(&self) -> Option<usize> {
+    self.bring_in
+  }
+
+  /// Seat that shows or mucks next at showdown.
+  pub fn next_to_show(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Showdown => self
+        .showdown_order
+        .iter()
+        .copied()
+        .find(|seat| self.hands[*seat].is_none() && !self.mucked[*seat]),
+      _ => None,
+    }
+  }
+
+  /// Rank of the hand shown by `seat`, `None` until it shows or if it folded or mucked.
+  pub fn shown_hand(&self, seat: usize) -> Option<HandRank> {
+    self.hands.get(seat).copied().flatten()
+  }
+
+  pub fn has_mucked(&self, seat: usize) -> bool {
+    self.mucked.get(seat).copied().unwrap_or(false)
+  }
+
+  /// Whether `seat` may muck, under the same rule as `HoldemHand::can_muck`.
+  pub fn can_muck(&self, seat: usize) -> bool {
+    if self.next_to_show() != Some(seat) {
+      return false;
+    }
+
+    let pots = pot::build_pots(&self.betting.contributions(), &self.folded());
+    let shown: Vec<bool> = self.hands.iter().map(Option::is_some).collect();
+    pot::may_muck(&pots, seat, &shown)
+  }
+
+  /// Chips won by every seat, once the hand is complete.
+  pub fn payouts(&self) -> Option<&[u64]> {
+    self.payouts.as_deref()
+  }
+
+  /// Shuffle and remask the current deck, as the next shuffler does locally before sending the
+  /// result to the others.
+  pub fn shuffle_deck<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let num_of_cards = self.deck.len();
+    let permutation = Permutation::new(rng, num_of_cards);
+    let masking_factors: Vec<Scalar> = sample_vector(rng, num_of_cards);
+
+    Ok(CardProtocol::shuffle_and_remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &masking_factors,
+      &permutation,
+    )?)
+  }
+
+  /// Verify and adopt the shuffle of `seat`.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    CardProtocol::verify_shuffle(
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &shuffled_deck,
+      shuffle_proof,
+    )?;
+
+    self.deck = shuffled_deck;
+    self.shuffles += 1;
+    self.advance()
+  }
+
+  /// Verify and record the reveal token of `seat` for the card `id` of `cards`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    let awaited = Awaiting::RevealToken { seat, card: id };
+    if !self.awaited_tokens().contains(&awaited) {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+    }
+
+    let cards = self.cards.as_mut().unwrap();
+    if self.phase == Phase::Showdown {
+      cards.show(seat)?;
+    }
+    cards.add_reveal_token(seat, id, token)?;
+    self.advance()
+  }
+
+  pub fn act(&mut self, seat: usize, action: Action) -> anyhow::Result<()> {
+    if !matches!(self.phase, Phase::Betting(_)) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    self.betting.act(seat, action)?;
+    self.advance()
+  }
+
+  /// Concede the pot at showdown without opening the face-down cards of `seat`.
+  pub fn muck(&mut self, seat: usize) -> anyhow::Result<()> {
+    if self.next_to_show() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    if !self.can_muck(seat) {
+      return Err(GameErrors::CannotMuck(seat as u32).into());
+    }
+
+    self.mucked[seat] = true;
+    self.advance()
+  }
+
+  fn seat_after_dealer(&self, offset: usize) -> usize {
+    (self.dealer + offset) % self.num_of_players()
+  }
+
+  /// Seats still in the hand, starting left of the dealer.
+  fn live_from_dealer(&self) -> Vec<usize> {
+    let live = self.betting.live_seats();
+    (1..=self.num_of_players())
+      .map(|offset| self.seat_after_dealer(offset))
+      .filter(|seat| live.contains(seat))
+      .collect()
+  }
+
+  fn awaited_tokens(&self) -> Vec<Awaiting> {
+    let cards = match &self.cards {
+      Some(cards) => cards,
+      None => return vec![],
+    };
+
+    match self.phase {
+      Phase::Deal(_) => cards
+        .awaited_tokens()
+        .into_iter()
+        .map(|(seat, card)| Awaiting::RevealToken { seat, card })
+        .collect(),
+      // Only the seat whose turn it is to show, so that folded and mucked hands stay masked
+      Phase::Showdown => match self.next_to_show() {
+        Some(seat) => cards
+          .hand(seat)
+          .into_iter()
+          .filter(|id| cards.reveal_tokens(*id).len() < self.num_of_players())
+          .map(|card| Awaiting::RevealToken { seat, card })
+          .collect(),
+        None => vec![],
+      },
+      _ => vec![],
+    }
+  }
+
+  /// Move through every phase whose requirements are already met.
+  fn advance(&mut self) -> anyhow::Result<()> {
+    loop {
+      match self.phase {
+        Phase::Shuffle if self.shuffles == self.num_of_players() => {
+          let deck = std::mem::take(&mut self.deck);
+          self.cards = Some(DrawDeck::new(
+            self.parameters,
+            self.card_mapping,
+            &self.players,
+            deck,
+            false,
+          )?);
+          self.deal(StudStreet::Third)?;
+        }
+        Phase::Deal(street) if self.awaited_tokens().is_empty() => {
+          self.start_betting(street)?;
+        }
+        Phase::Betting(street) if self.betting.is_round_over() => {
+          if self.betting.live_seats().len() == 1 {
+            self.settle();
+            continue;
+          }
+          match street.next() {
+            Some(next) => self.deal(next)?,
+            None => {
+              self.showdown_order = self.showdown_order();
+              self.phase = Phase::Showdown;
+            }
+          }
+        }
+        Phase::Showdown if self.awaited_tokens().is_empty() => match self.next_to_show() {
+          Some(seat) => self.show(seat)?,
+          None => self.settle(),
+        },
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  /// Burn a card and deal the cards of `street` to every seat still in the hand.
+  fn deal(&mut self, street: StudStreet) -> anyhow::Result<()> {
+    let live = self.live_from_dealer();
+    let cards = self.cards.as_mut().unwrap();
+    cards.burn(1)?;
+
+    let rounds = match street {
+      StudStreet::Third => vec![
+        Visibility::FaceDown,
+        Visibility::FaceDown,
+        Visibility::Public,
+      ],
+      StudStreet::Seventh => vec![Visibility::FaceDown],
+      _ => vec![Visibility::Public],
+    };
+    if street == StudStreet::Seventh && cards.stub_len() < live.len() {
+      cards.deal_to_board(1)?;
+    } else {
+      for visibility in rounds {
+        for seat in &live {
+          cards.deal_as(*seat, 1, visibility.clone())?;
+        }
+      }
+    }
+
+    self.phase = Phase::Deal(street);
+    Ok(())
+  }
+
+  fn start_betting(&mut self, street: StudStreet) -> anyhow::Result<()> {
+    let live = self.live_from_dealer();
+    if street == StudStreet::Third {
+      // The lowest card showing among the seats with chips left brings it in
+      let bring_in = live
+        .iter()
+        .copied()
+        .filter(|seat| self.betting.status(*seat) == SeatStatus::Active)
+        .min_by_key(|seat| self.up_cards(*seat));
+      self.opener = bring_in.map_or(self.seat_after_dealer(1), |seat| {
+        (seat + 1) % self.num_of_players()
+      });
+      self
+        .betting
+        .start_round(street.betting_street(), self.opener);
+      if let Some(seat) = bring_in {
+        self.betting.post_bring_in(seat, self.config.bring_in)?;
+      }
+      self.bring_in = bring_in;
+    } else {
+      // `max_by_key` keeps the last of equal keys, so look from the seat right of the dealer
+      self.opener = live
+        .iter()
+        .rev()
+        .copied()
+        .max_by_key(|seat| showing_rank(&self.up_cards(*seat)))
+        .unwrap();
+      self
+        .betting
+        .start_round(street.betting_street(), self.opener);
+    }
+
+    self.phase = Phase::Betting(street);
+    Ok(())
+  }
+
+  /// The last aggressor on seventh street shows first. Without a bet, showing starts with the
+  /// first to act on seventh street.
+  fn showdown_order(&self) -> Vec<usize> {
+    let n = self.num_of_players();
+    let live = self.betting.live_seats();
+    let first = self
+      .betting
+      .last_aggressor()
+      .filter(|seat| live.contains(seat))
+      .unwrap_or(self.opener);
+
+    (0..n)
+      .map(|offset| (first + offset) % n)
+      .filter(|seat| live.contains(seat))
+      .collect()
+  }
+
+  fn show(&mut self, seat: usize) -> anyhow::Result<()> {
+    let cards = self.cards.as_ref().unwrap();
+    let hand = [cards.open_hand(seat)?, self.board()].concat();
+    self.hands[seat] = Some(evaluator::evaluate(&hand)?);
+    Ok(())
+  }
+
+  fn folded(&self) -> Vec<bool> {
+    (0..self.num_of_players())
+      .map(|seat| self.betting.status(seat) == SeatStatus::Folded)
+      .collect()
+  }
+
+  /// Award the pots to the hands shown down, or to the last seat left if everyone else folded.
+  fn settle(&mut self) {
+    let pots = pot::build_pots(&self.betting.contributions(), &self.folded());
+    let payouts = pot::distribute(&pots, &self.hands, self.dealer);
+
+    for (seat, payout) in payouts.iter().enumerate() {
+      self.betting.award(seat, *payout);
+    }
+    self.payouts = Some(payouts);
+    self.phase = Phase::Complete;
+  }
+}
+
+/// Order of the cards showing on a street: quads, trips, two pair, a pair, then high cards.
+/// Straights and flushes are not counted.
+fn showing_rank(cards: &[ClassicPlayingCard]) -> (Vec<usize>, Vec<Value>) {
+  let mut groups: Vec<(usize, Value)> = vec![];
+  for card in cards {
+    match groups.iter_mut().find(|(_, value)| *value == card.value) {
+      Some((count, _)) => *count += 1,
+      None => groups.push((1, card.value)),
+    }
+  }
+  groups.sort_by_key(|group| Reverse(*group));
+
+  groups.into_iter().unzip()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{key_info, run_protocol, setup, Protocol, Step};
+
+  use rand::thread_rng;
+
+  const CONFIG: StudConfig = StudConfig {
+    ante: 1,
+    bring_in: 2,
+    small_bet: 4,
+    big_bet: 8,
+  };
+
+  impl Protocol for StudHand<'_> {
+    fn step(&self) -> Option<Step> {
+      match self.awaiting().first().copied()? {
+        Awaiting::Shuffle { seat } => Some(Step::Shuffle { seat }),
+        Awaiting::RevealToken { seat, card } => Some(Step::RevealToken { seat, card }),
+        _ => None,
+      }
+    }
+
+    fn parameters(&self) -> &CardParameters {
+      self.parameters
+    }
+
+    fn card(&self, id: usize) -> MaskedCard {
+      *self.cards().unwrap().card(id).unwrap()
+    }
+
+    fn shuffle_as<R: Rng>(&mut self, rng: &mut R, seat: usize) -> anyhow::Result<()> {
+      let (deck, proof) = self.shuffle_deck(rng)?;
+      self.shuffle(seat, deck, &proof)
+    }
+
+    fn add_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+      self.add_reveal_token(seat, id, token)
+    }
+  }
+
+  fn card(value: Value, suite: crate::Suite) -> ClassicPlayingCard {
+    ClassicPlayingCard::new(value, suite)
+  }
+
+  #[test]
+  fn ranks_the_cards_showing() {
+    use crate::Suite::*;
+    use Value::*;
+
+    let pair = showing_rank(&[card(Two, Club), card(Two, Heart), card(Ace, Spade)]);
+    let two_pair = showing_rank(&[
+      card(Three, Club),
+      card(Three, Heart),
+      card(Four, Spade),
+      card(Four, Club),
+    ]);
+    let trips = showing_rank(&[card(Two, Club), card(Two, Heart), card(Two, Spade)]);
+    let aces = showing_rank(&[card(Ace, Club), card(King, Heart), card(Queen, Spade)]);
+    let kings = showing_rank(&[card(King, Club), card(Ace, Heart), card(Jack, Spade)]);
+    // A pair of twos beats aces up, two pair beats any pair, trips beat two pair
+    assert!(pair > aces);
+    assert!(two_pair > showing_rank(&[card(Ace, Club), card(Ace, Heart)]));
+    assert!(trips > two_pair);
+    assert!(aces > kings);
+    // Suits do not count
+    assert!(showing_rank(&[card(Nine, Club)]) == showing_rank(&[card(Nine, Spade)]));
+  }
+
+  #[test]
+  fn seven_players_share_a_community_card() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, mut players) = setup(rng, 7);
+    let mut too_many = key_info(&players);
+    too_many.push(too_many[0].clone());
+    assert!(StudHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &too_many,
+      vec![100; 8],
+      0,
+      CONFIG,
+    )
+    .is_err());
+
+    let mut hand = StudHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100; 7],
+      6,
+      CONFIG,
+    )
+    .unwrap();
+    assert_eq!(hand.betting().pot(), 7);
+    assert_eq!(hand.awaiting(), vec![Awaiting::Shuffle { seat: 0 }]);
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Betting(StudStreet::Third));
+
+    // Two cards down and one up each, one burn card first
+    let cards = hand.cards().unwrap();
+    assert_eq!(cards.stub_len(), 52 - 22);
+    for seat in 0..7 {
+      let ids = cards.hand(seat);
+      assert_eq!(ids.len(), 3);
+      assert_eq!(
+        cards.visibility(ids[0]),
+        Some(&Visibility::KnownTo(vec![seat]))
+      );
+      assert_eq!(cards.opened_card(ids[0]), None);
+      assert_eq!(cards.reveal_tokens(ids[0]).len(), 6);
+      assert_eq!(hand.up_cards(seat).len(), 1);
+    }
+
+    // Every player privately peeks at its face-down cards
+    for (seat, player) in players.iter_mut().enumerate() {
+      for id in cards.hand(seat).into_iter().take(2) {
+        let masked = *cards.card(id).unwrap();
+        player.receive_card(masked);
+        player
+          .peek_at_card(
+            rng,
+            &parameters,
+            &mut cards.reveal_tokens(id),
+            &card_mapping,
+            &masked,
+          )
+          .unwrap();
+      }
+    }
+
+    // The lowest card showing brings it in and the next seat acts
+    let bring_in = hand.bring_in().unwrap();
+    let lowest = (0..7).map(|seat| hand.up_cards(seat)).min().unwrap();
+    assert_eq!(hand.up_cards(bring_in), lowest);
+    assert_eq!(hand.betting().pot(), 9);
+    assert_eq!(
+      hand.awaiting(),
+      vec![Awaiting::Action {
+        seat: (bring_in + 1) % 7
+      }]
+    );
+
+    // Everyone calls the bring-in, then checks every street down
+    for street in [
+      StudStreet::Third,
+      StudStreet::Fourth,
+      StudStreet::Fifth,
+      StudStreet::Sixth,
+      StudStreet::Seventh,
+    ] {
+      run_protocol(rng, &mut hand, &players);
+      assert_eq!(hand.phase(), Phase::Betting(street));
+      if street != StudStreet::Third {
+        // The best hand showing acts first
+        let first = match hand.awaiting()[0] {
+          Awaiting::Action { seat } => seat,
+          _ => unreachable!(),
+        };
+        let best = (0..7)
+          .map(|seat| showing_rank(&hand.up_cards(seat)))
+          .max()
+          .unwrap();
+        assert!(showing_rank(&hand.up_cards(first)) == best);
+      }
+      while let Some(Awaiting::Action { seat }) = hand.awaiting().first().copied() {
+        let action = if hand.betting().to_call(seat) > 0 {
+          Action::Call
+        } else {
+          Action::Check
+        };
+        hand.act(seat, action).unwrap();
+      }
+    }
+
+    // Only five cards were left after the burn, so seventh street is a community card
+    assert_eq!(hand.board().len(), 1);
+    let cards = hand.cards().unwrap();
+    assert_eq!(cards.stub_len(), 4);
+    for seat in 0..7 {
+      assert_eq!(cards.hand(seat).len(), 6);
+      assert_eq!(hand.up_cards(seat).len(), 4);
+    }
+
+    assert_eq!(hand.phase(), Phase::Showdown);
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+    for seat in 0..7 {
+      let cards = hand.cards().unwrap();
+      let mut shown = cards.open_hand(seat).unwrap();
+      shown.extend(hand.board());
+      assert_eq!(
+        hand.shown_hand(seat),
+        Some(evaluator::evaluate(&shown).unwrap())
+      );
+    }
+    assert_eq!(hand.payouts().unwrap().iter().sum::<u64>(), 21);
+    assert_eq!(hand.betting().stacks().iter().sum::<u64>(), 700);
+  }
+
+  #[test]
+  fn folded_down_cards_stay_masked() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, 2);
+    let mut hand = StudHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100],
+      0,
+      CONFIG,
+    )
+    .unwrap();
+
+    run_protocol(rng, &mut hand, &players);
+    let bring_in = hand.bring_in().unwrap();
+    let other = 1 - bring_in;
+    assert_eq!(hand.awaiting(), vec![Awaiting::Action { seat: other }]);
+
+    // Completing the bring-in is a raise to the small bet
+    assert!(hand.act(other, Action::Raise(8)).is_err());
+    hand.act(other, Action::Raise(4)).unwrap();
+    hand.act(bring_in, Action::Fold).unwrap();
+
+    assert_eq!(hand.phase(), Phase::Complete);
+    let mut payouts = vec![0, 0];
+    payouts[other] = 8;
+    assert_eq!(hand.payouts().unwrap(), payouts);
+
+    // The up cards were opened to everyone, the down cards never were
+    let cards = hand.cards().unwrap();
+    for seat in 0..2 {
+      let ids = cards.hand(seat);
+      assert!(cards.opened_card(ids[2]).is_some());
+      for id in &ids[..2] {
+        assert_eq!(cards.opened_card(*id), None);
+        assert_eq!(cards.reveal_tokens(*id).len(), 1);
+      }
+    }
+    assert!(hand.muck(bring_in).is_err());
+  }
+}
@@ -0,0 +1,202 @@
+//! Fixtures shared by the tests of the game engines.
+
+use crate::ccg::PrivateDeck;
+use crate::draw::DrawDeck;
+use crate::zones::Deck;
+use crate::{
+  encode_cards, open_card, Card, CardParameters, CardProtocol, ClassicPlayingCard, MaskedCard,
+  Player, ProofKeyOwnership, PublicKey, RevealShare, Scalar,
+};
+
+use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::BarnettSmartProtocol;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use std::collections::HashMap;
+
+/// Parameters for a 52-card deck, an encoding of the deck and `num_of_players` players.
+pub fn setup<R: Rng>(
+  rng: &mut R,
+  num_of_players: usize,
+) -> (
+  CardParameters,
+  HashMap<Card, ClassicPlayingCard>,
+  Vec<Player>,
+) {
+  let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+  let card_mapping = encode_cards(rng, 52);
+  let players = players(rng, &parameters, num_of_players);
+  (parameters, card_mapping, players)
+}
+
+pub fn players<R: Rng>(
+  rng: &mut R,
+  parameters: &CardParameters,
+  num_of_players: usize,
+) -> Vec<Player> {
+  (0..num_of_players)
+    .map(|seat| Player::new(rng, parameters, &format!("seat {}", seat).into_bytes()).unwrap())
+    .collect()
+}
+
+/// The keys of `players` with their proofs of ownership, as the engines take them.
+pub fn key_info(players: &[Player]) -> Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)> {
+  players
+    .iter()
+    .map(|p| (p.pk, p.proof_key, p.name.clone()))
+    .collect()
+}
+
+/// Every card of `card_mapping` masked with the aggregate key of `players`, then shuffled by each
+/// of them in turn.
+pub fn shuffled_deck<R: Rng, T>(
+  rng: &mut R,
+  parameters: &CardParameters,
+  card_mapping: &HashMap<Card, T>,
+  players: &[Player],
+) -> Vec<MaskedCard> {
+  let joint_pk = CardProtocol::compute_aggregate_key(parameters, &key_info(players)).unwrap();
+  let mut deck: Vec<MaskedCard> = card_mapping
+    .keys()
+    .map(|card| {
+      CardProtocol::mask(rng, parameters, &joint_pk, card, &Scalar::one())
+        .unwrap()
+        .0
+    })
+    .collect();
+  for _ in players {
+    let permutation = Permutation::new(rng, deck.len());
+    let masking_factors: Vec<Scalar> = sample_vector(rng, deck.len());
+    deck = CardProtocol::shuffle_and_remask(
+      rng,
+      parameters,
+      &joint_pk,
+      &deck,
+      &masking_factors,
+      &permutation,
+    )
+    .unwrap()
+    .0;
+  }
+
+  deck
+}
+
+/// Open `card` with the tokens of every one of `players`, as a peek does.
+pub fn peek<R: Rng>(
+  rng: &mut R,
+  parameters: &CardParameters,
+  card_mapping: &HashMap<Card, ClassicPlayingCard>,
+  players: &[Player],
+  card: &MaskedCard,
+) -> ClassicPlayingCard {
+  let tokens: Vec<RevealShare> = players
+    .iter()
+    .map(|player| player.compute_reveal_token(rng, parameters, card).unwrap())
+    .collect();
+  open_card(parameters, &tokens, card_mapping, card).unwrap()
+}
+
+/// A deck that collects public reveal tokens, for `deliver_tokens`.
+pub trait Reveals {
+  fn awaited_tokens(&self) -> Vec<(usize, usize)>;
+
+  fn card(&self, id: usize) -> Option<&MaskedCard>;
+
+  fn add_reveal_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()>;
+}
+
+impl<T: Copy> Reveals for Deck<'_, T> {
+  fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    Deck::awaited_tokens(self)
+  }
+
+  fn card(&self, id: usize) -> Option<&MaskedCard> {
+    Deck::card(self, id)
+  }
+
+  fn add_reveal_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+    Deck::add_reveal_token(self, seat, id, token)
+  }
+}
+
+impl Reveals for DrawDeck<'_> {
+  fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    DrawDeck::awaited_tokens(self)
+  }
+
+  fn card(&self, id: usize) -> Option<&MaskedCard> {
+    DrawDeck::card(self, id)
+  }
+
+  fn add_reveal_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+    DrawDeck::add_reveal_token(self, seat, id, token)
+  }
+}
+
+impl<T: Copy + PartialEq> Reveals for PrivateDeck<'_, T> {
+  fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    PrivateDeck::awaited_tokens(self)
+  }
+
+  fn card(&self, id: usize) -> Option<&MaskedCard> {
+    PrivateDeck::card(self, id)
+  }
+
+  fn add_reveal_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+    PrivateDeck::add_reveal_token(self, seat, id, token)
+  }
+}
+
+/// Hand out every reveal token `deck` is waiting for.
+pub fn deliver_tokens<R: Rng, D: Reveals>(
+  rng: &mut R,
+  parameters: &CardParameters,
+  deck: &mut D,
+  players: &[Player],
+) {
+  for (seat, id) in deck.awaited_tokens() {
+    let token = players[seat]
+      .compute_reveal_token(rng, parameters, deck.card(id).unwrap())
+      .unwrap();
+    deck.add_reveal_token(seat, id, token).unwrap();
+  }
+}
+
+/// A shuffle or a reveal token an engine is waiting for.
+pub enum Step {
+  Shuffle { seat: usize },
+  RevealToken { seat: usize, card: usize },
+}
+
+/// The shuffles and reveal tokens of an engine, which `run_protocol` hands out for the players.
+pub trait Protocol {
+  /// The shuffle or reveal token the engine waits for next, `None` when it waits for a move of
+  /// the game or is complete.
+  fn step(&self) -> Option<Step>;
+
+  fn parameters(&self) -> &CardParameters;
+
+  fn card(&self, id: usize) -> MaskedCard;
+
+  /// Shuffle the deck as `seat` and have the engine verify it.
+  fn shuffle_as<R: Rng>(&mut self, rng: &mut R, seat: usize) -> anyhow::Result<()>;
+
+  fn add_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()>;
+}
+
+/// Hand out every shuffle and reveal token `hand` is waiting for, until it needs a move of the
+/// game or is complete.
+pub fn run_protocol<R: Rng, H: Protocol>(rng: &mut R, hand: &mut H, players: &[Player]) {
+  while let Some(step) = hand.step() {
+    match step {
+      Step::Shuffle { seat } => hand.shuffle_as(rng, seat).unwrap(),
+      Step::RevealToken { seat, card } => {
+        let token = players[seat]
+          .compute_reveal_token(rng, hand.parameters(), &hand.card(card))
+          .unwrap();
+        hand.add_token(seat, card, token).unwrap();
+      }
+    }
+  }
+}
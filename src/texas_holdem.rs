@@ -1,570 +1,992 @@
-use barnett_smart_card_protocol::discrete_log_cards;
-use barnett_smart_card_protocol::BarnettSmartProtocol;
+//! A hand of Texas Hold'em or Omaha played over the mental poker protocol.
+//!
+//! `HoldemHand` is the public state of one hand that every player (or a referee) keeps in sync.
+//! It moves through Shuffle → Deal → Preflop → Flop → Turn → River → Showdown, checking each
+//! shuffle proof and reveal token as it is received, and `awaiting` tells at every step which
+//! shuffles, reveal tokens and betting actions it needs next.
+//!
+//! Short-deck Hold'em deals from the 36 cards Six to Ace, under protocol parameters of its own
+//! (`Variant::protocol_shape`) and a card mapping built with `encode_deck(rng, &Variant::deck())`.
+//! Omaha deals four hole cards instead of two and a hand must use exactly two of them; the
+//! high-low variant also splits every pot with the best eight-or-better low. Pot-Limit Omaha is
+//! `Variant::Omaha` with `BettingLimit::PotLimit`.
+//!
+//! Deck positions are fixed by the seat order: hole cards are dealt one at a time starting left
+//! of the button, then a card is burned before the flop, the turn and the river. Burned cards
+//! are never opened.
+//!
+//! Hole cards are only opened at showdown, where players show in turn: the last to bet or raise
+//! on the river first, otherwise the first seat left of the button, and then clockwise. A folded
+//! hand is never opened, since its owner keeps the one reveal token the others are missing. A
+//! player facing a hand already shown may `muck` instead of showing and gives up the pot, so
+//! every pot still goes to a hand opened in public.
+
+use crate::betting::{Action, Betting, BettingLimit, SeatStatus, Street};
+use crate::evaluator::{self, HandRank, LowRank};
+use crate::pot;
+use crate::{
+  deck_order, open_card, Card, CardParameters, CardProtocol, ClassicPlayingCard, GameErrors,
+  MaskedCard, ProofKeyOwnership, PublicKey, RevealShare, Scalar, ShuffleProof, Suite, Value,
+};
 
-use anyhow;
-use ark_ff::{to_bytes, UniformRand};
 use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::BarnettSmartProtocol;
 use proof_essentials::utils::permutation::Permutation;
 use proof_essentials::utils::rand::sample_vector;
-use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
-use rand::thread_rng;
 use std::collections::HashMap;
-use std::iter::Iterator;
-use thiserror::Error;
-
-// Choose elliptic curve setting
-type Curve = starknet_curve::Projective;
-type Scalar = starknet_curve::Fr;
-
-// Instantiate concrete type for our card protocol
-type CardProtocol<'a> = discrete_log_cards::DLCards<'a, Curve>;
-type CardParameters = discrete_log_cards::Parameters<Curve>;
-type PublicKey = discrete_log_cards::PublicKey<Curve>;
-type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
-
-type Card = discrete_log_cards::Card<Curve>;
-type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
-type RevealToken = discrete_log_cards::RevealToken<Curve>;
-
-type ProofKeyOwnership = schnorr_identification::proof::Proof<Curve>;
-type RemaskingProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
-type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
-
-#[derive(Error, Debug, PartialEq)]
-pub enum GameErrors {
-    #[error("No such card in hand")]
-    CardNotFound,
-
-    #[error("Invalid card")]
-    InvalidCard,
-}
 
-#[derive(PartialEq, Clone, Copy, Eq)]
-pub enum Suite {
-    Club,
-    Diamond,
-    Heart,
-    Spade,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+  Holdem,
+  /// Six-plus Hold'em, without the Two to Five.
+  ShortDeck,
+  Omaha,
+  /// Omaha where the best high hand and the best eight-or-better low split each pot.
+  OmahaHiLo,
 }
 
-impl Suite {
-    const VALUES: [Self; 4] = [Self::Club, Self::Diamond, Self::Heart, Self::Spade];
-}
+impl Variant {
+  /// The cards the game is dealt from, in card order.
+  pub fn deck(&self) -> Vec<ClassicPlayingCard> {
+    let lowest = match self {
+      Self::ShortDeck => Value::Six,
+      _ => Value::Two,
+    };
+    Value::VALUES
+      .iter()
+      .filter(|value| **value >= lowest)
+      .flat_map(|value| {
+        Suite::VALUES
+          .iter()
+          .map(move |suite| ClassicPlayingCard::new(*value, *suite))
+      })
+      .collect()
+  }
+
+  /// Protocol parameters `(m, n)` to set up for the deck, with `m * n` cards.
+  pub fn protocol_shape(&self) -> (usize, usize) {
+    match self {
+      Self::ShortDeck => (2, 18),
+      _ => (2, 26),
+    }
+  }
 
-#[derive(PartialEq, PartialOrd, Clone, Copy, Eq)]
-pub enum Value {
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
+  pub fn hole_cards(&self) -> usize {
+    match self {
+      Self::Holdem | Self::ShortDeck => 2,
+      Self::Omaha | Self::OmahaHiLo => 4,
+    }
+  }
 
-impl Value {
-    const VALUES: [Self; 13] = [
-        Self::Two,
-        Self::Three,
-        Self::Four,
-        Self::Five,
-        Self::Six,
-        Self::Seven,
-        Self::Eight,
-        Self::Nine,
-        Self::Ten,
-        Self::Jack,
-        Self::Queen,
-        Self::King,
-        Self::Ace,
-    ];
+  /// Hole cards for every player, and the burn and board cards of the three streets.
+  fn cards_needed(&self, num_of_players: usize) -> usize {
+    self.hole_cards() * num_of_players + 8
+  }
 }
 
-#[derive(PartialEq, Clone, Eq, Copy)]
-pub struct ClassicPlayingCard {
-    value: Value,
-    suite: Suite,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HoldemConfig {
+  pub variant: Variant,
+  pub small_blind: u64,
+  pub big_blind: u64,
+  pub limit: BettingLimit,
 }
 
-impl ClassicPlayingCard {
-    pub fn new(value: Value, suite: Suite) -> Self {
-        Self { value, suite }
-    }
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+  /// Every player shuffles and remasks the deck in turn.
+  Shuffle,
+  /// Every player hands out reveal tokens for the hole cards of the others.
+  Deal,
+  Betting(Street),
+  /// Every player hands out reveal tokens for the board cards of the street.
+  Reveal(Street),
+  /// Players still in the hand show or muck their hole cards in turn.
+  Showdown,
+  Complete,
 }
 
-impl std::fmt::Debug for ClassicPlayingCard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let suite = match self.suite {
-            Suite::Club => "♣",
-            Suite::Diamond => "♦",
-            Suite::Heart => "♥",
-            Suite::Spade => "♠",
-        };
-
-        let val = match self.value {
-            Value::Two => "2",
-            Value::Three => "3",
-            Value::Four => "4",
-            Value::Five => "5",
-            Value::Six => "6",
-            Value::Seven => "7",
-            Value::Eight => "8",
-            Value::Nine => "9",
-            Value::Ten => "10",
-            Value::Jack => "J",
-            Value::Queen => "Q",
-            Value::King => "K",
-            Value::Ace => "A",
-        };
-
-        write!(f, "{}{}", val, suite)
-    }
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Awaiting {
+  Shuffle { seat: usize },
+  RevealToken { seat: usize, card: usize },
+  Action { seat: usize },
 }
 
-#[derive(Clone)]
-struct Player {
-    name: Vec<u8>,
-    sk: SecretKey,
-    pk: PublicKey,
-    proof_key: ProofKeyOwnership,
-    cards: Vec<MaskedCard>,
-    opened_cards: Vec<Option<ClassicPlayingCard>>,
+pub struct HoldemHand<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+  joint_pk: PublicKey,
+  public_keys: Vec<PublicKey>,
+  button: usize,
+  config: HoldemConfig,
+  phase: Phase,
+  shuffles: usize,
+  deck: Vec<MaskedCard>,
+  // Reveal tokens received for each card, indexed by card then seat
+  tokens: Vec<Vec<Option<RevealShare>>>,
+  opened: Vec<Option<ClassicPlayingCard>>,
+  betting: Betting,
+  // Seats still in the hand at showdown, in showing order
+  showdown_order: Vec<usize>,
+  hands: Vec<Option<HandRank>>,
+  lows: Vec<Option<LowRank>>,
+  mucked: Vec<bool>,
+  payouts: Option<Vec<u64>>,
 }
 
-impl Player {
-    pub fn new<R: Rng>(rng: &mut R, pp: &CardParameters, name: &Vec<u8>) -> anyhow::Result<Self> {
-        let (pk, sk) = CardProtocol::player_keygen(rng, pp)?;
-        let proof_key = CardProtocol::prove_key_ownership(rng, pp, &pk, &sk, name)?;
-        Ok(Self {
-            name: name.clone(),
-            sk,
-            pk,
-            proof_key,
-            cards: vec![],
-            opened_cards: vec![],
-        })
+impl<'a> HoldemHand<'a> {
+  /// Start a hand between `players`, listed in seat order with their proofs of key ownership.
+  /// The initial deck masks every card of `card_mapping` under the aggregate key, in card order,
+  /// so that all players build the same one.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    stacks: Vec<u64>,
+    button: usize,
+    config: HoldemConfig,
+  ) -> anyhow::Result<Self> {
+    let num_of_players = players.len();
+    let cards = deck_order(card_mapping);
+    let expected = config.variant.deck();
+    if !cards
+      .iter()
+      .map(|(_, classic)| **classic)
+      .eq(expected.iter().copied())
+    {
+      return Err(GameErrors::WrongDeck(expected.len() as u32).into());
     }
-
-    pub fn receive_card(&mut self, card: MaskedCard) {
-        self.cards.push(card);
-        self.opened_cards.push(None);
+    if num_of_players < 2 || config.variant.cards_needed(num_of_players) > card_mapping.len() {
+      return Err(GameErrors::InvalidPlayerCount(num_of_players as u32).into());
     }
-
-    pub fn peek_at_card(
-        &mut self,
-        parameters: &CardParameters,
-        reveal_tokens: &mut Vec<(RevealToken, RevealProof, PublicKey)>,
-        card_mappings: &HashMap<Card, ClassicPlayingCard>,
-        card: &MaskedCard,
-    ) -> Result<(), anyhow::Error> {
-        let i = self.cards.iter().position(|&x| x == *card);
-
-        let i = i.ok_or(GameErrors::CardNotFound)?;
-
-        //TODO add function to create that without the proof
-        let rng = &mut thread_rng();
-        let own_reveal_token = self.compute_reveal_token(rng, parameters, card)?;
-        reveal_tokens.push(own_reveal_token);
-
-        let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card)?;
-        let opened_card = card_mappings.get(&unmasked_card);
-        let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
-
-        self.opened_cards[i] = Some(*opened_card);
-        Ok(())
+    if stacks.len() != num_of_players {
+      return Err(GameErrors::WrongSeatCount(num_of_players as u32).into());
     }
-
-    pub fn compute_reveal_token<R: Rng>(
-        &self,
-        rng: &mut R,
-        pp: &CardParameters,
-        card: &MaskedCard,
-    ) -> anyhow::Result<(RevealToken, RevealProof, PublicKey)> {
-        let (reveal_token, reveal_proof) =
-            CardProtocol::compute_reveal_token(rng, &pp, &self.sk, &self.pk, card)?;
-
-        Ok((reveal_token, reveal_proof, self.pk))
+    if button >= num_of_players {
+      return Err(GameErrors::InvalidSeat(button as u32).into());
     }
-}
-
-//Every player will have to calculate this function for cards that are in play
-pub fn open_card(
-    parameters: &CardParameters,
-    reveal_tokens: &Vec<(RevealToken, RevealProof, PublicKey)>,
-    card_mappings: &HashMap<Card, ClassicPlayingCard>,
-    card: &MaskedCard,
-) -> Result<ClassicPlayingCard, anyhow::Error> {
-    let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card)?;
-    let opened_card = card_mappings.get(&unmasked_card);
-    let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
-
-    Ok(*opened_card)
-}
 
-fn encode_cards<R: Rng>(rng: &mut R, num_of_cards: usize) -> HashMap<Card, ClassicPlayingCard> {
-    let mut map: HashMap<Card, ClassicPlayingCard> = HashMap::new();
-    let plaintexts = (0..num_of_cards)
-        .map(|_| Card::rand(rng))
-        .collect::<Vec<_>>();
-
-    let mut i = 0;
-    for value in Value::VALUES.iter().copied() {
-        for suite in Suite::VALUES.iter().copied() {
-            let current_card = ClassicPlayingCard::new(value, suite);
-            map.insert(plaintexts[i], current_card);
-            i += 1;
-        }
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &players.to_vec())?;
+
+    let deck = cards
+      .into_iter()
+      .map(|(card, _)| {
+        CardProtocol::mask(rng, parameters, &joint_pk, card, &Scalar::one())
+          .map(|(masked, _)| masked)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let num_of_cards = deck.len();
+    Ok(Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      public_keys: players.iter().map(|(pk, _, _)| *pk).collect(),
+      button,
+      config,
+      phase: Phase::Shuffle,
+      shuffles: 0,
+      deck,
+      tokens: vec![vec![None; num_of_players]; num_of_cards],
+      opened: vec![None; num_of_cards],
+      betting: Betting::new(stacks, config.limit, config.big_blind),
+      showdown_order: vec![],
+      hands: vec![None; num_of_players],
+      lows: vec![None; num_of_players],
+      mucked: vec![false; num_of_players],
+      payouts: None,
+    })
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.public_keys.len()
+  }
+
+  pub fn button(&self) -> usize {
+    self.button
+  }
+
+  pub fn betting(&self) -> &Betting {
+    &self.betting
+  }
+
+  pub fn deck(&self) -> &[MaskedCard] {
+    &self.deck
+  }
+
+  /// Seat that shuffles next. The first shuffler sits left of the button.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Shuffle => Some(self.seat_after_button(1 + self.shuffles)),
+      _ => None,
     }
-
-    map
-}
-
-fn main() -> anyhow::Result<()> {
-    let m = 2;
-    let n = 26;
-    let num_of_cards = m * n;
-    let rng = &mut thread_rng();
-
-    let parameters = CardProtocol::setup(rng, m, n)?;
-    let card_mapping = encode_cards(rng, num_of_cards);
-
-    let mut andrija = Player::new(rng, &parameters, &to_bytes![b"Andrija"].unwrap())?;
-    let mut kobi = Player::new(rng, &parameters, &to_bytes![b"Kobi"].unwrap())?;
-    let mut nico = Player::new(rng, &parameters, &to_bytes![b"Nico"].unwrap())?;
-    let mut tom = Player::new(rng, &parameters, &to_bytes![b"Tom"].unwrap())?;
-
-    let players = vec![andrija.clone(), kobi.clone(), nico.clone(), tom.clone()];
-
-    // Crear una variable para las cartas comunitarias
-    let mut community_cards: Vec<MaskedCard> = Vec::new();
-    
-    let key_proof_info = players
-        .iter()
-        .map(|p| (p.pk, p.proof_key, p.name.clone()))
-        .collect::<Vec<_>>();
-
-    // Each player should run this computation. Alternatively, it can be ran by a smart contract
-    let joint_pk = CardProtocol::compute_aggregate_key(&parameters, &key_proof_info)?;
-
-    // Each player should run this computation and verify that all players agree on the initial deck
-    let deck_and_proofs: Vec<(MaskedCard, RemaskingProof)> = card_mapping
-        .keys()
-        .map(|card| CardProtocol::mask(rng, &parameters, &joint_pk, &card, &Scalar::one()))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let deck = deck_and_proofs
+  }
+
+  /// Everything the hand needs before it can move on.
+  pub fn awaiting(&self) -> Vec<Awaiting> {
+    match self.phase {
+      Phase::Shuffle => self
+        .next_shuffler()
+        .map(|seat| Awaiting::Shuffle { seat })
+        .into_iter()
+        .collect(),
+      Phase::Betting(_) => self
+        .betting
+        .to_act()
+        .map(|seat| Awaiting::Action { seat })
+        .into_iter()
+        .collect(),
+      Phase::Complete => vec![],
+      _ => self.awaited_tokens(),
+    }
+  }
+
+  /// Positions in the deck of the hole cards dealt to `seat`.
+  pub fn hole_cards(&self, seat: usize) -> Vec<usize> {
+    let n = self.num_of_players();
+    let offset = (seat + n - self.button - 1) % n;
+    (0..self.config.variant.hole_cards())
+      .map(|round| round * n + offset)
+      .collect()
+  }
+
+  /// Positions in the deck of the board cards dealt on `street`.
+  pub fn board_cards(&self, street: Street) -> Vec<usize> {
+    // Hole cards come first, then a burn card before each street
+    let start = self.config.variant.hole_cards() * self.num_of_players();
+    match street {
+      Street::Preflop => vec![],
+      Street::Flop => (start + 1..start + 4).collect(),
+      Street::Turn => vec![start + 5],
+      Street::River => vec![start + 7],
+    }
+  }
+
+  /// The board cards opened so far.
+  pub fn board(&self) -> Vec<ClassicPlayingCard> {
+    [Street::Flop, Street::Turn, Street::River]
+      .iter()
+      .flat_map(|street| self.board_cards(*street))
+      .filter_map(|index| self.opened[index])
+      .collect()
+  }
+
+  pub fn opened_card(&self, index: usize) -> Option<ClassicPlayingCard> {
+    self.opened.get(index).copied().flatten()
+  }
+
+  /// Verified reveal tokens received so far for the card at `index`. Once the deal is done,
+  /// the owner of a hole card combines them with its own token to peek at it.
+  pub fn reveal_tokens(&self, index: usize) -> Vec<RevealShare> {
+    self
+      .tokens
+      .get(index)
+      .map(|tokens| tokens.iter().flatten().cloned().collect())
+      .unwrap_or_default()
+  }
+
+  /// Seat that shows or mucks next at showdown.
+  pub fn next_to_show(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Showdown => self
+        .showdown_order
         .iter()
-        .map(|x| x.0)
-        .collect::<Vec<MaskedCard>>();
-
-    
-        
-    // SHUFFLE TIME --------------
-    // 1.a Andrija shuffles first
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (a_shuffled_deck, a_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    // 1.b everyone checks!
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &deck,
-        &a_shuffled_deck,
-        &a_shuffle_proof,
-    )?;
-
-    //2.a Kobi shuffles second
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (k_shuffled_deck, k_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &a_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    //2.b Everyone checks
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &a_shuffled_deck,
-        &k_shuffled_deck,
-        &k_shuffle_proof,
-    )?;
+        .copied()
+        .find(|seat| self.hands[*seat].is_none() && !self.mucked[*seat]),
+      _ => None,
+    }
+  }
+
+  /// Rank of the hand shown by `seat`, `None` until it shows or if it folded or mucked.
+  pub fn shown_hand(&self, seat: usize) -> Option<HandRank> {
+    self.hands.get(seat).copied().flatten()
+  }
+
+  /// Eight-or-better low shown by `seat` in Omaha Hi/Lo, `None` if it has none.
+  pub fn shown_low(&self, seat: usize) -> Option<LowRank> {
+    self.lows.get(seat).copied().flatten()
+  }
+
+  pub fn has_mucked(&self, seat: usize) -> bool {
+    self.mucked.get(seat).copied().unwrap_or(false)
+  }
+
+  /// Whether `seat` may muck: every contested pot it could win must already have a hand shown
+  /// by another seat, which the mucked hand concedes to.
+  pub fn can_muck(&self, seat: usize) -> bool {
+    if self.next_to_show() != Some(seat) {
+      return false;
+    }
 
-    //3.a Nico shuffles third
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (n_shuffled_deck, n_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &k_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
+    let pots = pot::build_pots(&self.betting.contributions(), &self.folded());
+    let shown: Vec<bool> = self.hands.iter().map(Option::is_some).collect();
+    pot::may_muck(&pots, seat, &shown)
+  }
+
+  /// Chips won by every seat, once the hand is complete.
+  pub fn payouts(&self) -> Option<&[u64]> {
+    self.payouts.as_deref()
+  }
+
+  /// Shuffle and remask the current deck, as the next shuffler does locally before sending the
+  /// result to the others.
+  pub fn shuffle_deck<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let num_of_cards = self.deck.len();
+    let permutation = Permutation::new(rng, num_of_cards);
+    let masking_factors: Vec<Scalar> = sample_vector(rng, num_of_cards);
+
+    Ok(CardProtocol::shuffle_and_remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &masking_factors,
+      &permutation,
+    )?)
+  }
+
+  /// Verify and adopt the shuffle of `seat`.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
 
-    //3.b Everyone checks
     CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &k_shuffled_deck,
-        &n_shuffled_deck,
-        &n_shuffle_proof,
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &shuffled_deck,
+      shuffle_proof,
     )?;
 
-    //4.a Tom shuffles last
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (final_shuffled_deck, final_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &n_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
+    self.deck = shuffled_deck;
+    self.shuffles += 1;
+    self.advance()
+  }
+
+  /// Verify and record the reveal token of `seat` for the card at `index`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    index: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    let awaited = Awaiting::RevealToken { seat, card: index };
+    if !self.awaited_tokens().contains(&awaited) || token.2 != self.public_keys[seat] {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, index as u32).into());
+    }
 
-    //4.b Everyone checks before accepting last deck for game
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &n_shuffled_deck,
-        &final_shuffled_deck,
-        &final_shuffle_proof,
+    CardProtocol::verify_reveal(
+      self.parameters,
+      &token.2,
+      &token.0,
+      &self.deck[index],
+      &token.1,
     )?;
 
-    // CARDS ARE SHUFFLED. ROUND OF THE GAME CAN BEGIN
-    let deck = final_shuffled_deck;
-
-    // Each player receives two cards
-
-    andrija.receive_card(deck[0]);
-    andrija.receive_card(deck[1]);
-
-    kobi.receive_card(deck[2]);
-    kobi.receive_card(deck[3]);
+    self.tokens[index][seat] = Some(token);
+    self.advance()
+  }
 
-    nico.receive_card(deck[4]);
-    nico.receive_card(deck[5]);
-
-    tom.receive_card(deck[6]);
-    tom.receive_card(deck[7]);
-
-    // El flop: 3 cartas comunitarias
-    community_cards.push(deck[8]);
-    community_cards.push(deck[9]);
-    community_cards.push(deck[10]);
-
-    // El turn: 1 carta comunitaria
-    community_cards.push(deck[11]);
-
-    // El river: 1 carta comunitaria
-    community_cards.push(deck[12]);
+  pub fn act(&mut self, seat: usize, action: Action) -> anyhow::Result<()> {
+    if !matches!(self.phase, Phase::Betting(_)) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
 
+    self.betting.act(seat, action)?;
+    self.advance()
+  }
 
+  /// Concede the pot at showdown without opening the hole cards of `seat`.
+  pub fn muck(&mut self, seat: usize) -> anyhow::Result<()> {
+    if self.next_to_show() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    if !self.can_muck(seat) {
+      return Err(GameErrors::CannotMuck(seat as u32).into());
+    }
 
-    
-    // Flop: las primeras tres cartas comunitarias
-    let flop = vec![deck[8], deck[9], deck[10]];
-    let mut flop_reveal_tokens = Vec::new();
-    for card in &flop {
-        flop_reveal_tokens.push(andrija.compute_reveal_token(rng, &parameters, card)?);
-        flop_reveal_tokens.push(kobi.compute_reveal_token(rng, &parameters, card)?);
-        flop_reveal_tokens.push(nico.compute_reveal_token(rng, &parameters, card)?);
-        flop_reveal_tokens.push(tom.compute_reveal_token(rng, &parameters, card)?);
+    self.mucked[seat] = true;
+    self.advance()
+  }
+
+  fn seat_after_button(&self, offset: usize) -> usize {
+    (self.button + offset) % self.num_of_players()
+  }
+
+  fn awaited_tokens(&self) -> Vec<Awaiting> {
+    let n = self.num_of_players();
+    let mut awaited = vec![];
+    let mut wait_for = |card: usize, seats: &mut dyn Iterator<Item = usize>| {
+      for seat in seats {
+        if self.tokens[card][seat].is_none() {
+          awaited.push(Awaiting::RevealToken { seat, card });
+        }
+      }
+    };
+
+    match self.phase {
+      Phase::Deal => {
+        for owner in 0..n {
+          for card in self.hole_cards(owner) {
+            wait_for(card, &mut (0..n).filter(|seat| *seat != owner));
+          }
+        }
+      }
+      Phase::Reveal(street) => {
+        for card in self.board_cards(street) {
+          wait_for(card, &mut (0..n));
+        }
+      }
+      Phase::Showdown => {
+        // Only the seat whose turn it is to show, so that folded and mucked hands stay masked
+        if let Some(seat) = self.next_to_show() {
+          for card in self.hole_cards(seat) {
+            wait_for(card, &mut std::iter::once(seat));
+          }
+        }
+      }
+      _ => {}
     }
 
-    let mut flop_reveal_tokens1 = Vec::new();
-    flop_reveal_tokens1.push(andrija.compute_reveal_token(rng, &parameters, &deck[8])?);
-    flop_reveal_tokens1.push(kobi.compute_reveal_token(rng, &parameters, &deck[8])?);
-    flop_reveal_tokens1.push(nico.compute_reveal_token(rng, &parameters, &deck[8])?);
-    flop_reveal_tokens1.push(tom.compute_reveal_token(rng, &parameters, &deck[8])?);
+    awaited
+  }
 
-    let mut flop_reveal_tokens2 = Vec::new();
-    flop_reveal_tokens2.push(andrija.compute_reveal_token(rng, &parameters, &deck[9])?);
-    flop_reveal_tokens2.push(kobi.compute_reveal_token(rng, &parameters, &deck[9])?);
-    flop_reveal_tokens2.push(nico.compute_reveal_token(rng, &parameters, &deck[9])?);
-    flop_reveal_tokens2.push(tom.compute_reveal_token(rng, &parameters, &deck[9])?);
+  /// Move through every phase whose requirements are already met.
+  fn advance(&mut self) -> anyhow::Result<()> {
+    loop {
+      match self.phase {
+        Phase::Shuffle if self.shuffles == self.num_of_players() => {
+          self.phase = Phase::Deal;
+        }
+        Phase::Deal if self.awaited_tokens().is_empty() => {
+          self.start_preflop()?;
+        }
+        Phase::Betting(street) if self.betting.is_round_over() => {
+          if self.betting.live_seats().len() == 1 {
+            self.settle();
+            continue;
+          }
+          self.phase = match street {
+            Street::Preflop => Phase::Reveal(Street::Flop),
+            Street::Flop => Phase::Reveal(Street::Turn),
+            Street::Turn => Phase::Reveal(Street::River),
+            Street::River => {
+              self.showdown_order = self.showdown_order();
+              Phase::Showdown
+            }
+          };
+        }
+        Phase::Reveal(street) if self.awaited_tokens().is_empty() => {
+          for index in self.board_cards(street) {
+            self.open(index)?;
+          }
+          // After the flop, action starts left of the button
+          self.betting.start_round(street, self.seat_after_button(1));
+          self.phase = Phase::Betting(street);
+        }
+        Phase::Showdown if self.awaited_tokens().is_empty() => match self.next_to_show() {
+          Some(seat) => self.show(seat)?,
+          None => self.settle(),
+        },
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  fn start_preflop(&mut self) -> anyhow::Result<()> {
+    // Heads-up, the button posts the small blind and acts first before the flop
+    let (small_blind, big_blind) = if self.num_of_players() == 2 {
+      (self.button, self.seat_after_button(1))
+    } else {
+      (self.seat_after_button(1), self.seat_after_button(2))
+    };
+    let first_to_act = (big_blind + 1) % self.num_of_players();
+
+    self.betting.start_round(Street::Preflop, first_to_act);
+    self
+      .betting
+      .post_blind(small_blind, self.config.small_blind)?;
+    self.betting.post_blind(big_blind, self.config.big_blind)?;
+    self.phase = Phase::Betting(Street::Preflop);
+    Ok(())
+  }
+
+  fn open(&mut self, index: usize) -> anyhow::Result<ClassicPlayingCard> {
+    let tokens = self.reveal_tokens(index);
+    let card = open_card(
+      self.parameters,
+      &tokens,
+      self.card_mapping,
+      &self.deck[index],
+    )?;
+    self.opened[index] = Some(card);
+    Ok(card)
+  }
+
+  /// The last aggressor on the river shows first. Without a river bet, showing starts left of
+  /// the button.
+  fn showdown_order(&self) -> Vec<usize> {
+    let n = self.num_of_players();
+    let live = self.betting.live_seats();
+    let first = self
+      .betting
+      .last_aggressor()
+      .filter(|seat| live.contains(seat))
+      .unwrap_or_else(|| self.seat_after_button(1));
+
+    (0..n)
+      .map(|offset| (first + offset) % n)
+      .filter(|seat| live.contains(seat))
+      .collect()
+  }
+
+  fn show(&mut self, seat: usize) -> anyhow::Result<()> {
+    let board = self.board();
+    let mut hole = vec![];
+    for index in self.hole_cards(seat) {
+      hole.push(self.open(index)?);
+    }
 
-    let mut flop_reveal_tokens3 = Vec::new();
-    flop_reveal_tokens3.push(andrija.compute_reveal_token(rng, &parameters, &deck[10])?);
-    flop_reveal_tokens3.push(kobi.compute_reveal_token(rng, &parameters, &deck[10])?);
-    flop_reveal_tokens3.push(nico.compute_reveal_token(rng, &parameters, &deck[10])?);
-    flop_reveal_tokens3.push(tom.compute_reveal_token(rng, &parameters, &deck[10])?);
+    match self.config.variant {
+      Variant::Holdem => {
+        self.hands[seat] = Some(evaluator::evaluate(&[hole, board].concat())?);
+      }
+      Variant::ShortDeck => {
+        self.hands[seat] = Some(evaluator::evaluate_short_deck(&[hole, board].concat())?);
+      }
+      Variant::Omaha => {
+        self.hands[seat] = Some(evaluator::evaluate_omaha(&hole, &board)?);
+      }
+      Variant::OmahaHiLo => {
+        self.hands[seat] = Some(evaluator::evaluate_omaha(&hole, &board)?);
+        self.lows[seat] = evaluator::evaluate_omaha_low(&hole, &board)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn folded(&self) -> Vec<bool> {
+    (0..self.num_of_players())
+      .map(|seat| self.betting.status(seat) == SeatStatus::Folded)
+      .collect()
+  }
+
+  /// Award the pots to the hands shown down, or to the last seat left if everyone else folded.
+  fn settle(&mut self) {
+    let pots = pot::build_pots(&self.betting.contributions(), &self.folded());
+    let payouts = match self.config.variant {
+      Variant::OmahaHiLo => pot::distribute_hi_lo(&pots, &self.hands, &self.lows, self.button),
+      _ => pot::distribute(&pots, &self.hands, self.button),
+    };
+
+    for (seat, payout) in payouts.iter().enumerate() {
+      self.betting.award(seat, *payout);
+    }
+    self.payouts = Some(payouts);
+    self.phase = Phase::Complete;
+  }
+}
 
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{encode_cards, encode_deck, Player};
+
+  use rand::thread_rng;
+
+  fn setup<R: Rng>(
+    rng: &mut R,
+    names: &[&str],
+  ) -> (
+    CardParameters,
+    HashMap<Card, ClassicPlayingCard>,
+    Vec<Player>,
+  ) {
+    let parameters = CardProtocol::setup(rng, 2, 26).unwrap();
+    let card_mapping = encode_cards(rng, 52);
+    let players = names
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    (parameters, card_mapping, players)
+  }
+
+  fn key_info(players: &[Player]) -> Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)> {
+    players
+      .iter()
+      .map(|p| (p.pk, p.proof_key, p.name.clone()))
+      .collect()
+  }
+
+  /// Hand out every shuffle and reveal token the hand is waiting for, until it needs a betting
+  /// action or is complete.
+  fn run_protocol<R: Rng>(rng: &mut R, hand: &mut HoldemHand, players: &[Player]) {
+    loop {
+      match hand.awaiting().first().copied() {
+        Some(Awaiting::Shuffle { seat }) => {
+          let (deck, proof) = hand.shuffle_deck(rng).unwrap();
+          hand.shuffle(seat, deck, &proof).unwrap();
+        }
+        Some(Awaiting::RevealToken { seat, card }) => {
+          let token = players[seat]
+            .compute_reveal_token(rng, hand.parameters, &hand.deck()[card])
+            .unwrap();
+          hand.add_reveal_token(seat, card, token).unwrap();
+        }
+        _ => return,
+      }
+    }
+  }
 
+  #[test]
+  fn plays_a_hand_to_showdown() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, mut players) = setup(rng, &["Andrija", "Kobi", "Nico", "Tom"]);
+    let config = HoldemConfig {
+      variant: Variant::Holdem,
+      small_blind: 1,
+      big_blind: 2,
+      limit: BettingLimit::NoLimit,
+    };
+    let mut hand = HoldemHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100, 100, 50],
+      0,
+      config,
+    )
+    .unwrap();
+
+    assert_eq!(hand.awaiting(), vec![Awaiting::Shuffle { seat: 1 }]);
+    let (deck, proof) = hand.shuffle_deck(rng).unwrap();
+    assert!(hand.shuffle(0, deck, &proof).is_err());
+
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Betting(Street::Preflop));
+    assert_eq!(hand.hole_cards(1), vec![0, 4]);
+    assert_eq!(hand.hole_cards(0), vec![3, 7]);
+
+    // Every player privately peeks at its hole cards
+    for (seat, player) in players.iter_mut().enumerate() {
+      for index in hand.hole_cards(seat) {
+        let card = hand.deck()[index];
+        player.receive_card(card);
+        let mut tokens = hand.reveal_tokens(index);
+        assert_eq!(tokens.len(), 3);
+        player
+          .peek_at_card(rng, &parameters, &mut tokens, &card_mapping, &card)
+          .unwrap();
+      }
+      assert!(player.opened_cards.iter().all(|card| card.is_some()));
+    }
+    assert_eq!(hand.opened_card(0), None);
+
+    // Preflop: the small stack goes all-in and everyone calls
+    assert_eq!(hand.awaiting(), vec![Awaiting::Action { seat: 3 }]);
+    hand.act(3, Action::AllIn).unwrap();
+    hand.act(0, Action::Call).unwrap();
+    hand.act(1, Action::Call).unwrap();
+    hand.act(2, Action::Call).unwrap();
+
+    // Flop, turn and river are checked down between the three seats left with chips
+    for street in [Street::Flop, Street::Turn, Street::River] {
+      assert_eq!(hand.phase(), Phase::Reveal(street));
+      run_protocol(rng, &mut hand, &players);
+      assert_eq!(hand.phase(), Phase::Betting(street));
+      for seat in [1, 2, 0] {
+        hand.act(seat, Action::Check).unwrap();
+      }
+    }
+    assert_eq!(hand.board().len(), 5);
+    // Burn cards stay masked
+    assert_eq!(hand.opened_card(8), None);
+
+    // Without a river bet, showing starts left of the button
+    assert_eq!(hand.phase(), Phase::Showdown);
+    assert_eq!(hand.next_to_show(), Some(1));
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+    assert!((0..4).all(|seat| hand.shown_hand(seat).is_some()));
+
+    let payouts = hand.payouts().unwrap();
+    assert_eq!(payouts.iter().sum::<u64>(), 200);
+    // The all-in seat can win at most the main pot
+    assert!(payouts[3] <= 200);
+    assert_eq!(hand.betting().stacks().iter().sum::<u64>(), 350);
+  }
+
+  #[test]
+  fn folded_and_mucked_hands_stay_masked() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, &["Andrija", "Kobi", "Nico", "Tom"]);
+    let config = HoldemConfig {
+      variant: Variant::Holdem,
+      small_blind: 1,
+      big_blind: 2,
+      limit: BettingLimit::NoLimit,
+    };
+    let mut hand = HoldemHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100, 100, 100],
+      0,
+      config,
+    )
+    .unwrap();
+
+    run_protocol(rng, &mut hand, &players);
+    hand.act(3, Action::Fold).unwrap();
+    hand.act(0, Action::Call).unwrap();
+    hand.act(1, Action::Call).unwrap();
+    hand.act(2, Action::Check).unwrap();
+
+    // The folded seat still hands out its reveal tokens for the board
+    assert_eq!(hand.phase(), Phase::Reveal(Street::Flop));
+    assert!(hand
+      .awaiting()
+      .contains(&Awaiting::RevealToken { seat: 3, card: 9 }));
+
+    for street in [Street::Flop, Street::Turn] {
+      run_protocol(rng, &mut hand, &players);
+      assert_eq!(hand.phase(), Phase::Betting(street));
+      for seat in [1, 2, 0] {
+        hand.act(seat, Action::Check).unwrap();
+      }
+    }
+    run_protocol(rng, &mut hand, &players);
+    hand.act(1, Action::Check).unwrap();
+    hand.act(2, Action::Bet(10)).unwrap();
+    hand.act(0, Action::Call).unwrap();
+    hand.act(1, Action::Call).unwrap();
+
+    // The river bettor shows first and cannot muck, the others follow clockwise
+    assert_eq!(hand.phase(), Phase::Showdown);
+    assert_eq!(hand.next_to_show(), Some(2));
+    assert!(!hand.can_muck(2));
+    assert!(hand.muck(2).is_err());
+    assert_eq!(
+      hand.awaiting(),
+      hand
+        .hole_cards(2)
+        .into_iter()
+        .map(|card| Awaiting::RevealToken { seat: 2, card })
+        .collect::<Vec<_>>()
+    );
+    assert!(hand.muck(0).is_err());
+    run_protocol(rng, &mut hand, &players);
+    assert!(hand.shown_hand(2).is_some());
+
+    assert_eq!(hand.next_to_show(), Some(0));
+    assert!(hand.can_muck(0));
+    hand.muck(0).unwrap();
+    assert!(hand.has_mucked(0));
+
+    assert_eq!(hand.next_to_show(), Some(1));
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+
+    // Neither the folded nor the mucked hole cards were ever opened
+    for seat in [0, 3] {
+      for index in hand.hole_cards(seat) {
+        assert_eq!(hand.opened_card(index), None);
+        assert_eq!(hand.reveal_tokens(index).len(), 3);
+      }
+      assert_eq!(hand.shown_hand(seat), None);
+    }
 
+    let payouts = hand.payouts().unwrap();
+    assert_eq!(payouts[0], 0);
+    assert_eq!(payouts[3], 0);
+    assert_eq!(payouts[1] + payouts[2], 36);
+    let best = hand.shown_hand(1).max(hand.shown_hand(2));
+    for seat in [1, 2] {
+      assert_eq!(payouts[seat] > 0, hand.shown_hand(seat) == best);
+    }
+  }
 
-    // Turn: la cuarta carta comunitaria
-    let turn = vec![deck[11]];
-    let mut turn_reveal_tokens = Vec::new();
-    for card in &turn {
-        turn_reveal_tokens.push(andrija.compute_reveal_token(rng, &parameters, card)?);
-        turn_reveal_tokens.push(kobi.compute_reveal_token(rng, &parameters, card)?);
-        turn_reveal_tokens.push(nico.compute_reveal_token(rng, &parameters, card)?);
-        turn_reveal_tokens.push(tom.compute_reveal_token(rng, &parameters, card)?);
+  #[test]
+  fn plays_pot_limit_omaha_hi_lo() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, &["Andrija", "Kobi", "Nico"]);
+    let config = HoldemConfig {
+      variant: Variant::OmahaHiLo,
+      small_blind: 1,
+      big_blind: 2,
+      limit: BettingLimit::PotLimit,
+    };
+    let mut hand = HoldemHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100, 100],
+      0,
+      config,
+    )
+    .unwrap();
+
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.hole_cards(1), vec![0, 3, 6, 9]);
+    assert_eq!(hand.hole_cards(0), vec![2, 5, 8, 11]);
+    assert_eq!(hand.board_cards(Street::Flop), vec![13, 14, 15]);
+
+    // Pot-limit: calling the big blind then raising the pot of 5 makes it 7
+    assert_eq!(hand.betting().max_raise_to(0), 7);
+    assert!(hand.act(0, Action::Raise(8)).is_err());
+    hand.act(0, Action::Raise(7)).unwrap();
+    hand.act(1, Action::Call).unwrap();
+    hand.act(2, Action::Call).unwrap();
+
+    for street in [Street::Flop, Street::Turn, Street::River] {
+      run_protocol(rng, &mut hand, &players);
+      assert_eq!(hand.phase(), Phase::Betting(street));
+      for seat in [1, 2, 0] {
+        hand.act(seat, Action::Check).unwrap();
+      }
+    }
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+
+    // Every hand plays exactly two of its four hole cards
+    let board = hand.board();
+    let mut highs = vec![];
+    let mut lows = vec![];
+    for seat in 0..3 {
+      let hole: Vec<ClassicPlayingCard> = hand
+        .hole_cards(seat)
+        .into_iter()
+        .map(|index| hand.opened_card(index).unwrap())
+        .collect();
+      highs.push(Some(evaluator::evaluate_omaha(&hole, &board).unwrap()));
+      lows.push(evaluator::evaluate_omaha_low(&hole, &board).unwrap());
+      assert_eq!(hand.shown_hand(seat), highs[seat]);
+      assert_eq!(hand.shown_low(seat), lows[seat]);
     }
 
+    let pots = pot::build_pots(&[7, 7, 7], &[false; 3]);
+    let payouts = hand.payouts().unwrap();
+    assert_eq!(payouts, pot::distribute_hi_lo(&pots, &highs, &lows, 0));
+    assert_eq!(payouts.iter().sum::<u64>(), 21);
+  }
 
-    // River: la quinta carta comunitaria
-    let river = vec![deck[12]];
-    let mut river_reveal_tokens = Vec::new();
-    for card in &river {
-        river_reveal_tokens.push(andrija.compute_reveal_token(rng, &parameters, card)?);
-        river_reveal_tokens.push(kobi.compute_reveal_token(rng, &parameters, card)?);
-        river_reveal_tokens.push(nico.compute_reveal_token(rng, &parameters, card)?);
-        river_reveal_tokens.push(tom.compute_reveal_token(rng, &parameters, card)?);
+  #[test]
+  fn plays_short_deck_on_its_own_parameters() {
+    let rng = &mut thread_rng();
+    let (m, n) = Variant::ShortDeck.protocol_shape();
+    assert_eq!(m * n, 36);
+    let parameters = CardProtocol::setup(rng, m, n).unwrap();
+    let card_mapping = encode_deck(rng, &Variant::ShortDeck.deck());
+    let players: Vec<Player> = ["Andrija", "Kobi"]
+      .iter()
+      .map(|name| Player::new(rng, &parameters, &name.as_bytes().to_vec()).unwrap())
+      .collect();
+    let config = HoldemConfig {
+      variant: Variant::ShortDeck,
+      small_blind: 1,
+      big_blind: 2,
+      limit: BettingLimit::NoLimit,
+    };
+
+    let full_deck = encode_cards(rng, 52);
+    assert!(HoldemHand::new(
+      rng,
+      &parameters,
+      &full_deck,
+      &key_info(&players),
+      vec![100, 100],
+      0,
+      config,
+    )
+    .is_err());
+
+    let mut hand = HoldemHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100],
+      0,
+      config,
+    )
+    .unwrap();
+    assert_eq!(hand.deck().len(), 36);
+
+    run_protocol(rng, &mut hand, &players);
+    hand.act(0, Action::Call).unwrap();
+    hand.act(1, Action::Check).unwrap();
+    for street in [Street::Flop, Street::Turn, Street::River] {
+      run_protocol(rng, &mut hand, &players);
+      assert_eq!(hand.phase(), Phase::Betting(street));
+      hand.act(1, Action::Check).unwrap();
+      hand.act(0, Action::Check).unwrap();
     }
+    run_protocol(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+
+    for seat in 0..2 {
+      let mut cards = hand.board();
+      for index in hand.hole_cards(seat) {
+        let card = hand.opened_card(index).unwrap();
+        assert!(card.value >= Value::Six);
+        cards.push(card);
+      }
+      assert_eq!(
+        hand.shown_hand(seat),
+        Some(evaluator::evaluate_short_deck(&cards).unwrap())
+      );
+    }
+    assert_eq!(hand.payouts().unwrap().iter().sum::<u64>(), 4);
+  }
 
-    let andrija_rt_2 = andrija.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let andrija_rt_3 = andrija.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let andrija_rt_4 = andrija.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let andrija_rt_5 = andrija.compute_reveal_token(rng, &parameters, &deck[5])?;
-    let andrija_rt_6 = andrija.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let andrija_rt_7 = andrija.compute_reveal_token(rng, &parameters, &deck[7])?;
-    
-    let kobi_rt_0 = kobi.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let kobi_rt_1 = kobi.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let kobi_rt_4 = kobi.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let kobi_rt_5 = kobi.compute_reveal_token(rng, &parameters, &deck[5])?;
-    let kobi_rt_6 = kobi.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let kobi_rt_7 = kobi.compute_reveal_token(rng, &parameters, &deck[7])?;
-
-    let nico_rt_0 = nico.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let nico_rt_1 = nico.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let nico_rt_2 = nico.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let nico_rt_3 = nico.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let nico_rt_6 = nico.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let nico_rt_7 = nico.compute_reveal_token(rng, &parameters, &deck[7])?;
-
-    let tom_rt_0 = tom.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let tom_rt_1 = tom.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let tom_rt_2 = tom.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let tom_rt_3 = tom.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let tom_rt_4 = tom.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let tom_rt_5 = tom.compute_reveal_token(rng, &parameters, &deck[5])?;
-
-    let mut rts_andrija0 = vec![kobi_rt_0, nico_rt_0, tom_rt_0];
-    let mut rts_andrija1 = vec![kobi_rt_1, nico_rt_1, tom_rt_1];
-    let mut rts_kobi2 = vec![andrija_rt_2, nico_rt_2, tom_rt_2];
-    let mut rts_kobi3 = vec![andrija_rt_3, nico_rt_3, tom_rt_3];
-    let mut rts_nico4 = vec![andrija_rt_4, kobi_rt_4, tom_rt_4];
-    let mut rts_nico5 = vec![andrija_rt_5, kobi_rt_5, tom_rt_5];
-    let mut rts_tom6 = vec![andrija_rt_6, kobi_rt_6, nico_rt_6];
-    let mut rts_tom7 = vec![andrija_rt_7, kobi_rt_7, nico_rt_7];
-
-    //At this moment players privately open their cards and only they know that values
-    andrija.peek_at_card(&parameters, &mut rts_andrija0, &card_mapping, &deck[0])?;
-    andrija.peek_at_card(&parameters, &mut rts_andrija1, &card_mapping, &deck[1])?;
-    kobi.peek_at_card(&parameters, &mut rts_kobi2, &card_mapping, &deck[2])?;
-    kobi.peek_at_card(&parameters, &mut rts_kobi3, &card_mapping, &deck[3])?;
-    nico.peek_at_card(&parameters, &mut rts_nico4, &card_mapping, &deck[4])?;
-    nico.peek_at_card(&parameters, &mut rts_nico5, &card_mapping, &deck[5])?;
-    tom.peek_at_card(&parameters, &mut rts_tom6, &card_mapping, &deck[6])?;
-    tom.peek_at_card(&parameters, &mut rts_tom7, &card_mapping, &deck[7])?;
-
-    /* Here we can add custom logic of a game:
-        1. swap card
-        2. place a bet
-        3. ...
-    */
-
-    //At this moment players reveal their cards to each other and everything becomes public
-
-    //1.a everyone reveals the secret for their card
-    let andrija_rt_0 = andrija.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let andrija_rt_1 = andrija.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let kobi_rt_2 = kobi.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let kobi_rt_3 = kobi.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let nico_rt_4 = nico.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let nico_rt_5 = nico.compute_reveal_token(rng, &parameters, &deck[5])?;
-    let tom_rt_6 = tom.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let tom_rt_7 = tom.compute_reveal_token(rng, &parameters, &deck[7])?;
-
-    //2. tokens for all other cards are exchanged
-    //TODO add struct for this so that we can just clone
-
-    let andrija_rt_2 = andrija.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let andrija_rt_3 = andrija.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let andrija_rt_4 = andrija.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let andrija_rt_5 = andrija.compute_reveal_token(rng, &parameters, &deck[5])?;
-    let andrija_rt_6 = andrija.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let andrija_rt_7 = andrija.compute_reveal_token(rng, &parameters, &deck[7])?;
-    
-    let kobi_rt_0 = kobi.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let kobi_rt_1 = kobi.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let kobi_rt_4 = kobi.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let kobi_rt_5 = kobi.compute_reveal_token(rng, &parameters, &deck[5])?;
-    let kobi_rt_6 = kobi.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let kobi_rt_7 = kobi.compute_reveal_token(rng, &parameters, &deck[7])?;
-
-    let nico_rt_0 = nico.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let nico_rt_1 = nico.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let nico_rt_2 = nico.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let nico_rt_3 = nico.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let nico_rt_6 = nico.compute_reveal_token(rng, &parameters, &deck[6])?;
-    let nico_rt_7 = nico.compute_reveal_token(rng, &parameters, &deck[7])?;
-
-    let tom_rt_0 = tom.compute_reveal_token(rng, &parameters, &deck[0])?;
-    let tom_rt_1 = tom.compute_reveal_token(rng, &parameters, &deck[1])?;
-    let tom_rt_2 = tom.compute_reveal_token(rng, &parameters, &deck[2])?;
-    let tom_rt_3 = tom.compute_reveal_token(rng, &parameters, &deck[3])?;
-    let tom_rt_4 = tom.compute_reveal_token(rng, &parameters, &deck[4])?;
-    let tom_rt_5 = tom.compute_reveal_token(rng, &parameters, &deck[5])?;
-
-    let rt_0 = vec![andrija_rt_0, kobi_rt_0, nico_rt_0, tom_rt_0];
-    let rt_1 = vec![andrija_rt_1, kobi_rt_1, nico_rt_1, tom_rt_1];
-    let rt_2 = vec![andrija_rt_2, kobi_rt_2, nico_rt_2, tom_rt_2];
-    let rt_3 = vec![andrija_rt_3, kobi_rt_3, nico_rt_3, tom_rt_3];
-    let rt_4 = vec![andrija_rt_4, kobi_rt_4, nico_rt_4, tom_rt_4];
-    let rt_5 = vec![andrija_rt_5, kobi_rt_5, nico_rt_5, tom_rt_5];
-    let rt_6 = vec![andrija_rt_6, kobi_rt_6, nico_rt_6, tom_rt_6];
-    let rt_7 = vec![andrija_rt_7, kobi_rt_7, nico_rt_7, tom_rt_7];
-
-    //Everyone computes for each card (except for their own card):
-    let andrija_card1 = open_card(&parameters, &rt_0, &card_mapping, &deck[0])?;
-    let andrija_card2 = open_card(&parameters, &rt_1, &card_mapping, &deck[1])?;
-    let kobi_card1 = open_card(&parameters, &rt_2, &card_mapping, &deck[2])?;
-    let kobi_card2 = open_card(&parameters, &rt_3, &card_mapping, &deck[3])?;
-    let nico_card1 = open_card(&parameters, &rt_4, &card_mapping, &deck[4])?;
-    let nico_card2 = open_card(&parameters, &rt_5, &card_mapping, &deck[5])?;
-    let tom_card1 = open_card(&parameters, &rt_6, &card_mapping, &deck[6])?;
-    let tom_card2 = open_card(&parameters, &rt_7, &card_mapping, &deck[7])?;
-    
-    let cm_card1 = open_card(&parameters, &flop_reveal_tokens1, &card_mapping, &deck[8])?;
-    let cm_card2 = open_card(&parameters, &flop_reveal_tokens2, &card_mapping, &deck[9])?;
-    let cm_card3 = open_card(&parameters, &flop_reveal_tokens3, &card_mapping, &deck[10])?;
-    let cm_card4 = open_card(&parameters, &turn_reveal_tokens, &card_mapping, &deck[11])?;
-    let cm_card5 = open_card(&parameters, &river_reveal_tokens, &card_mapping, &deck[12])?;
-
-    println!("Andrija cards: {:?}, {:?}", andrija_card1, andrija_card2);
-    println!("Kobi cards: {:?}, {:?}", kobi_card1, kobi_card2);
-    println!("Nico cards: {:?}, {:?}", nico_card1, nico_card2);
-    println!("Tom cards: {:?}, {:?}", tom_card1, tom_card2);
-    
-    println!("Community Cards: {:?} {:?} {:?} {:?} {:?}", cm_card1, cm_card2, cm_card3, cm_card4, cm_card5);
-    // println!("Community Cards: {:?} {:?}", cm_card4, cm_card5);
-
-    Ok(())
+  #[test]
+  fn ends_when_everyone_else_folds() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, &["Andrija", "Kobi"]);
+    let config = HoldemConfig {
+      variant: Variant::Holdem,
+      small_blind: 1,
+      big_blind: 2,
+      limit: BettingLimit::NoLimit,
+    };
+    let mut hand = HoldemHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      vec![100, 100],
+      1,
+      config,
+    )
+    .unwrap();
+
+    run_protocol(rng, &mut hand, &players);
+    // Heads-up, the button posts the small blind and acts first
+    assert_eq!(hand.awaiting(), vec![Awaiting::Action { seat: 1 }]);
+    hand.act(1, Action::Raise(6)).unwrap();
+    hand.act(0, Action::Fold).unwrap();
+
+    assert_eq!(hand.phase(), Phase::Complete);
+    assert_eq!(hand.payouts().unwrap(), &[0, 8]);
+    assert_eq!(hand.betting().stacks(), vec![98, 102]);
+    assert!(hand.board().is_empty());
+  }
 }
@@ -0,0 +1,1009 @@
+//! Trick-taking games over the mental poker protocol: Hearts, Spades and Bridge.
+//!
+//! `TrickHand` is the public state of one deal that every player keeps in sync. Once the four
+//! players have shuffled the deck, it is dealt out face down through a `DrawDeck`, one card at a
+//! time starting left of the dealer, and every player peeks at its thirteen cards. A card is
+//! played by handing out its owner's reveal token for it, which opens it to everyone.
+//!
+//! Players must follow the suit led when they can. Since nobody else knows what is left in a
+//! hand, a player that plays another suit attaches an exclusion proof for every card it holds,
+//! showing that none of them is of the suit led without opening any. The highest trump, or else
+//! the highest card of the suit led, takes the trick and its winner leads the next one.
+//!
+//! The first trick is led from the left of the dealer in Hearts and Spades, and hearts may be
+//! led at any time. In Bridge the contract comes from an auction held outside of the hand: the
+//! seat left of the declarer leads, then the dummy hands out its own reveal tokens so that its
+//! hand lies open, and the declarer plays it.
+
+use crate::draw::DrawDeck;
+use crate::texas_holdem::Variant;
+use crate::zones::Zone;
+use crate::{
+  deck_order, open_card, Card, CardParameters, CardProtocol, ClassicPlayingCard, ExclusionProof,
+  GameErrors, MaskedCard, Player, ProofKeyOwnership, PublicKey, RevealShare, RevealToken, Scalar,
+  ShuffleProof, Suite, Value,
+};
+
+use ark_std::{rand::Rng, One};
+use barnett_smart_protocol::discrete_log_cards::exclusion;
+use barnett_smart_protocol::BarnettSmartProtocol;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use std::collections::HashMap;
+
+/// Hearts, Spades and Bridge are all played by four, partners sitting across in Spades and
+/// Bridge.
+pub const NUM_OF_PLAYERS: usize = 4;
+const TRICKS: usize = 13;
+
+/// A Bridge contract, as won in the auction.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct Contract {
+  /// Tricks bid over the first six, from 1 to 7.
+  pub level: u8,
+  /// `None` for no trump.
+  pub trump: Option<Suite>,
+  pub declarer: usize,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrickGame {
+  /// No trump. Every heart taken costs a point and the queen of spades thirteen, unless one
+  /// player takes them all and the others are charged 26 each.
+  Hearts,
+  /// Spades are trump and every partnership plays for its combined bid.
+  Spades,
+  /// Played for the contract, undoubled and not vulnerable.
+  Bridge(Contract),
+}
+
+impl TrickGame {
+  pub fn trump(&self) -> Option<Suite> {
+    match self {
+      Self::Hearts => None,
+      Self::Spades => Some(Suite::Spade),
+      Self::Bridge(contract) => contract.trump,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+  /// Every player shuffles and remasks the deck in turn.
+  Shuffle,
+  /// Every player hands out reveal tokens for the cards dealt to the others.
+  Deal,
+  /// Spades only: every player bids the tricks it expects to take, starting left of the dealer.
+  Bid,
+  Play,
+  Complete,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Awaiting {
+  Shuffle { seat: usize },
+  RevealToken { seat: usize, card: usize },
+  Bid { seat: usize },
+  Play { seat: usize },
+}
+
+pub struct TrickHand<'a> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+  joint_pk: PublicKey,
+  players: Vec<(PublicKey, ProofKeyOwnership, Vec<u8>)>,
+  dealer: usize,
+  game: TrickGame,
+  phase: Phase,
+  shuffles: usize,
+  // The deck being shuffled, until it is handed to `cards` for dealing
+  deck: Vec<MaskedCard>,
+  cards: Option<DrawDeck<'a>>,
+  bids: Vec<Option<u8>>,
+  // Seat that leads the current trick, and the ids of the cards played to it so far
+  leader: usize,
+  trick: Vec<usize>,
+  // Cards of the tricks taken by every seat
+  taken: Vec<Vec<ClassicPlayingCard>>,
+  scores: Option<Vec<i64>>,
+}
+
+impl<'a> TrickHand<'a> {
+  /// Start a deal of `game` between `players`, listed in seat order with their proofs of key
+  /// ownership. `card_mapping` must hold the 52 cards, masked in card order into the initial
+  /// deck as in `HoldemHand::new`.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    dealer: usize,
+    game: TrickGame,
+  ) -> anyhow::Result<Self> {
+    let cards = deck_order(card_mapping);
+    let expected = Variant::Holdem.deck();
+    if !cards
+      .iter()
+      .map(|(_, classic)| **classic)
+      .eq(expected.iter().copied())
+    {
+      return Err(GameErrors::WrongDeck(expected.len() as u32).into());
+    }
+    if players.len() != NUM_OF_PLAYERS {
+      return Err(GameErrors::InvalidPlayerCount(players.len() as u32).into());
+    }
+    if dealer >= NUM_OF_PLAYERS {
+      return Err(GameErrors::InvalidSeat(dealer as u32).into());
+    }
+    let leader = match game {
+      TrickGame::Bridge(contract) => {
+        if !(1..=7).contains(&contract.level) {
+          return Err(GameErrors::InvalidAmount(contract.level as u64, 1, 7).into());
+        }
+        if contract.declarer >= NUM_OF_PLAYERS {
+          return Err(GameErrors::InvalidSeat(contract.declarer as u32).into());
+        }
+        (contract.declarer + 1) % NUM_OF_PLAYERS
+      }
+      _ => (dealer + 1) % NUM_OF_PLAYERS,
+    };
+
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &players.to_vec())?;
+
+    let deck = cards
+      .into_iter()
+      .map(|(card, _)| {
+        CardProtocol::mask(rng, parameters, &joint_pk, card, &Scalar::one())
+          .map(|(masked, _)| masked)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      players: players.to_vec(),
+      dealer,
+      game,
+      phase: Phase::Shuffle,
+      shuffles: 0,
+      deck,
+      cards: None,
+      bids: vec![None; NUM_OF_PLAYERS],
+      leader,
+      trick: vec![],
+      taken: vec![vec![]; NUM_OF_PLAYERS],
+      scores: None,
+    })
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  pub fn dealer(&self) -> usize {
+    self.dealer
+  }
+
+  pub fn game(&self) -> TrickGame {
+    self.game
+  }
+
+  /// The cards in play, once every player has shuffled the deck. Players peek at their hand
+  /// with the reveal tokens it holds.
+  pub fn cards(&self) -> Option<&DrawDeck<'a>> {
+    self.cards.as_ref()
+  }
+
+  /// Seat that shuffles next. The first shuffler sits left of the dealer.
+  pub fn next_shuffler(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Shuffle => Some((self.dealer + 1 + self.shuffles) % NUM_OF_PLAYERS),
+      _ => None,
+    }
+  }
+
+  /// Everything the deal needs before it can move on. Reveal tokens are keyed by card id in
+  /// `cards`.
+  pub fn awaiting(&self) -> Vec<Awaiting> {
+    match self.phase {
+      Phase::Shuffle => self
+        .next_shuffler()
+        .map(|seat| Awaiting::Shuffle { seat })
+        .into_iter()
+        .collect(),
+      Phase::Bid => self
+        .next_bidder()
+        .map(|seat| Awaiting::Bid { seat })
+        .into_iter()
+        .collect(),
+      Phase::Play if self.awaited_tokens().is_empty() => self
+        .to_play()
+        .map(|seat| Awaiting::Play { seat })
+        .into_iter()
+        .collect(),
+      Phase::Complete => vec![],
+      _ => self.awaited_tokens(),
+    }
+  }
+
+  /// Tricks bid by every seat in Spades.
+  pub fn bids(&self) -> &[Option<u8>] {
+    &self.bids
+  }
+
+  /// Seat that plays the next card. The declarer plays for the dummy in Bridge.
+  pub fn to_play(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Play if self.awaited_tokens().is_empty() => {
+        Some((self.leader + self.trick.len()) % NUM_OF_PLAYERS)
+      }
+      _ => None,
+    }
+  }
+
+  /// The dummy in Bridge, the declarer's partner.
+  pub fn dummy(&self) -> Option<usize> {
+    match self.game {
+      TrickGame::Bridge(contract) => Some((contract.declarer + 2) % NUM_OF_PLAYERS),
+      _ => None,
+    }
+  }
+
+  /// The cards played to the current trick so far, with the seats that played them.
+  pub fn trick(&self) -> Vec<(usize, ClassicPlayingCard)> {
+    let cards = match &self.cards {
+      Some(cards) => cards,
+      None => return vec![],
+    };
+
+    self
+      .trick
+      .iter()
+      .enumerate()
+      .filter_map(|(i, id)| {
+        let seat = (self.leader + i) % NUM_OF_PLAYERS;
+        cards.opened_card(*id).map(|card| (seat, card))
+      })
+      .collect()
+  }
+
+  pub fn led_suit(&self) -> Option<Suite> {
+    self.trick().first().map(|(_, card)| card.suite)
+  }
+
+  pub fn tricks_won(&self, seat: usize) -> usize {
+    self
+      .taken
+      .get(seat)
+      .map_or(0, |cards| cards.len() / NUM_OF_PLAYERS)
+  }
+
+  /// Score of every seat, once the deal is complete: penalty points in Hearts, and the score of
+  /// the seat's partnership in Spades and Bridge. Bridge scores are the declaring side's
+  /// contract score, negated for the defenders.
+  pub fn scores(&self) -> Option<&[i64]> {
+    self.scores.as_deref()
+  }
+
+  /// Shuffle and remask the current deck, as the next shuffler does locally before sending the
+  /// result to the others.
+  pub fn shuffle_deck<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> anyhow::Result<(Vec<MaskedCard>, ShuffleProof)> {
+    let num_of_cards = self.deck.len();
+    let permutation = Permutation::new(rng, num_of_cards);
+    let masking_factors: Vec<Scalar> = sample_vector(rng, num_of_cards);
+
+    Ok(CardProtocol::shuffle_and_remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &masking_factors,
+      &permutation,
+    )?)
+  }
+
+  /// Verify and adopt the shuffle of `seat`.
+  pub fn shuffle(
+    &mut self,
+    seat: usize,
+    shuffled_deck: Vec<MaskedCard>,
+    shuffle_proof: &ShuffleProof,
+  ) -> anyhow::Result<()> {
+    if self.next_shuffler() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+
+    CardProtocol::verify_shuffle(
+      self.parameters,
+      &self.joint_pk,
+      &self.deck,
+      &shuffled_deck,
+      shuffle_proof,
+    )?;
+
+    self.deck = shuffled_deck;
+    self.shuffles += 1;
+    self.advance()
+  }
+
+  /// Verify and record the reveal token of `seat` for the card `id` of `cards`.
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    let awaited = Awaiting::RevealToken { seat, card: id };
+    if !self.awaited_tokens().contains(&awaited) {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+    }
+
+    self
+      .cards
+      .as_mut()
+      .unwrap()
+      .add_reveal_token(seat, id, token)?;
+    self.advance()
+  }
+
+  /// Bid the number of tricks `seat` expects to take in Spades, from 0 for nil to 13.
+  pub fn bid(&mut self, seat: usize, tricks: u8) -> anyhow::Result<()> {
+    if self.next_bidder() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    if tricks as usize > TRICKS {
+      return Err(GameErrors::InvalidAmount(tricks as u64, 0, TRICKS as u64).into());
+    }
+
+    self.bids[seat] = Some(tricks);
+    self.advance()
+  }
+
+  /// Prove, as `player` in `seat`, that none of the cards left in its hand is of the suit led,
+  /// so that it may play another suit.
+  pub fn prove_void<R: Rng>(
+    &self,
+    rng: &mut R,
+    player: &Player,
+    seat: usize,
+  ) -> anyhow::Result<Vec<ExclusionProof>> {
+    let suit = self
+      .led_suit()
+      .ok_or(GameErrors::IllegalAction("no suit has been led"))?;
+    let cards = self.cards.as_ref().unwrap();
+    let excluded = self.suit_cards(suit);
+
+    cards
+      .hand(seat)
+      .into_iter()
+      .map(|id| {
+        player.prove_exclusion(
+          rng,
+          self.parameters,
+          &cards.reveal_tokens(id),
+          cards.card(id).unwrap(),
+          &excluded,
+        )
+      })
+      .collect()
+  }
+
+  /// Play the card `id` from the hand of `seat` with the owner's reveal token for it, or without
+  /// one from the open hand of the dummy. A card off the suit led needs `void`, the proofs of
+  /// `prove_void`, unless the hand is open.
+  pub fn play(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: Option<RevealShare>,
+    void: Option<&[ExclusionProof]>,
+  ) -> anyhow::Result<()> {
+    if self.to_play() != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    let cards = self.cards.as_ref().unwrap();
+    if cards.zone(id) != Some(Zone::Hand(seat)) {
+      return Err(GameErrors::CardNotFound.into());
+    }
+
+    // Verify the owner's token and open the card before playing it, so that a bad token or a
+    // revoke is refused without changing the deal
+    let mut tokens = cards.reveal_tokens(id);
+    let own_token = token.is_some();
+    if let Some(token) = token {
+      if tokens.len() == NUM_OF_PLAYERS || token.2 != self.players[seat].0 {
+        return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+      }
+      CardProtocol::verify_reveal(
+        self.parameters,
+        &token.2,
+        &token.0,
+        cards.card(id).unwrap(),
+        &token.1,
+      )?;
+      tokens.push(token);
+    }
+    if tokens.len() < NUM_OF_PLAYERS {
+      return Err(GameErrors::CardNotOpened(id as u32).into());
+    }
+    let card = open_card(
+      self.parameters,
+      &tokens,
+      self.card_mapping,
+      cards.card(id).unwrap(),
+    )?;
+    if let Some(led) = self.led_suit() {
+      if card.suite != led {
+        self.check_void(seat, led, void)?;
+      }
+    }
+
+    let cards = self.cards.as_mut().unwrap();
+    cards.play(seat, id)?;
+    if own_token {
+      cards.add_reveal_token(seat, id, tokens.pop().unwrap())?;
+    }
+    self.trick.push(id);
+
+    // The dummy's hand opens once the opening lead is on the table
+    if let Some(dummy) = self.dummy() {
+      if self.trick.len() == 1 && self.taken.iter().all(Vec::is_empty) {
+        self.cards.as_mut().unwrap().show(dummy)?;
+      }
+    }
+    if self.trick.len() == NUM_OF_PLAYERS {
+      self.take_trick();
+    }
+    self.advance()
+  }
+
+  fn next_bidder(&self) -> Option<usize> {
+    match self.phase {
+      Phase::Bid => {
+        let bids = self.bids.iter().flatten().count();
+        Some((self.dealer + 1 + bids) % NUM_OF_PLAYERS)
+      }
+      _ => None,
+    }
+  }
+
+  fn awaited_tokens(&self) -> Vec<Awaiting> {
+    self
+      .cards
+      .as_ref()
+      .map(|cards| {
+        cards
+          .awaited_tokens()
+          .into_iter()
+          .map(|(seat, card)| Awaiting::RevealToken { seat, card })
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  /// The plaintexts of the cards of `suit`, in card order.
+  fn suit_cards(&self, suit: Suite) -> Vec<Card> {
+    deck_order(self.card_mapping)
+      .into_iter()
+      .filter(|(_, classic)| classic.suite == suit)
+      .map(|(card, _)| *card)
+      .collect()
+  }
+
+  /// Check that `seat` holds no card of `suit`, from its open hand or from `void`, one proof for
+  /// every card left in its hand.
+  fn check_void(
+    &self,
+    seat: usize,
+    suit: Suite,
+    void: Option<&[ExclusionProof]>,
+  ) -> anyhow::Result<()> {
+    let cards = self.cards.as_ref().unwrap();
+    if let Ok(hand) = cards.open_hand(seat) {
+      if hand.iter().any(|card| card.suite == suit) {
+        return Err(GameErrors::MustFollowSuit(seat as u32).into());
+      }
+      return Ok(());
+    }
+
+    let hand = cards.hand(seat);
+    let void = void.ok_or(GameErrors::MustFollowSuit(seat as u32))?;
+    if void.len() != hand.len() {
+      return Err(GameErrors::MustFollowSuit(seat as u32).into());
+    }
+
+    let pk = self.players[seat].0;
+    let excluded = self.suit_cards(suit);
+    for (id, proof) in hand.iter().zip(void) {
+      let tokens: Vec<RevealToken> = cards
+        .reveal_tokens(*id)
+        .iter()
+        .filter(|(_, _, key)| *key != pk)
+        .map(|(token, _, _)| *token)
+        .collect();
+      exclusion::verify_exclusion(
+        self.parameters,
+        &pk,
+        cards.card(*id).unwrap(),
+        &tokens,
+        &excluded,
+        proof,
+      )?;
+    }
+    Ok(())
+  }
+
+  fn take_trick(&mut self) {
+    let trick = self.trick();
+    let winner = trick_winner(&trick, self.game.trump());
+    self.taken[winner].extend(trick.iter().map(|(_, card)| *card));
+    self.trick.clear();
+    self.leader = winner;
+  }
+
+  /// Move through every phase whose requirements are already met.
+  fn advance(&mut self) -> anyhow::Result<()> {
+    loop {
+      match self.phase {
+        Phase::Shuffle if self.shuffles == NUM_OF_PLAYERS => {
+          let deck = std::mem::take(&mut self.deck);
+          let mut cards = DrawDeck::new(
+            self.parameters,
+            self.card_mapping,
+            &self.players,
+            deck,
+            false,
+          )?;
+          for _ in 0..TRICKS {
+            for offset in 1..=NUM_OF_PLAYERS {
+              cards.deal((self.dealer + offset) % NUM_OF_PLAYERS, 1)?;
+            }
+          }
+          self.cards = Some(cards);
+          self.phase = Phase::Deal;
+        }
+        Phase::Deal if self.awaited_tokens().is_empty() => {
+          self.phase = match self.game {
+            TrickGame::Spades => Phase::Bid,
+            _ => Phase::Play,
+          };
+        }
+        Phase::Bid if self.bids.iter().all(Option::is_some) => {
+          self.phase = Phase::Play;
+        }
+        Phase::Play if self.taken.iter().map(Vec::len).sum::<usize>() == 52 => {
+          self.scores = Some(self.score());
+          self.phase = Phase::Complete;
+        }
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  fn score(&self) -> Vec<i64> {
+    let tricks: Vec<usize> = (0..NUM_OF_PLAYERS)
+      .map(|seat| self.tricks_won(seat))
+      .collect();
+
+    match self.game {
+      TrickGame::Hearts => hearts_points(&self.taken),
+      TrickGame::Spades => {
+        let bids: Vec<u8> = self.bids.iter().map(|bid| bid.unwrap_or(0)).collect();
+        spades_scores(&bids, &tricks)
+      }
+      TrickGame::Bridge(contract) => {
+        let partner = (contract.declarer + 2) % NUM_OF_PLAYERS;
+        let score = contract_score(&contract, tricks[contract.declarer] + tricks[partner]);
+        (0..NUM_OF_PLAYERS)
+          .map(|seat| {
+            if seat % 2 == contract.declarer % 2 {
+              score
+            } else {
+              -score
+            }
+          })
+          .collect()
+      }
+    }
+  }
+}
+
+/// Seat that takes `trick`, listed in playing order: the highest trump, or else the highest card
+/// of the suit led.
+fn trick_winner(trick: &[(usize, ClassicPlayingCard)], trump: Option<Suite>) -> usize {
+  let led = trick[0].1.suite;
+  trick
+    .iter()
+    .max_by_key(|(_, card)| (Some(card.suite) == trump, card.suite == led, card.value))
+    .unwrap()
+    .0
+}
+
+fn hearts_points(taken: &[Vec<ClassicPlayingCard>]) -> Vec<i64> {
+  let points: Vec<i64> = taken
+    .iter()
+    .map(|cards| {
+      cards
+        .iter()
+        .map(|card| match (card.value, card.suite) {
+          (_, Suite::Heart) => 1,
+          (Value::Queen, Suite::Spade) => 13,
+          _ => 0,
+        })
+        .sum()
+    })
+    .collect();
+
+  // Shooting the moon
+  if points.contains(&26) {
+    return points
+      .iter()
+      .map(|p| if *p == 26 { 0 } else { 26 })
+      .collect();
+  }
+  points
+}
+
+/// Every partnership makes ten points a trick bid and one for every overtrick if it takes at
+/// least its bid, and loses ten a trick bid otherwise. Tricks taken by either partner count. A
+/// nil bid is worth a hundred points on its own, won if that player takes no trick and lost
+/// otherwise.
+fn spades_scores(bids: &[u8], tricks: &[usize]) -> Vec<i64> {
+  let mut scores = vec![0; NUM_OF_PLAYERS];
+  for seat in 0..NUM_OF_PLAYERS / 2 {
+    let partners = [seat, seat + NUM_OF_PLAYERS / 2];
+    let mut score = 0;
+    for partner in partners {
+      if bids[partner] == 0 {
+        score += if tricks[partner] == 0 { 100 } else { -100 };
+      }
+    }
+    let bid: i64 = partners.iter().map(|partner| bids[*partner] as i64).sum();
+    let taken: i64 = partners.iter().map(|partner| tricks[*partner] as i64).sum();
+    if bid > 0 {
+      score += if taken >= bid {
+        10 * bid + taken - bid
+      } else {
+        -10 * bid
+      };
+    }
+
+    for partner in partners {
+      scores[partner] = score;
+    }
+  }
+  scores
+}
+
+/// Duplicate score of the declaring side, undoubled and not vulnerable: negative when the
+/// contract goes down.
+fn contract_score(contract: &Contract, tricks: usize) -> i64 {
+  let level = contract.level as i64;
+  let needed = 6 + level;
+  let tricks = tricks as i64;
+  if tricks < needed {
+    return -50 * (needed - tricks);
+  }
+
+  let (per_trick, first_trick) = match contract.trump {
+    Some(Suite::Club) | Some(Suite::Diamond) => (20, 0),
+    Some(Suite::Heart) | Some(Suite::Spade) => (30, 0),
+    None => (30, 10),
+  };
+  let contracted = per_trick * level + first_trick;
+  let overtricks = per_trick * (tricks - needed);
+  let bonus = if contracted >= 100 { 300 } else { 50 };
+  let slam = match level {
+    6 => 500,
+    7 => 1000,
+    _ => 0,
+  };
+
+  contracted + overtricks + bonus + slam
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{key_info, run_protocol, setup, Protocol, Step};
+
+  use rand::thread_rng;
+
+  impl Protocol for TrickHand<'_> {
+    fn step(&self) -> Option<Step> {
+      match self.awaiting().first().copied()? {
+        Awaiting::Shuffle { seat } => Some(Step::Shuffle { seat }),
+        Awaiting::RevealToken { seat, card } => Some(Step::RevealToken { seat, card }),
+        _ => None,
+      }
+    }
+
+    fn parameters(&self) -> &CardParameters {
+      self.parameters
+    }
+
+    fn card(&self, id: usize) -> MaskedCard {
+      *self.cards().unwrap().card(id).unwrap()
+    }
+
+    fn shuffle_as<R: Rng>(&mut self, rng: &mut R, seat: usize) -> anyhow::Result<()> {
+      let (deck, proof) = self.shuffle_deck(rng)?;
+      self.shuffle(seat, deck, &proof)
+    }
+
+    fn add_token(&mut self, seat: usize, id: usize, token: RevealShare) -> anyhow::Result<()> {
+      self.add_reveal_token(seat, id, token)
+    }
+  }
+
+  /// Hand out every shuffle and reveal token the deal is waiting for, and bid three tricks
+  /// each, until a card is to be played or the deal is complete.
+  fn run_to_play<R: Rng>(rng: &mut R, hand: &mut TrickHand, players: &[Player]) {
+    loop {
+      run_protocol(rng, hand, players);
+      match hand.awaiting().first().copied() {
+        Some(Awaiting::Bid { seat }) => hand.bid(seat, 3).unwrap(),
+        _ => return,
+      }
+    }
+  }
+
+  /// The hand of `seat` as its player sees it, from its own reveal tokens.
+  fn peek<R: Rng>(
+    rng: &mut R,
+    hand: &TrickHand,
+    players: &[Player],
+    seat: usize,
+  ) -> Vec<(usize, ClassicPlayingCard)> {
+    let cards = hand.cards().unwrap();
+    cards
+      .hand(seat)
+      .into_iter()
+      .map(|id| {
+        let masked = cards.card(id).unwrap();
+        let mut tokens = cards.reveal_tokens(id);
+        if tokens.len() < NUM_OF_PLAYERS {
+          tokens.push(
+            players[seat]
+              .compute_reveal_token(rng, hand.parameters, masked)
+              .unwrap(),
+          );
+        }
+        let card = open_card(hand.parameters, &tokens, hand.card_mapping, masked).unwrap();
+        (id, card)
+      })
+      .collect()
+  }
+
+  fn play<R: Rng>(
+    rng: &mut R,
+    hand: &mut TrickHand,
+    players: &[Player],
+    seat: usize,
+    id: usize,
+    void: Option<&[ExclusionProof]>,
+  ) -> anyhow::Result<()> {
+    let token = if hand.dummy() == Some(seat) {
+      None
+    } else {
+      let masked = hand.cards().unwrap().card(id).unwrap();
+      Some(players[seat].compute_reveal_token(rng, hand.parameters, masked)?)
+    };
+    hand.play(seat, id, token, void)
+  }
+
+  /// Play out the deal, every seat following suit with its first card of the suit led when it
+  /// can and proving it is void otherwise.
+  fn play_deal<R: Rng>(rng: &mut R, hand: &mut TrickHand, players: &[Player]) {
+    loop {
+      run_to_play(rng, hand, players);
+      let seat = match hand.to_play() {
+        Some(seat) => seat,
+        None => return,
+      };
+
+      let cards = peek(rng, hand, players, seat);
+      match hand.led_suit() {
+        None => play(rng, hand, players, seat, cards[0].0, None).unwrap(),
+        Some(led) => match cards.iter().find(|(_, card)| card.suite == led) {
+          Some((id, _)) => play(rng, hand, players, seat, *id, None).unwrap(),
+          None if hand.dummy() == Some(seat) => {
+            play(rng, hand, players, seat, cards[0].0, None).unwrap()
+          }
+          None => {
+            let void = hand.prove_void(rng, &players[seat], seat).unwrap();
+            play(rng, hand, players, seat, cards[0].0, Some(&void)).unwrap();
+          }
+        },
+      }
+    }
+  }
+
+  fn card(value: Value, suite: Suite) -> ClassicPlayingCard {
+    ClassicPlayingCard::new(value, suite)
+  }
+
+  #[test]
+  fn takes_tricks_with_trump_or_the_suit_led() {
+    use Suite::*;
+    use Value::*;
+
+    let trick = [
+      (2, card(Ten, Heart)),
+      (3, card(Ace, Club)),
+      (0, card(Queen, Heart)),
+      (1, card(Two, Spade)),
+    ];
+    // Off-suit aces take nothing
+    assert_eq!(trick_winner(&trick, None), 0);
+    assert_eq!(trick_winner(&trick, Some(Diamond)), 0);
+    // The lowest trump beats the suit led
+    assert_eq!(trick_winner(&trick, Some(Spade)), 1);
+    assert_eq!(trick_winner(&trick, Some(Club)), 3);
+  }
+
+  #[test]
+  fn scores_hearts_spades_and_bridge() {
+    use Suite::*;
+
+    let mut taken = vec![vec![]; NUM_OF_PLAYERS];
+    taken[0] = vec![card(Value::Queen, Spade), card(Value::Two, Heart)];
+    taken[1] = vec![card(Value::Ace, Heart), card(Value::Ace, Club)];
+    assert_eq!(hearts_points(&taken), vec![14, 1, 0, 0]);
+    let moon: Vec<ClassicPlayingCard> = Value::VALUES
+      .iter()
+      .map(|value| card(*value, Heart))
+      .chain([card(Value::Queen, Spade)])
+      .collect();
+    assert_eq!(
+      hearts_points(&[moon, vec![], vec![], vec![]]),
+      vec![0, 26, 26, 26]
+    );
+
+    // North and South bid 6 and take 7, East makes nil and West takes 6 on a bid of 4
+    assert_eq!(
+      spades_scores(&[4, 0, 2, 4], &[5, 0, 2, 6]),
+      vec![61, 142, 61, 142]
+    );
+    // A failed nil still counts its tricks towards the partner's bid
+    assert_eq!(
+      spades_scores(&[4, 0, 2, 4], &[4, 1, 2, 6]),
+      vec![60, -57, 60, -57]
+    );
+    assert_eq!(
+      spades_scores(&[4, 0, 2, 5], &[4, 0, 2, 3]),
+      vec![60, 50, 60, 50]
+    );
+
+    let contract = |level, trump| Contract {
+      level,
+      trump,
+      declarer: 0,
+    };
+    assert_eq!(contract_score(&contract(4, Some(Spade)), 10), 420);
+    assert_eq!(contract_score(&contract(3, None), 10), 430);
+    assert_eq!(contract_score(&contract(2, Some(Club)), 8), 90);
+    assert_eq!(contract_score(&contract(6, Some(Heart)), 12), 980);
+    assert_eq!(contract_score(&contract(7, None), 13), 1520);
+    assert_eq!(contract_score(&contract(4, Some(Heart)), 7), -150);
+  }
+
+  #[test]
+  fn plays_a_deal_of_hearts() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, NUM_OF_PLAYERS);
+    assert!(TrickHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players[..3]),
+      0,
+      TrickGame::Hearts,
+    )
+    .is_err());
+
+    let mut hand = TrickHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      0,
+      TrickGame::Hearts,
+    )
+    .unwrap();
+    run_to_play(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Play);
+    assert_eq!(hand.to_play(), Some(1));
+
+    // The owner's token must come with a proof for the card played
+    let lead = peek(rng, &hand, &players, 1)[0];
+    let cards = hand.cards().unwrap();
+    let (token, _, pk) = players[1]
+      .compute_reveal_token(rng, &parameters, cards.card(lead.0).unwrap())
+      .unwrap();
+    let other = cards.hand(1)[1];
+    let (_, proof, _) = players[1]
+      .compute_reveal_token(rng, &parameters, cards.card(other).unwrap())
+      .unwrap();
+    assert!(hand
+      .play(1, lead.0, Some((token, proof, pk)), None)
+      .is_err());
+    assert!(hand.trick().is_empty());
+    assert_eq!(hand.to_play(), Some(1));
+    let cards = hand.cards().unwrap();
+    assert_eq!(cards.zone(lead.0), Some(Zone::Hand(1)));
+    assert_eq!(cards.hand(1).len(), 13);
+    assert_eq!(cards.reveal_tokens(lead.0).len(), NUM_OF_PLAYERS - 1);
+
+    // Nobody can play off suit while holding the suit led, with or without a proof
+    play(rng, &mut hand, &players, 1, lead.0, None).unwrap();
+    assert_eq!(hand.led_suit(), Some(lead.1.suite));
+    let cards = peek(rng, &hand, &players, 2);
+    let following = cards.iter().find(|(_, card)| card.suite == lead.1.suite);
+    let off_suit = cards.iter().find(|(_, card)| card.suite != lead.1.suite);
+    if let (Some(_), Some((id, _))) = (following, off_suit) {
+      let err = play(rng, &mut hand, &players, 2, *id, None).unwrap_err();
+      assert_eq!(
+        err.downcast::<GameErrors>().unwrap(),
+        GameErrors::MustFollowSuit(2)
+      );
+      assert!(hand.prove_void(rng, &players[2], 2).is_err());
+      assert_eq!(hand.trick().len(), 1);
+      assert_eq!(hand.cards().unwrap().hand(2).len(), 13);
+    }
+
+    play_deal(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+    let tricks: usize = (0..NUM_OF_PLAYERS).map(|seat| hand.tricks_won(seat)).sum();
+    assert_eq!(tricks, 13);
+    let points: i64 = hand.scores().unwrap().iter().sum();
+    assert!(points == 26 || points == 78);
+  }
+
+  #[test]
+  fn the_dummy_opens_after_the_opening_lead() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, NUM_OF_PLAYERS);
+    let contract = Contract {
+      level: 4,
+      trump: Some(Suite::Spade),
+      declarer: 0,
+    };
+    let mut hand = TrickHand::new(
+      rng,
+      &parameters,
+      &card_mapping,
+      &key_info(&players),
+      3,
+      TrickGame::Bridge(contract),
+    )
+    .unwrap();
+    run_to_play(rng, &mut hand, &players);
+
+    // West of the declarer leads, and only then does the dummy lay down its hand
+    assert_eq!(hand.to_play(), Some(1));
+    assert!(hand.cards().unwrap().open_hand(2).is_err());
+    let lead = peek(rng, &hand, &players, 1)[0].0;
+    play(rng, &mut hand, &players, 1, lead, None).unwrap();
+    assert!(matches!(
+      hand.awaiting()[0],
+      Awaiting::RevealToken { seat: 2, .. }
+    ));
+    run_to_play(rng, &mut hand, &players);
+    assert_eq!(hand.cards().unwrap().open_hand(2).unwrap().len(), 13);
+    assert_eq!(hand.to_play(), Some(2));
+
+    play_deal(rng, &mut hand, &players);
+    assert_eq!(hand.phase(), Phase::Complete);
+    let scores = hand.scores().unwrap();
+    let declared = hand.tricks_won(0) + hand.tricks_won(2);
+    assert_eq!(scores[0], contract_score(&contract, declared));
+    assert_eq!(scores[0], scores[2]);
+    assert_eq!(scores[1], -scores[0]);
+    assert_eq!(scores[3], -scores[0]);
+  }
+}
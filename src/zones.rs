@@ -0,0 +1,583 @@
+//! Decks, zones and visibility, to write a card game as rules over where its cards are and who
+//! can see them.
+//!
+//! `Deck` is the public state of every masked card of a game that the players keep in sync. A
+//! card sits in a `Zone` (the deck itself, the hand of a seat, the board, the cards a seat played,
+//! the discard pile or the burned cards) and has a `Visibility`: face down to everyone, known to
+//! some seats, or public. Moving a card returns the protocol steps the move takes, for the
+//! players to carry out in order:
+//!
+//! - a card leaving a hand face down for another hand is remasked by every seat in turn,
+//!   starting left of its former holder, so that no reveal token sent for it before opens it;
+//! - a seat that gets to know a card peeks at it with the reveal tokens of the other seats. For a
+//!   card in the hand of the only seat that knows it, or in the deck it searches, they publish them
+//!   with `add_reveal_token`, since the card still needs the token of that seat to open; otherwise
+//!   they send them to it privately;
+//! - a card that becomes public is opened with the reveal tokens of every seat, which they
+//!   publish with `add_reveal_token`.
+//!
+//! Nothing seen is forgotten: a public card stays public and a seat that knows a card keeps
+//! knowing it, wherever the card goes next. Cards can also enter the game after it starts, such
+//! as a reshuffled discard pile, or a remask of a card played from a hand that keeps the original.
+//! Since the original can still be played too, a remask and any other card that open to the same
+//! card are copies, and whichever opens second is refused.
+
+use crate::{
+  Card, CardParameters, CardProtocol, GameErrors, MaskedCard, ProofKeyOwnership, PublicKey,
+  RemaskingProof, RevealShare, Scalar,
+};
+
+use ark_ff::UniformRand;
+use ark_std::rand::Rng;
+use barnett_smart_protocol::BarnettSmartProtocol;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Zone {
+  /// The cards left to deal, in order.
+  Deck,
+  Hand(usize),
+  Board,
+  /// Played face up by the seat, as to a trick.
+  Played(usize),
+  Discard,
+  Burn,
+  /// Shuffled back into the deck under a new id.
+  Reshuffled,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Visibility {
+  FaceDown,
+  /// Known to the listed seats only.
+  KnownTo(Vec<usize>),
+  Public,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Step {
+  /// `seat` remasks the card with `remask_card`, and everyone adopts it with `apply_remask`.
+  Remask { id: usize, seat: usize },
+  /// Every other seat sends `seat` its reveal token for the card.
+  Peek { id: usize, seat: usize },
+  /// Every seat publishes its reveal token for the card with `add_reveal_token`.
+  Reveal { id: usize },
+}
+
+pub struct Deck<'a, T> {
+  parameters: &'a CardParameters,
+  card_mapping: &'a HashMap<Card, T>,
+  joint_pk: PublicKey,
+  public_keys: Vec<PublicKey>,
+  cards: Vec<MaskedCard>,
+  zones: Vec<Zone>,
+  // When each card entered its zone, to keep the cards of a zone in order
+  arrivals: Vec<usize>,
+  next_arrival: usize,
+  visibility: Vec<Visibility>,
+  opened: Vec<Option<T>>,
+  plaintexts: Vec<Option<Card>>,
+  // Cards added as remasks of cards still in play
+  copies: Vec<usize>,
+  // Reveal tokens received for each card, indexed by card then seat
+  tokens: Vec<Vec<Option<RevealShare>>>,
+  // Seats that still owe a remask of a card, in the order they make it
+  remasks: HashMap<usize, VecDeque<usize>>,
+}
+
+impl<'a, T: Copy> Deck<'a, T> {
+  /// Start a game on `deck`, once every one of `players` has shuffled it. Players are listed in
+  /// seat order with their proofs of key ownership, and every card starts face down in
+  /// `Zone::Deck`, the first card on top.
+  pub fn new(
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, T>,
+    players: &[(PublicKey, ProofKeyOwnership, Vec<u8>)],
+    deck: Vec<MaskedCard>,
+  ) -> anyhow::Result<Self> {
+    let joint_pk = CardProtocol::compute_aggregate_key(parameters, &players.to_vec())?;
+    let num_of_players = players.len();
+    let num_of_cards = deck.len();
+    Ok(Self {
+      parameters,
+      card_mapping,
+      joint_pk,
+      public_keys: players.iter().map(|(pk, _, _)| *pk).collect(),
+      cards: deck,
+      zones: vec![Zone::Deck; num_of_cards],
+      arrivals: (0..num_of_cards).collect(),
+      next_arrival: num_of_cards,
+      visibility: vec![Visibility::FaceDown; num_of_cards],
+      opened: vec![None; num_of_cards],
+      plaintexts: vec![None; num_of_cards],
+      copies: vec![],
+      tokens: vec![vec![None; num_of_players]; num_of_cards],
+      remasks: HashMap::new(),
+    })
+  }
+
+  pub fn num_of_players(&self) -> usize {
+    self.public_keys.len()
+  }
+
+  /// The aggregate key the cards are masked with.
+  pub fn joint_pk(&self) -> &PublicKey {
+    &self.joint_pk
+  }
+
+  pub fn card(&self, id: usize) -> Option<&MaskedCard> {
+    self.cards.get(id)
+  }
+
+  pub fn zone(&self, id: usize) -> Option<Zone> {
+    self.zones.get(id).copied()
+  }
+
+  pub fn visibility(&self, id: usize) -> Option<&Visibility> {
+    self.visibility.get(id)
+  }
+
+  /// The card `id` once it is public and all its reveal tokens are in.
+  pub fn opened_card(&self, id: usize) -> Option<T> {
+    self.opened.get(id).copied().flatten()
+  }
+
+  /// The id of `card`, if it is in the game.
+  pub fn find(&self, card: &MaskedCard) -> Option<usize> {
+    self.cards.iter().position(|other| other == card)
+  }
+
+  /// The cards in `zone`, in the order they entered it.
+  pub fn cards_in(&self, zone: Zone) -> Vec<usize> {
+    let mut ids: Vec<usize> = (0..self.zones.len())
+      .filter(|id| self.zones[*id] == zone)
+      .collect();
+    ids.sort_by_key(|id| self.arrivals[*id]);
+    ids
+  }
+
+  /// Move the card `id` to the end of `zone`, adding `visibility` to what is already known of it.
+  /// Returns the steps the move takes.
+  pub fn move_card(
+    &mut self,
+    id: usize,
+    zone: Zone,
+    visibility: Visibility,
+  ) -> anyhow::Result<Vec<Step>> {
+    self
+      .card(id)
+      .ok_or(GameErrors::InvalidCardIndex(id as u32))?;
+    if self.remasks.contains_key(&id) {
+      return Err(GameErrors::IllegalAction("move during a remask").into());
+    }
+    if let Zone::Hand(seat) = zone {
+      self.check_seat(seat)?;
+    }
+    if let Visibility::KnownTo(seats) = &visibility {
+      for seat in seats {
+        self.check_seat(*seat)?;
+      }
+    }
+
+    let known = match (&self.visibility[id], visibility) {
+      (Visibility::Public, _) | (_, Visibility::Public) => Visibility::Public,
+      (Visibility::KnownTo(old), Visibility::KnownTo(new)) => {
+        let mut seats: Vec<usize> = old.iter().chain(&new).copied().collect();
+        seats.sort_unstable();
+        seats.dedup();
+        Visibility::KnownTo(seats)
+      }
+      (Visibility::KnownTo(old), Visibility::FaceDown) => Visibility::KnownTo(old.clone()),
+      (Visibility::FaceDown, Visibility::KnownTo(mut new)) => {
+        new.sort_unstable();
+        new.dedup();
+        Visibility::KnownTo(new)
+      }
+      (Visibility::FaceDown, Visibility::FaceDown) => Visibility::FaceDown,
+    };
+
+    let mut steps = vec![];
+    match (self.zones[id], zone) {
+      (Zone::Hand(from), Zone::Hand(to)) if from != to && known != Visibility::Public => {
+        let num_of_seats = self.num_of_players();
+        let seats: VecDeque<usize> = (1..=num_of_seats)
+          .map(|i| (from + i) % num_of_seats)
+          .collect();
+        steps.extend(seats.iter().map(|seat| Step::Remask { id, seat: *seat }));
+        self.remasks.insert(id, seats);
+      }
+      _ => {}
+    }
+    match (&self.visibility[id], &known) {
+      (_, Visibility::Public) if self.opened[id].is_none() => steps.push(Step::Reveal { id }),
+      (old, Visibility::KnownTo(seats)) => steps.extend(
+        seats
+          .iter()
+          .filter(|seat| !matches!(old, Visibility::KnownTo(old) if old.contains(*seat)))
+          .map(|seat| Step::Peek { id, seat: *seat }),
+      ),
+      _ => {}
+    }
+
+    self.zones[id] = zone;
+    self.arrivals[id] = self.next_arrival;
+    self.next_arrival += 1;
+    self.visibility[id] = known;
+    Ok(steps)
+  }
+
+  /// Add `card` face down at the end of `zone`, for a card that enters the game after it starts.
+  /// Returns its id.
+  pub fn add_card(&mut self, card: MaskedCard, zone: Zone) -> anyhow::Result<usize> {
+    if let Zone::Hand(seat) = zone {
+      self.check_seat(seat)?;
+    }
+
+    let id = self.cards.len();
+    self.cards.push(card);
+    self.zones.push(zone);
+    self.arrivals.push(self.next_arrival);
+    self.next_arrival += 1;
+    self.visibility.push(Visibility::FaceDown);
+    self.opened.push(None);
+    self.plaintexts.push(None);
+    self.tokens.push(vec![None; self.num_of_players()]);
+    Ok(id)
+  }
+
+  /// Add `card` like `add_card`, as a remask of a card that stays where it is, such as a card
+  /// played from a hand without telling which. It is refused when it opens if a card opened
+  /// before to the same card, and so is any card that opens to the same card as it after it.
+  pub fn add_remask(&mut self, card: MaskedCard, zone: Zone) -> anyhow::Result<usize> {
+    let id = self.add_card(card, zone)?;
+    self.copies.push(id);
+    Ok(id)
+  }
+
+  /// Deal `count` cards from the top of the deck to the hand of `seat`, for it to peek at.
+  pub fn deal(&mut self, seat: usize, count: usize) -> anyhow::Result<Vec<Step>> {
+    self.deal_as(seat, count, Visibility::FaceDown)
+  }
+
+  /// Deal `count` cards from the top of the deck to the hand of `seat`, which peeks at them,
+  /// showing them to the seats `visibility` adds.
+  pub fn deal_as(
+    &mut self,
+    seat: usize,
+    count: usize,
+    visibility: Visibility,
+  ) -> anyhow::Result<Vec<Step>> {
+    self.check_seat(seat)?;
+    let known = match visibility {
+      Visibility::FaceDown => Visibility::KnownTo(vec![seat]),
+      Visibility::KnownTo(mut seats) => {
+        seats.push(seat);
+        Visibility::KnownTo(seats)
+      }
+      Visibility::Public => Visibility::Public,
+    };
+    self.move_from_deck(count, Zone::Hand(seat), known)
+  }
+
+  /// Deal `count` cards from the top of the deck to the board, face up.
+  pub fn deal_to_board(&mut self, count: usize) -> anyhow::Result<Vec<Step>> {
+    self.move_from_deck(count, Zone::Board, Visibility::Public)
+  }
+
+  /// Burn `count` cards from the top of the deck, unseen.
+  pub fn burn(&mut self, count: usize) -> anyhow::Result<Vec<Step>> {
+    self.move_from_deck(count, Zone::Burn, Visibility::FaceDown)
+  }
+
+  /// Throw the cards `ids` from the hand of `seat` away, without showing them.
+  pub fn discard(&mut self, seat: usize, ids: &[usize]) -> anyhow::Result<Vec<Step>> {
+    for (i, id) in ids.iter().enumerate() {
+      if self.zone(*id) != Some(Zone::Hand(seat)) || ids[..i].contains(id) {
+        return Err(GameErrors::CardNotFound.into());
+      }
+    }
+
+    let mut steps = vec![];
+    for id in ids {
+      steps.extend(self.move_card(*id, Zone::Discard, Visibility::FaceDown)?);
+    }
+    Ok(steps)
+  }
+
+  /// The seat that remasks the card `id` next, if it is being remasked.
+  pub fn next_remasker(&self, id: usize) -> Option<usize> {
+    self
+      .remasks
+      .get(&id)
+      .and_then(|seats| seats.front().copied())
+  }
+
+  /// Remask the card `id`, as its next remasker does locally before sending it to the others.
+  pub fn remask_card<R: Rng>(
+    &self,
+    rng: &mut R,
+    id: usize,
+  ) -> anyhow::Result<(MaskedCard, RemaskingProof)> {
+    let card = self
+      .card(id)
+      .ok_or(GameErrors::InvalidCardIndex(id as u32))?;
+    let alpha = Scalar::rand(rng);
+    Ok(CardProtocol::remask(
+      rng,
+      self.parameters,
+      &self.joint_pk,
+      card,
+      &alpha,
+    )?)
+  }
+
+  /// Verify and adopt the remask of the card `id` by `seat`.
+  pub fn apply_remask(
+    &mut self,
+    seat: usize,
+    id: usize,
+    card: MaskedCard,
+    proof: &RemaskingProof,
+  ) -> anyhow::Result<()> {
+    if self.next_remasker(id) != Some(seat) {
+      return Err(GameErrors::NotYourTurn(seat as u32).into());
+    }
+    CardProtocol::verify_remask(
+      self.parameters,
+      &self.joint_pk,
+      &self.cards[id],
+      &card,
+      proof,
+    )?;
+
+    self.cards[id] = card;
+    self.tokens[id] = vec![None; self.num_of_players()];
+    let seats = self.remasks.get_mut(&id).unwrap();
+    seats.pop_front();
+    if seats.is_empty() {
+      self.remasks.remove(&id);
+    }
+    Ok(())
+  }
+
+  /// Pairs of seat and card for which a public reveal token is expected: every token of a public
+  /// card, and those of the other seats for a card in the hand of the only seat that knows it or
+  /// in the deck it searches.
+  pub fn awaited_tokens(&self) -> Vec<(usize, usize)> {
+    (0..self.cards.len())
+      .filter(|id| !self.remasks.contains_key(id))
+      .flat_map(|id| {
+        let holder = match (&self.visibility[id], self.zones[id]) {
+          (Visibility::Public, _) if self.opened[id].is_none() => None,
+          (Visibility::KnownTo(seats), Zone::Hand(seat)) if seats[..] == [seat] => Some(seat),
+          (Visibility::KnownTo(seats), Zone::Deck) if seats.len() == 1 => Some(seats[0]),
+          _ => return vec![],
+        };
+        (0..self.num_of_players())
+          .filter(|seat| Some(*seat) != holder && self.tokens[id][*seat].is_none())
+          .map(|seat| (seat, id))
+          .collect()
+      })
+      .collect()
+  }
+
+  pub fn add_reveal_token(
+    &mut self,
+    seat: usize,
+    id: usize,
+    token: RevealShare,
+  ) -> anyhow::Result<()> {
+    if !self.awaited_tokens().contains(&(seat, id)) || token.2 != self.public_keys[seat] {
+      return Err(GameErrors::UnexpectedRevealToken(seat as u32, id as u32).into());
+    }
+
+    CardProtocol::verify_reveal(
+      self.parameters,
+      &token.2,
+      &token.0,
+      &self.cards[id],
+      &token.1,
+    )?;
+
+    self.tokens[id][seat] = Some(token);
+    if self.tokens[id].iter().all(Option::is_some) {
+      let tokens = self.reveal_tokens(id);
+      let plaintext = CardProtocol::unmask(self.parameters, &tokens, &self.cards[id])?;
+      let opened = *self
+        .card_mapping
+        .get(&plaintext)
+        .ok_or(GameErrors::InvalidCard)?;
+      // A reshuffled card comes back under a new id, it is not a copy of itself
+      let copied = (0..self.cards.len()).any(|other| {
+        self.plaintexts[other] == Some(plaintext)
+          && self.zones[other] != Zone::Reshuffled
+          && (self.copies.contains(&id) || self.copies.contains(&other))
+      });
+      if copied {
+        return Err(GameErrors::CardAlreadyPlayed(id as u32).into());
+      }
+      self.opened[id] = Some(opened);
+      self.plaintexts[id] = Some(plaintext);
+    }
+    Ok(())
+  }
+
+  /// Verified reveal tokens received so far for the card `id`. The only seat that knows a card
+  /// in its hand combines them with its own token to peek at it.
+  pub fn reveal_tokens(&self, id: usize) -> Vec<RevealShare> {
+    self
+      .tokens
+      .get(id)
+      .map(|tokens| tokens.iter().flatten().cloned().collect())
+      .unwrap_or_default()
+  }
+
+  fn move_from_deck(
+    &mut self,
+    count: usize,
+    zone: Zone,
+    visibility: Visibility,
+  ) -> anyhow::Result<Vec<Step>> {
+    let deck = self.cards_in(Zone::Deck);
+    if count > deck.len() {
+      return Err(GameErrors::NotEnoughCards(deck.len()).into());
+    }
+
+    let mut steps = vec![];
+    for id in &deck[..count] {
+      steps.extend(self.move_card(*id, zone, visibility.clone())?);
+    }
+    Ok(steps)
+  }
+
+  fn check_seat(&self, seat: usize) -> Result<(), GameErrors> {
+    if seat < self.num_of_players() {
+      Ok(())
+    } else {
+      Err(GameErrors::InvalidSeat(seat as u32))
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{deliver_tokens, key_info, peek, setup, shuffled_deck};
+  use crate::{ClassicPlayingCard, Player};
+
+  use rand::thread_rng;
+
+  fn new_deck<'a, R: Rng>(
+    rng: &mut R,
+    parameters: &'a CardParameters,
+    card_mapping: &'a HashMap<Card, ClassicPlayingCard>,
+    players: &[Player],
+  ) -> Deck<'a, ClassicPlayingCard> {
+    let deck = shuffled_deck(rng, parameters, card_mapping, players);
+    Deck::new(parameters, card_mapping, &key_info(players), deck).unwrap()
+  }
+
+  #[test]
+  fn moves_emit_the_steps_they_take() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, 3);
+    let mut deck = new_deck(rng, &parameters, &card_mapping, &players);
+
+    assert_eq!(
+      deck.deal(0, 2).unwrap(),
+      vec![Step::Peek { id: 0, seat: 0 }, Step::Peek { id: 1, seat: 0 }]
+    );
+    assert!(deck.burn(1).unwrap().is_empty());
+    assert_eq!(deck.deal_to_board(1).unwrap(), vec![Step::Reveal { id: 3 }]);
+    assert_eq!(deck.cards_in(Zone::Deck)[0], 4);
+    assert_eq!(deck.visibility(2), Some(&Visibility::FaceDown));
+    assert_eq!(deck.awaited_tokens().len(), 7);
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    assert!(deck.opened_card(3).is_some());
+    assert_eq!(deck.reveal_tokens(0).len(), 2);
+    let token = players[0]
+      .compute_reveal_token(rng, &parameters, deck.card(0).unwrap())
+      .unwrap();
+    assert!(deck.add_reveal_token(0, 0, token).is_err());
+
+    // Passing a card to another hand remasks it all round the table before seat 1 peeks
+    let passed = peek(
+      rng,
+      &parameters,
+      &card_mapping,
+      &players,
+      deck.card(0).unwrap(),
+    );
+    let original = *deck.card(0).unwrap();
+    assert_eq!(
+      deck
+        .move_card(0, Zone::Hand(1), Visibility::KnownTo(vec![1]))
+        .unwrap(),
+      vec![
+        Step::Remask { id: 0, seat: 1 },
+        Step::Remask { id: 0, seat: 2 },
+        Step::Remask { id: 0, seat: 0 },
+        Step::Peek { id: 0, seat: 1 },
+      ]
+    );
+    assert!(deck.move_card(0, Zone::Board, Visibility::Public).is_err());
+    let (card, proof) = deck.remask_card(rng, 0).unwrap();
+    assert!(deck.apply_remask(2, 0, card, &proof).is_err());
+    for seat in [1, 2, 0] {
+      let (card, proof) = deck.remask_card(rng, 0).unwrap();
+      deck.apply_remask(seat, 0, card, &proof).unwrap();
+    }
+    assert_eq!(deck.next_remasker(0), None);
+    assert_ne!(*deck.card(0).unwrap(), original);
+    assert_eq!(
+      peek(
+        rng,
+        &parameters,
+        &card_mapping,
+        &players,
+        deck.card(0).unwrap(),
+      ),
+      passed
+    );
+    assert_eq!(deck.visibility(0), Some(&Visibility::KnownTo(vec![0, 1])));
+    assert_eq!(deck.cards_in(Zone::Hand(1)), vec![0]);
+    assert!(deck.reveal_tokens(0).is_empty());
+    assert!(deck.awaited_tokens().is_empty());
+  }
+
+  #[test]
+  fn public_cards_stay_public() {
+    let rng = &mut thread_rng();
+    let (parameters, card_mapping, players) = setup(rng, 3);
+    let mut deck = new_deck(rng, &parameters, &card_mapping, &players);
+
+    deck.deal(2, 3).unwrap();
+    assert!(deck.discard(1, &[0]).is_err());
+    assert!(deck.discard(2, &[0, 0]).is_err());
+    assert!(deck.discard(2, &[0]).unwrap().is_empty());
+    assert_eq!(deck.visibility(0), Some(&Visibility::KnownTo(vec![2])));
+
+    // Showing a card to one more seat only needs that seat to peek
+    assert_eq!(
+      deck
+        .move_card(1, Zone::Hand(2), Visibility::KnownTo(vec![0, 2]))
+        .unwrap(),
+      vec![Step::Peek { id: 1, seat: 0 }]
+    );
+
+    assert_eq!(
+      deck.move_card(2, Zone::Board, Visibility::Public).unwrap(),
+      vec![Step::Reveal { id: 2 }]
+    );
+    deliver_tokens(rng, &parameters, &mut deck, &players);
+    let opened = deck.opened_card(2).unwrap();
+    assert!(deck
+      .move_card(2, Zone::Discard, Visibility::FaceDown)
+      .unwrap()
+      .is_empty());
+    assert_eq!(deck.visibility(2), Some(&Visibility::Public));
+    assert!(deck.opened_card(2) == Some(opened));
+    assert_eq!(deck.cards_in(Zone::Discard), vec![0, 2]);
+    assert!(deck.deal(0, 50).is_err());
+  }
+}